@@ -0,0 +1,711 @@
+//! Generates the registration list for the natives of `RustView` and the
+//! bits of `ViewCallbacks` from the tables below, and checks the `native`
+//! declarations and `CALLBACK_*` constants in `RustView.java` and the
+//! forwarding methods in `RustInputConnection.java` against them. The
+//! registration list casts each `extern "system"` function to the function
+//! pointer type of its Java signature, so the Rust side is checked by the
+//! compiler.
+//!
+//! Set `ANDROID_VIEW_REGENERATE_JAVA=1` to rewrite the generated sections of
+//! the Java files instead of failing when they are out of date.
+
+use std::{env, fmt::Write as _, fs, path::Path};
+
+include!("build/java.rs");
+
+const INPUT_CONNECTION_FILE: &str =
+    "library/src/main/java/org/linebender/android/rustview/RustInputConnection.java";
+const REGENERATE_VAR: &str = "ANDROID_VIEW_REGENERATE_JAVA";
+const BEGIN_MARKER: &str = "    // BEGIN GENERATED NATIVES";
+const END_MARKER: &str = "    // END GENERATED NATIVES";
+const FORWARDS_BEGIN_MARKER: &str = "    // BEGIN GENERATED FORWARDS";
+const FORWARDS_END_MARKER: &str = "    // END GENERATED FORWARDS";
+/// The column limit of google-java-format.
+const MAX_WIDTH: usize = 100;
+
+/// A native method of `RustView`. Every one takes the peer ID as its first
+/// parameter, which is not listed in `params`.
+struct Native {
+    /// The `extern "system"` function in `src/view.rs`.
+    rust: &'static str,
+    /// Natives called from `RustInputConnection` are package-private.
+    private: bool,
+    ret: &'static str,
+    java: &'static str,
+    params: &'static [&'static str],
+    /// The `InputConnection` method that `RustInputConnection` forwards to
+    /// this native, and the arguments it passes after the peer.
+    forward: Option<(&'static str, &'static [&'static str])>,
+}
+
+const fn native(
+    rust: &'static str,
+    ret: &'static str,
+    java: &'static str,
+    params: &'static [&'static str],
+) -> Native {
+    Native {
+        rust,
+        private: true,
+        ret,
+        java,
+        params,
+        forward: None,
+    }
+}
+
+const fn package_native(
+    rust: &'static str,
+    ret: &'static str,
+    java: &'static str,
+    params: &'static [&'static str],
+) -> Native {
+    Native {
+        private: false,
+        ..native(rust, ret, java, params)
+    }
+}
+
+impl Native {
+    const fn forwards(self, method: &'static str, args: &'static [&'static str]) -> Self {
+        Self {
+            forward: Some((method, args)),
+            ..self
+        }
+    }
+}
+
+const NATIVES: &[Native] = &[
+    native("callbacks", "int", "callbacksNative", &[]),
+    native(
+        "on_measure",
+        "int[]",
+        "onMeasureNative",
+        &["int widthSpec", "int heightSpec"],
+    ),
+    native(
+        "on_layout",
+        "void",
+        "onLayoutNative",
+        &[
+            "boolean changed",
+            "int left",
+            "int top",
+            "int right",
+            "int bottom",
+        ],
+    ),
+    native(
+        "on_size_changed",
+        "void",
+        "onSizeChangedNative",
+        &["int w", "int h", "int oldw", "int oldh"],
+    ),
+    native(
+        "on_apply_window_insets",
+        "int[]",
+        "onApplyWindowInsetsNative",
+        &["int[] insets"],
+    ),
+    native(
+        "on_configuration_changed",
+        "void",
+        "onConfigurationChangedNative",
+        &["Configuration newConfig"],
+    ),
+    native(
+        "on_display_changed",
+        "void",
+        "onDisplayChangedNative",
+        &["Display display"],
+    ),
+    native(
+        "on_key_down",
+        "boolean",
+        "onKeyDownNative",
+        &["int keyCode", "KeyEvent event"],
+    ),
+    native(
+        "on_key_up",
+        "boolean",
+        "onKeyUpNative",
+        &["int keyCode", "KeyEvent event"],
+    ),
+    native(
+        "on_trackball_event",
+        "boolean",
+        "onTrackballEventNative",
+        &["MotionEvent event"],
+    ),
+    native(
+        "on_touch_event",
+        "boolean",
+        "onTouchEventNative",
+        &["MotionEvent event"],
+    ),
+    native(
+        "on_generic_motion_event",
+        "boolean",
+        "onGenericMotionEventNative",
+        &["MotionEvent event"],
+    ),
+    native(
+        "on_hover_event",
+        "boolean",
+        "onHoverEventNative",
+        &["MotionEvent event"],
+    ),
+    native(
+        "on_focus_changed",
+        "void",
+        "onFocusChangedNative",
+        &[
+            "boolean gainFocus",
+            "int direction",
+            "Rect previouslyFocusedRect",
+        ],
+    ),
+    native(
+        "on_window_focus_changed",
+        "void",
+        "onWindowFocusChangedNative",
+        &["boolean hasWindowFocus"],
+    ),
+    native(
+        "on_attached_to_window",
+        "void",
+        "onAttachedToWindowNative",
+        &[],
+    ),
+    native(
+        "on_detached_from_window",
+        "void",
+        "onDetachedFromWindowNative",
+        &[],
+    ),
+    native(
+        "on_window_visibility_changed",
+        "void",
+        "onWindowVisibilityChangedNative",
+        &["int visibility"],
+    ),
+    native(
+        "surface_created",
+        "void",
+        "surfaceCreatedNative",
+        &["SurfaceHolder holder"],
+    ),
+    native(
+        "surface_changed",
+        "void",
+        "surfaceChangedNative",
+        &[
+            "SurfaceHolder holder",
+            "int format",
+            "int width",
+            "int height",
+        ],
+    ),
+    native(
+        "surface_destroyed",
+        "void",
+        "surfaceDestroyedNative",
+        &["SurfaceHolder holder"],
+    ),
+    package_native(
+        "ime_animation_prepare",
+        "void",
+        "imeAnimationPrepareNative",
+        &[],
+    ),
+    package_native(
+        "ime_animation_start",
+        "void",
+        "imeAnimationStartNative",
+        &["int[] bounds", "long durationMillis"],
+    ),
+    package_native(
+        "ime_animation_progress",
+        "void",
+        "imeAnimationProgressNative",
+        &[
+            "int[] insets",
+            "float fraction",
+            "float interpolatedFraction",
+            "float alpha",
+        ],
+    ),
+    package_native("ime_animation_end", "void", "imeAnimationEndNative", &[]),
+    package_native(
+        "do_frame",
+        "void",
+        "doFrameNative",
+        &[
+            "long frameTimeNanos",
+            "long expectedPresentationTimeNanos",
+            "long deadlineNanos",
+            "long frameIntervalNanos",
+        ],
+    ),
+    native("on_timer", "void", "timerNative", &["long token"]),
+    native("run_posted", "void", "runPostedNative", &[]),
+    native(
+        "has_accessibility_node_provider",
+        "boolean",
+        "hasAccessibilityNodeProviderNative",
+        &[],
+    ),
+    native(
+        "create_accessibility_node_info",
+        "AccessibilityNodeInfo",
+        "createAccessibilityNodeInfoNative",
+        &["int virtualViewId"],
+    ),
+    native(
+        "accessibility_find_focus",
+        "AccessibilityNodeInfo",
+        "accessibilityFindFocusNative",
+        &["int virtualViewId"],
+    ),
+    native(
+        "perform_accessibility_action",
+        "boolean",
+        "performAccessibilityActionNative",
+        &["int virtualViewId", "int action", "Bundle arguments"],
+    ),
+    native(
+        "on_create_input_connection",
+        "boolean",
+        "onCreateInputConnectionNative",
+        &["EditorInfo outAttrs"],
+    ),
+    package_native(
+        "get_text_before_cursor",
+        "String",
+        "getTextBeforeCursorNative",
+        &["int n"],
+    )
+    .forwards("CharSequence getTextBeforeCursor(int n, int flags)", &["n"]),
+    package_native(
+        "get_text_after_cursor",
+        "String",
+        "getTextAfterCursorNative",
+        &["int n"],
+    )
+    .forwards("CharSequence getTextAfterCursor(int n, int flags)", &["n"]),
+    package_native("get_selected_text", "String", "getSelectedTextNative", &[])
+        .forwards("CharSequence getSelectedText(int flags)", &[]),
+    package_native(
+        "get_cursor_caps_mode",
+        "int",
+        "getCursorCapsModeNative",
+        &["int reqModes"],
+    )
+    .forwards("int getCursorCapsMode(int reqModes)", &["reqModes"]),
+    package_native(
+        "delete_surrounding_text",
+        "boolean",
+        "deleteSurroundingTextNative",
+        &["int beforeLength", "int afterLength"],
+    )
+    .forwards(
+        "boolean deleteSurroundingText(int beforeLength, int afterLength)",
+        &["beforeLength", "afterLength"],
+    ),
+    package_native(
+        "delete_surrounding_text_in_code_points",
+        "boolean",
+        "deleteSurroundingTextInCodePointsNative",
+        &["int beforeLength", "int afterLength"],
+    )
+    .forwards(
+        "boolean deleteSurroundingTextInCodePoints(int beforeLength, int afterLength)",
+        &["beforeLength", "afterLength"],
+    ),
+    package_native(
+        "set_composing_text",
+        "boolean",
+        "setComposingTextNative",
+        &["String text", "int newCursorPosition"],
+    )
+    .forwards(
+        "boolean setComposingText(CharSequence text, int newCursorPosition)",
+        &["text.toString()", "newCursorPosition"],
+    ),
+    package_native(
+        "set_composing_region",
+        "boolean",
+        "setComposingRegionNative",
+        &["int start", "int end"],
+    )
+    .forwards(
+        "boolean setComposingRegion(int start, int end)",
+        &["start", "end"],
+    ),
+    package_native(
+        "finish_composing_text",
+        "boolean",
+        "finishComposingTextNative",
+        &[],
+    )
+    .forwards("boolean finishComposingText()", &[]),
+    package_native(
+        "commit_text",
+        "boolean",
+        "commitTextNative",
+        &["String text", "int newCursorPosition"],
+    )
+    .forwards(
+        "boolean commitText(CharSequence text, int newCursorPosition)",
+        &["text.toString()", "newCursorPosition"],
+    ),
+    package_native(
+        "set_selection",
+        "boolean",
+        "setSelectionNative",
+        &["int start", "int end"],
+    )
+    .forwards(
+        "boolean setSelection(int start, int end)",
+        &["start", "end"],
+    ),
+    package_native(
+        "perform_editor_action",
+        "boolean",
+        "performEditorActionNative",
+        &["int editorAction"],
+    )
+    .forwards(
+        "boolean performEditorAction(int editorAction)",
+        &["editorAction"],
+    ),
+    package_native(
+        "perform_context_menu_action",
+        "boolean",
+        "performContextMenuActionNative",
+        &["int id"],
+    )
+    .forwards("boolean performContextMenuAction(int id)", &["id"]),
+    package_native("begin_batch_edit", "boolean", "beginBatchEditNative", &[])
+        .forwards("boolean beginBatchEdit()", &[]),
+    package_native("end_batch_edit", "boolean", "endBatchEditNative", &[])
+        .forwards("boolean endBatchEdit()", &[]),
+    package_native(
+        "input_connection_send_key_event",
+        "boolean",
+        "inputConnectionSendKeyEventNative",
+        &["KeyEvent event"],
+    )
+    .forwards("boolean sendKeyEvent(KeyEvent event)", &["event"]),
+    package_native(
+        "input_connection_clear_meta_key_states",
+        "boolean",
+        "inputConnectionClearMetaKeyStatesNative",
+        &["int states"],
+    )
+    .forwards("boolean clearMetaKeyStates(int states)", &["states"]),
+    package_native(
+        "input_connection_report_fullscreen_mode",
+        "boolean",
+        "inputConnectionReportFullscreenModeNative",
+        &["boolean enabled"],
+    )
+    .forwards(
+        "boolean reportFullscreenMode(boolean enabled)",
+        &["enabled"],
+    ),
+    package_native(
+        "request_cursor_updates",
+        "boolean",
+        "requestCursorUpdatesNative",
+        &["int cursorUpdateMode"],
+    )
+    .forwards(
+        "boolean requestCursorUpdates(int cursorUpdateMode)",
+        &["cursorUpdateMode"],
+    ),
+    package_native(
+        "close_input_connection",
+        "void",
+        "closeInputConnectionNative",
+        &[],
+    )
+    .forwards("void closeConnection()", &[]),
+];
+
+/// The bits of `ViewCallbacks`, in order, with the `RustView` methods that
+/// each one gates.
+const CALLBACKS: &[(&str, &[&str])] = &[
+    ("MEASURE", &["onMeasure"]),
+    ("LAYOUT", &["onLayout"]),
+    ("SIZE_CHANGED", &["onSizeChanged"]),
+    ("WINDOW_INSETS", &["onApplyWindowInsets"]),
+    ("IME_ANIMATION", &["WindowInsetsAnimation.Callback"]),
+    ("CONFIGURATION_CHANGED", &["onConfigurationChanged"]),
+    ("DISPLAY_CHANGED", &["onDisplayChanged"]),
+    ("KEY", &["onKeyDown", "onKeyUp"]),
+    ("TRACKBALL", &["onTrackballEvent"]),
+    ("TOUCH", &["onTouchEvent"]),
+    ("GENERIC_MOTION", &["onGenericMotionEvent"]),
+    ("HOVER", &["onHoverEvent"]),
+    ("FOCUS_CHANGED", &["onFocusChanged"]),
+    ("WINDOW_FOCUS_CHANGED", &["onWindowFocusChanged"]),
+    ("ATTACHED_TO_WINDOW", &["onAttachedToWindow"]),
+    ("WINDOW_VISIBILITY_CHANGED", &["onWindowVisibilityChanged"]),
+    (
+        "SURFACE",
+        &["surfaceCreated", "surfaceChanged", "surfaceDestroyed"],
+    ),
+];
+
+/// The Rust type of a parameter or return value of Java type `ty`, with the
+/// lifetime `'a` for references.
+fn rust_type(ty: &str) -> String {
+    match ty {
+        "boolean" => "jni::sys::jboolean".into(),
+        "byte" => "jni::sys::jbyte".into(),
+        "char" => "jni::sys::jchar".into(),
+        "short" => "jni::sys::jshort".into(),
+        "int" => "jni::sys::jint".into(),
+        "long" => "jni::sys::jlong".into(),
+        "float" => "jni::sys::jfloat".into(),
+        "double" => "jni::sys::jdouble".into(),
+        "int[]" => "jni::objects::JIntArray<'a>".into(),
+        _ => format!("{}<'a>", class(ty).2),
+    }
+}
+
+/// Lay out `head`, the comma-separated `items` and `tail` as google-java-format
+/// does: on one line if it fits, else with the items on a continuation line
+/// indented by 8, else with one item on each continuation line.
+fn wrap(indent: usize, head: &str, items: &[&str], tail: &str) -> String {
+    let pad = " ".repeat(indent);
+    let line = format!("{pad}{head}{}{tail}", items.join(", "));
+    if line.len() <= MAX_WIDTH || items.is_empty() {
+        return line;
+    }
+    let continuation = " ".repeat(indent + 8);
+    let items_line = format!("{continuation}{}{tail}", items.join(", "));
+    let items = if items_line.len() <= MAX_WIDTH {
+        items_line
+    } else {
+        let items: Vec<_> = items
+            .iter()
+            .map(|item| format!("{continuation}{item}"))
+            .collect();
+        format!("{}{tail}", items.join(",\n"))
+    };
+    format!("{pad}{head}\n{items}")
+}
+
+impl Native {
+    fn signature(&self) -> String {
+        signature(
+            ["long"]
+                .into_iter()
+                .chain(self.params.iter().map(|p| param_type(p))),
+            self.ret,
+        )
+    }
+
+    /// The declaration as formatted by google-java-format.
+    fn java_declaration(&self) -> String {
+        let visibility = if self.private { "private " } else { "" };
+        let head = format!("{visibility}native {} {}(", self.ret, self.java);
+        let params: Vec<_> = ["long peer"]
+            .into_iter()
+            .chain(self.params.iter().copied())
+            .collect();
+        wrap(4, &head, &params, ");")
+    }
+
+    /// The type of `extern "system"` function that implements the native.
+    fn rust_fn_type(&self) -> String {
+        let params: Vec<_> = ["long"]
+            .into_iter()
+            .chain(self.params.iter().map(|p| param_type(p)))
+            .map(rust_type)
+            .collect();
+        let ret = match self.ret {
+            "void" => String::new(),
+            ret => format!(" -> {}", rust_type(ret)),
+        };
+        format!(
+            "for<'a> extern \"system\" fn(jni::JNIEnv<'a>, crate::View<'a>, {}){ret}",
+            params.join(", ")
+        )
+    }
+
+    /// The `RustInputConnection` method that forwards to the native.
+    fn java_forward(&self) -> Option<String> {
+        let (method, args) = self.forward?;
+        assert_eq!(
+            args.len(),
+            self.params.len(),
+            "`{method}` must pass an argument for each parameter of `{}`",
+            self.java
+        );
+        let (ret, _) = method.split_once(' ').unwrap();
+        let statement = if ret == "void" { "" } else { "return " };
+        let args: Vec<_> = ["getViewPeer()"]
+            .into_iter()
+            .chain(args.iter().copied())
+            .collect();
+        let call = wrap(8, &format!("{statement}mView.{}(", self.java), &args, ");");
+        Some(format!(
+            "    @Override\n    public {method} {{\n{call}\n    }}\n"
+        ))
+    }
+}
+
+fn generate_rust() -> String {
+    let mut out = String::from("vec![\n");
+    for native in NATIVES {
+        writeln!(
+            out,
+            "    NativeMethod {{ name: {:?}.into(), sig: {:?}.into(), fn_ptr: ({} as {}) as *mut c_void }},",
+            native.java,
+            native.signature(),
+            native.rust,
+            native.rust_fn_type(),
+        )
+        .unwrap();
+    }
+    out.push(']');
+    out
+}
+
+fn generate_callbacks() -> String {
+    let mut out = String::from("impl ViewCallbacks {\n");
+    for (bit, (name, methods)) in CALLBACKS.iter().enumerate() {
+        let methods: Vec<_> = methods.iter().map(|method| format!("`{method}`")).collect();
+        let methods = match methods.split_last() {
+            Some((last, [])) => last.clone(),
+            Some((last, rest)) => format!("{} and {last}", rest.join(", ")),
+            None => unreachable!(),
+        };
+        writeln!(out, "    /// Forward {methods} to the peer.").unwrap();
+        writeln!(out, "    pub const {name}: Self = Self(1 << {bit});").unwrap();
+    }
+    writeln!(
+        out,
+        "    const ALL_BITS: u32 = (1 << {}) - 1;",
+        CALLBACKS.len()
+    )
+    .unwrap();
+    out.push('}');
+    out
+}
+
+fn generate_java() -> String {
+    let mut out = format!(
+        "{BEGIN_MARKER}\n    // Generated by build.rs. Edit the table there and rebuild with\n    // {REGENERATE_VAR}=1 to update.\n\n"
+    );
+    for (bit, (name, _)) in CALLBACKS.iter().enumerate() {
+        writeln!(
+            out,
+            "    private static final int CALLBACK_{name} = 1 << {bit};"
+        )
+        .unwrap();
+    }
+    for native in NATIVES {
+        out.push('\n');
+        out.push_str(&native.java_declaration());
+        out.push('\n');
+    }
+    out.push_str(END_MARKER);
+    out
+}
+
+fn generate_forwards() -> String {
+    let mut out = format!(
+        "{FORWARDS_BEGIN_MARKER}\n    // Generated by build.rs. Edit the table there and rebuild with\n    // {REGENERATE_VAR}=1 to update.\n"
+    );
+    for forward in NATIVES.iter().filter_map(Native::java_forward) {
+        out.push('\n');
+        out.push_str(&forward);
+    }
+    out.push_str(FORWARDS_END_MARKER);
+    out
+}
+
+/// Check that the section of the Java file at `dir/file` between `begin` and
+/// `end` matches `generated`, or rewrite it. Returns the source if it matched.
+fn check_section(
+    dir: &Path,
+    file: &str,
+    (begin, end): (&str, &str),
+    generated: &str,
+) -> Option<String> {
+    let path = dir.join(file);
+    let source = fs::read_to_string(&path).unwrap();
+    let region = source
+        .find(begin)
+        .zip(source.find(end))
+        .map(|(b, e)| b..e + end.len());
+    let Some(region) = region else {
+        panic!("{file} has no `{begin}` ... `{end}` section");
+    };
+    if source[region.clone()] == *generated {
+        return Some(source);
+    }
+    if env::var_os(REGENERATE_VAR).is_some() {
+        let mut updated = source;
+        updated.replace_range(region, generated);
+        fs::write(path, updated).unwrap();
+        return None;
+    }
+    panic!(
+        "the generated section of {file} is out of date with build.rs; \
+         rebuild with {REGENERATE_VAR}=1 to update it"
+    );
+}
+
+fn check_java(dir: &Path) {
+    check_section(
+        dir,
+        INPUT_CONNECTION_FILE,
+        (FORWARDS_BEGIN_MARKER, FORWARDS_END_MARKER),
+        &generate_forwards(),
+    );
+    let Some(source) = check_section(dir, JAVA_FILE, (BEGIN_MARKER, END_MARKER), &generate_java())
+    else {
+        return;
+    };
+
+    // Catch natives declared by hand outside the generated section.
+    let expected: Vec<_> = NATIVES
+        .iter()
+        .map(|native| (native.java.to_string(), native.signature()))
+        .collect();
+    for native in parse_java_natives(&source) {
+        if !expected.contains(&native) {
+            panic!(
+                "{JAVA_FILE} declares native `{}` with signature `{}`, which is not in build.rs",
+                native.0, native.1
+            );
+        }
+    }
+}
+
+fn main() {
+    println!("cargo::rerun-if-changed=build.rs");
+    println!("cargo::rerun-if-changed=build/java.rs");
+    println!("cargo::rerun-if-changed={JAVA_FILE}");
+    println!("cargo::rerun-if-changed={INPUT_CONNECTION_FILE}");
+    println!("cargo::rerun-if-env-changed={REGENERATE_VAR}");
+
+    let out_dir = env::var_os("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("natives.rs"), generate_rust()).unwrap();
+    fs::write(
+        Path::new(&out_dir).join("callbacks.rs"),
+        generate_callbacks(),
+    )
+    .unwrap();
+
+    // The Java library may be missing, such as from a published package.
+    let dir = Path::new(&env::var_os("CARGO_MANIFEST_DIR").unwrap()).to_owned();
+    if dir.join(JAVA_FILE).exists() {
+        check_java(&dir);
+    }
+}
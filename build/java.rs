@@ -0,0 +1,113 @@
+// Java types and signatures, shared by `build.rs` and the tests that check
+// `RustView.java` when the output of the build script is cached.
+
+pub(crate) const JAVA_FILE: &str =
+    "library/src/main/java/org/linebender/android/rustview/RustView.java";
+
+/// Classes that appear in the signatures of natives, by their simple name,
+/// with the type that the Rust functions take or return for them.
+const CLASSES: &[(&str, &str, &str)] = &[
+    (
+        "AccessibilityNodeInfo",
+        "android/view/accessibility/AccessibilityNodeInfo",
+        "crate::AccessibilityNodeInfo",
+    ),
+    ("Bundle", "android/os/Bundle", "crate::Bundle"),
+    (
+        "Configuration",
+        "android/content/res/Configuration",
+        "jni::objects::JObject",
+    ),
+    ("Display", "android/view/Display", "crate::Display"),
+    (
+        "EditorInfo",
+        "android/view/inputmethod/EditorInfo",
+        "crate::EditorInfo",
+    ),
+    ("KeyEvent", "android/view/KeyEvent", "crate::KeyEvent"),
+    (
+        "MotionEvent",
+        "android/view/MotionEvent",
+        "crate::MotionEvent",
+    ),
+    ("Rect", "android/graphics/Rect", "crate::Rect"),
+    ("String", "java/lang/String", "jni::objects::JString"),
+    (
+        "SurfaceHolder",
+        "android/view/SurfaceHolder",
+        "crate::SurfaceHolder",
+    ),
+];
+
+fn class(ty: &str) -> &'static (&'static str, &'static str, &'static str) {
+    CLASSES
+        .iter()
+        .find(|(name, ..)| *name == ty)
+        .unwrap_or_else(|| panic!("unknown Java type `{ty}`; add it to CLASSES in build/java.rs"))
+}
+
+fn descriptor(ty: &str) -> String {
+    if let Some(element) = ty.strip_suffix("[]") {
+        return format!("[{}", descriptor(element));
+    }
+    let primitive = match ty {
+        "void" => "V",
+        "boolean" => "Z",
+        "byte" => "B",
+        "char" => "C",
+        "short" => "S",
+        "int" => "I",
+        "long" => "J",
+        "float" => "F",
+        "double" => "D",
+        _ => return format!("L{};", class(ty).1),
+    };
+    primitive.into()
+}
+
+/// The JNI signature of a method, given the types of its parameters.
+fn signature<'a>(params: impl IntoIterator<Item = &'a str>, ret: &str) -> String {
+    let params: String = params.into_iter().map(descriptor).collect();
+    format!("({params}){}", descriptor(ret))
+}
+
+fn param_type(param: &str) -> &str {
+    param.rsplit_once(' ').map_or(param, |(ty, _)| ty.trim())
+}
+
+/// Every `native` method declared in `source`, as its name and JNI signature.
+pub(crate) fn parse_java_natives(source: &str) -> Vec<(String, String)> {
+    let mut code = String::new();
+    let mut rest = source;
+    while let Some(start) = rest.find("/*") {
+        code.push_str(&rest[..start]);
+        rest = rest[start..]
+            .split_once("*/")
+            .map_or("", |(_, after)| after);
+    }
+    code.push_str(rest);
+    let code = code
+        .lines()
+        .map(|line| line.split_once("//").map_or(line, |(code, _)| code))
+        .collect::<Vec<_>>()
+        .join(" ");
+    code.split([';', '{', '}'])
+        .map(|statement| statement.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|statement| statement.split(' ').any(|word| word == "native"))
+        .map(|statement| {
+            let (head, params) = statement
+                .split_once('(')
+                .unwrap_or_else(|| panic!("can't parse native declaration `{statement}`"));
+            let mut words = head.split(' ').rev();
+            let name = words.next().unwrap().to_string();
+            let ret = words.next().unwrap();
+            let params = params.trim_end_matches(')');
+            let params = params
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(param_type);
+            (name, signature(params, ret))
+        })
+        .collect()
+}
@@ -158,6 +158,8 @@ struct DemoViewPeer {
     access_adapter: accesskit_android::Adapter,
     /// Pointer adapter state.
     tap_counter: TapCounter,
+    /// The timer for the next cursor blink.
+    blink_timer: Option<TimerToken>,
 }
 
 impl DemoViewPeer {
@@ -168,19 +170,25 @@ impl DemoViewPeer {
         {
             return;
         }
-        ctx.view.post_frame_callback(&mut ctx.env);
+        ctx.post_frame_callback();
     }
 
-    fn schedule_next_blink(&self, ctx: &mut CallbackCtx) {
+    fn schedule_next_blink(&mut self, ctx: &mut CallbackCtx) {
         if let Some(next_time) = self.editor.next_blink_time() {
             let delay = next_time.duration_since(Instant::now());
-            ctx.view.post_delayed(&mut ctx.env, delay.as_millis() as _);
+            self.blink_timer = Some(ctx.start_timer(delay));
+        }
+    }
+
+    fn cancel_blink(&mut self, ctx: &mut CallbackCtx) {
+        if let Some(token) = self.blink_timer.take() {
+            ctx.cancel_timer(token);
         }
     }
 
     fn update_cursor_state(&mut self, ctx: &mut CallbackCtx, focused: bool) {
         self.last_drawn_generation = Default::default();
-        ctx.view.remove_delayed_callbacks(&mut ctx.env);
+        self.cancel_blink(ctx);
         if focused {
             self.editor.cursor_reset();
             self.schedule_next_blink(ctx);
@@ -330,6 +338,19 @@ impl DemoViewPeer {
 }
 
 impl ViewPeer for DemoViewPeer {
+    fn callbacks(&self) -> ViewCallbacks {
+        ViewCallbacks::KEY
+            | ViewCallbacks::TOUCH
+            | ViewCallbacks::GENERIC_MOTION
+            | ViewCallbacks::HOVER
+            | ViewCallbacks::FOCUS_CHANGED
+            | ViewCallbacks::SURFACE
+    }
+
+    fn frame_source(&self) -> FrameSource {
+        FrameSource::Native
+    }
+
     fn on_key_down<'local>(
         &mut self,
         ctx: &mut CallbackCtx<'local>,
@@ -489,15 +510,19 @@ impl ViewPeer for DemoViewPeer {
         _holder: &SurfaceHolder<'local>,
     ) {
         self.render_surface = None;
-        ctx.view.remove_frame_callback(&mut ctx.env);
-        ctx.view.remove_delayed_callbacks(&mut ctx.env);
+        ctx.remove_frame_callback();
+        self.cancel_blink(ctx);
     }
 
-    fn do_frame(&mut self, ctx: &mut CallbackCtx, _frame_time_nanos: jlong) {
+    fn do_frame(&mut self, ctx: &mut CallbackCtx, _frame: &FrameInfo) {
         self.render(ctx);
     }
 
-    fn delayed_callback(&mut self, ctx: &mut CallbackCtx) {
+    fn on_timer(&mut self, ctx: &mut CallbackCtx, token: TimerToken) {
+        if self.blink_timer != Some(token) {
+            return;
+        }
+        self.blink_timer = None;
         self.editor.cursor_blink();
         self.last_drawn_generation = Default::default();
         self.enqueue_render_if_needed(ctx);
@@ -860,8 +885,9 @@ extern "system" fn new_view_peer<'local>(
         batch_edit_depth: 0,
         access_adapter: Default::default(),
         tap_counter: TapCounter::default(),
+        blink_timer: None,
     };
-    register_view_peer(peer)
+    register_view_peer(peer).id()
 }
 
 /// Symbol run at JNI load time.
@@ -0,0 +1,107 @@
+package org.linebender.android.rustview;
+
+import android.os.Build;
+import android.view.KeyEvent;
+import android.view.MotionEvent;
+
+/** Packs input events into primitive arrays so native code can copy them in a few calls. */
+final class EventSnapshots {
+    /**
+     * Number of axis values stored per pointer per sample, covering AXIS_X to
+     * AXIS_GESTURE_SWIPE_FINGER_COUNT.
+     */
+    static final int AXIS_COUNT = 54;
+
+    /** The axes that exist before API level 34, AXIS_X to AXIS_GENERIC_16. */
+    private static final int LEGACY_AXIS_COUNT = 48;
+
+    private EventSnapshots() {}
+
+    private static long eventTimeNanos(MotionEvent event) {
+        if (Build.VERSION.SDK_INT >= 34) {
+            return event.getEventTimeNanos();
+        }
+        return event.getEventTime() * 1000000L;
+    }
+
+    private static long historicalEventTimeNanos(MotionEvent event, int pos) {
+        if (Build.VERSION.SDK_INT >= 34) {
+            return event.getHistoricalEventTimeNanos(pos);
+        }
+        return event.getHistoricalEventTime(pos) * 1000000L;
+    }
+
+    /**
+     * Returns the scalar fields of a motion event, followed by the id and tool type of each
+     * pointer, followed by the time of each sample, oldest first.
+     */
+    static long[] packMotionEventHeader(MotionEvent event) {
+        int pointerCount = event.getPointerCount();
+        int historySize = event.getHistorySize();
+        long[] result = new long[10 + 2 * pointerCount + historySize + 1];
+        result[0] = event.getDeviceId();
+        result[1] = event.getSource();
+        result[2] = event.getAction();
+        result[3] = event.getActionButton();
+        result[4] = event.getButtonState();
+        result[5] = event.getFlags();
+        result[6] = event.getMetaState();
+        result[7] = event.getDownTime();
+        result[8] = pointerCount;
+        result[9] = historySize;
+        int i = 10;
+        for (int p = 0; p < pointerCount; p++) {
+            result[i++] = event.getPointerId(p);
+            result[i++] = event.getToolType(p);
+        }
+        for (int h = 0; h < historySize; h++) {
+            result[i++] = historicalEventTimeNanos(event, h);
+        }
+        result[i] = eventTimeNanos(event);
+        return result;
+    }
+
+    /**
+     * Returns every axis value of a motion event, laid out as [sample][pointer][axis] with the
+     * oldest historical sample first and the current sample last.
+     */
+    static float[] packMotionEventAxes(MotionEvent event) {
+        int pointerCount = event.getPointerCount();
+        int historySize = event.getHistorySize();
+        // The gesture axes are left as 0 where they don't exist.
+        int axisCount = Build.VERSION.SDK_INT >= 34 ? AXIS_COUNT : LEGACY_AXIS_COUNT;
+        float[] result = new float[(historySize + 1) * pointerCount * AXIS_COUNT];
+        for (int h = 0; h < historySize; h++) {
+            for (int p = 0; p < pointerCount; p++) {
+                int i = (h * pointerCount + p) * AXIS_COUNT;
+                for (int axis = 0; axis < axisCount; axis++) {
+                    result[i + axis] = event.getHistoricalAxisValue(axis, p, h);
+                }
+            }
+        }
+        for (int p = 0; p < pointerCount; p++) {
+            int i = (historySize * pointerCount + p) * AXIS_COUNT;
+            for (int axis = 0; axis < axisCount; axis++) {
+                result[i + axis] = event.getAxisValue(axis, p);
+            }
+        }
+        return result;
+    }
+
+    /** Returns the fields of a key event. */
+    static long[] packKeyEvent(KeyEvent event) {
+        return new long[] {
+            event.getDeviceId(),
+            event.getSource(),
+            event.getAction(),
+            event.getEventTime(),
+            event.getDownTime(),
+            event.getFlags(),
+            event.getMetaState(),
+            event.getRepeatCount(),
+            event.getKeyCode(),
+            event.getScanCode(),
+            event.getUnicodeChar(),
+        };
+    }
+}
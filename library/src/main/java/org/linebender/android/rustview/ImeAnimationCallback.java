@@ -0,0 +1,66 @@
+package org.linebender.android.rustview;
+
+import android.graphics.Insets;
+import android.view.WindowInsets;
+import android.view.WindowInsetsAnimation;
+import java.util.List;
+
+/** Forwards animations of the IME insets to the peer of a {@link RustView}. Requires API 30. */
+final class ImeAnimationCallback extends WindowInsetsAnimation.Callback {
+    private final RustView mView;
+
+    ImeAnimationCallback(RustView view) {
+        super(DISPATCH_MODE_STOP);
+        mView = view;
+    }
+
+    private static boolean isIme(WindowInsetsAnimation animation) {
+        return (animation.getTypeMask() & WindowInsets.Type.ime()) != 0;
+    }
+
+    @Override
+    public void onPrepare(WindowInsetsAnimation animation) {
+        if (isIme(animation)) {
+            mView.imeAnimationPrepareNative(mView.mViewPeer);
+        }
+    }
+
+    @Override
+    public WindowInsetsAnimation.Bounds onStart(
+            WindowInsetsAnimation animation, WindowInsetsAnimation.Bounds bounds) {
+        if (isIme(animation)) {
+            Insets lower = bounds.getLowerBound();
+            Insets upper = bounds.getUpperBound();
+            int[] packed = {
+                lower.left, lower.top, lower.right, lower.bottom,
+                upper.left, upper.top, upper.right, upper.bottom,
+            };
+            mView.imeAnimationStartNative(mView.mViewPeer, packed, animation.getDurationMillis());
+        }
+        return bounds;
+    }
+
+    @Override
+    public WindowInsets onProgress(
+            WindowInsets insets, List<WindowInsetsAnimation> runningAnimations) {
+        for (WindowInsetsAnimation animation : runningAnimations) {
+            if (isIme(animation)) {
+                mView.imeAnimationProgressNative(
+                        mView.mViewPeer,
+                        WindowInsetsSnapshots.pack(insets),
+                        animation.getFraction(),
+                        animation.getInterpolatedFraction(),
+                        animation.getAlpha());
+                break;
+            }
+        }
+        return insets;
+    }
+
+    @Override
+    public void onEnd(WindowInsetsAnimation animation) {
+        if (isIme(animation)) {
+            mView.imeAnimationEndNative(mView.mViewPeer);
+        }
+    }
+}
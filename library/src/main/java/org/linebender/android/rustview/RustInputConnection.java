@@ -21,6 +21,40 @@ class RustInputConnection implements InputConnection {
         return mView.mViewPeer;
     }
 
+    @Override
+    public ExtractedText getExtractedText(ExtractedTextRequest request, int flags) {
+        return null;
+    }
+
+    @Override
+    public boolean commitCompletion(CompletionInfo text) {
+        return false;
+    }
+
+    @Override
+    public boolean commitCorrection(CorrectionInfo correctionInfo) {
+        return false;
+    }
+
+    @Override
+    public boolean performPrivateCommand(String action, Bundle data) {
+        return false;
+    }
+
+    @Override
+    public Handler getHandler() {
+        return null;
+    }
+
+    @Override
+    public boolean commitContent(InputContentInfo inputContentInfo, int flags, Bundle opts) {
+        return false;
+    }
+
+    // BEGIN GENERATED FORWARDS
+    // Generated by build.rs. Edit the table there and rebuild with
+    // ANDROID_VIEW_REGENERATE_JAVA=1 to update.
+
     @Override
     public CharSequence getTextBeforeCursor(int n, int flags) {
         return mView.getTextBeforeCursorNative(getViewPeer(), n);
@@ -41,11 +75,6 @@ class RustInputConnection implements InputConnection {
         return mView.getCursorCapsModeNative(getViewPeer(), reqModes);
     }
 
-    @Override
-    public ExtractedText getExtractedText(ExtractedTextRequest request, int flags) {
-        return null;
-    }
-
     @Override
     public boolean deleteSurroundingText(int beforeLength, int afterLength) {
         return mView.deleteSurroundingTextNative(getViewPeer(), beforeLength, afterLength);
@@ -53,7 +82,8 @@ class RustInputConnection implements InputConnection {
 
     @Override
     public boolean deleteSurroundingTextInCodePoints(int beforeLength, int afterLength) {
-        return mView.deleteSurroundingTextInCodePointsNative(getViewPeer(), beforeLength, afterLength);
+        return mView.deleteSurroundingTextInCodePointsNative(
+                getViewPeer(), beforeLength, afterLength);
     }
 
     @Override
@@ -76,16 +106,6 @@ class RustInputConnection implements InputConnection {
         return mView.commitTextNative(getViewPeer(), text.toString(), newCursorPosition);
     }
 
-    @Override
-    public boolean commitCompletion(CompletionInfo text) {
-        return false;
-    }
-
-    @Override
-    public boolean commitCorrection(CorrectionInfo correctionInfo) {
-        return false;
-    }
-
     @Override
     public boolean setSelection(int start, int end) {
         return mView.setSelectionNative(getViewPeer(), start, end);
@@ -126,28 +146,14 @@ class RustInputConnection implements InputConnection {
         return mView.inputConnectionReportFullscreenModeNative(getViewPeer(), enabled);
     }
 
-    @Override
-    public boolean performPrivateCommand(String action, Bundle data) {
-        return false;
-    }
-
     @Override
     public boolean requestCursorUpdates(int cursorUpdateMode) {
         return mView.requestCursorUpdatesNative(getViewPeer(), cursorUpdateMode);
     }
 
-    @Override
-    public Handler getHandler() {
-        return null;
-    }
-
     @Override
     public void closeConnection() {
         mView.closeInputConnectionNative(getViewPeer());
     }
-
-    @Override
-    public boolean commitContent(InputContentInfo inputContentInfo, int flags, Bundle opts) {
-        return false;
-    }
+    // END GENERATED FORWARDS
 }
@@ -1,38 +1,63 @@
 package org.linebender.android.rustview;
 
 import android.content.Context;
+import android.content.res.Configuration;
 import android.graphics.Rect;
+import android.os.Build;
 import android.os.Bundle;
 import android.view.Choreographer;
+import android.view.Display;
 import android.view.KeyEvent;
 import android.view.MotionEvent;
 import android.view.SurfaceHolder;
 import android.view.SurfaceView;
+import android.view.WindowInsets;
 import android.view.accessibility.AccessibilityNodeInfo;
 import android.view.accessibility.AccessibilityNodeProvider;
 import android.view.inputmethod.EditorInfo;
 import android.view.inputmethod.InputConnection;
 import android.view.inputmethod.InputMethodManager;
+import java.util.HashMap;
 
 public abstract class RustView extends SurfaceView
         implements SurfaceHolder.Callback, Choreographer.FrameCallback {
     final long mViewPeer;
+    private final int mCallbacks;
     final InputMethodManager mInputMethodManager;
+    private int mDisplayId = Display.INVALID_DISPLAY;
 
     protected abstract long newViewPeer(Context context);
 
     public RustView(Context context) {
         super(context);
         mViewPeer = newViewPeer(context);
+        mCallbacks = callbacksNative(mViewPeer);
         getHolder().addCallback(this);
+        if (Build.VERSION.SDK_INT >= 30 && handles(CALLBACK_IME_ANIMATION)) {
+            setWindowInsetsAnimationCallback(new ImeAnimationCallback(this));
+        }
         mInputMethodManager =
                 (InputMethodManager) context.getSystemService(Context.INPUT_METHOD_SERVICE);
     }
 
-    private native int[] onMeasureNative(long peer, int widthSpec, int heightSpec);
+    /**
+     * Returns the ID of the Rust peer of this view, for passing to native
+     * methods that look it up with {@code PeerHandle::from_id}.
+     */
+    public final long getViewPeer() {
+        return mViewPeer;
+    }
+
+    private boolean handles(int callback) {
+        return (mCallbacks & callback) != 0;
+    }
 
     @Override
     protected void onMeasure(int widthSpec, int heightSpec) {
+        if (!handles(CALLBACK_MEASURE)) {
+            super.onMeasure(widthSpec, heightSpec);
+            return;
+        }
         int[] result = onMeasureNative(mViewPeer, widthSpec, heightSpec);
         if (result != null) {
             setMeasuredDimension(result[0], result[1]);
@@ -41,173 +66,246 @@ public abstract class RustView extends SurfaceView
         }
     }
 
-    private native void onLayoutNative(
-            long peer, boolean changed, int left, int top, int right, int bottom);
-
     @Override
     protected void onLayout(boolean changed, int left, int top, int right, int bottom) {
-        onLayoutNative(mViewPeer, changed, left, top, right, bottom);
+        if (handles(CALLBACK_LAYOUT)) {
+            onLayoutNative(mViewPeer, changed, left, top, right, bottom);
+        }
         super.onLayout(changed, left, top, right, bottom);
     }
 
-    private native void onSizeChangedNative(long peer, int w, int h, int oldw, int oldh);
-
     @Override
     protected void onSizeChanged(int w, int h, int oldw, int oldh) {
-        onSizeChangedNative(mViewPeer, w, h, oldw, oldh);
+        if (handles(CALLBACK_SIZE_CHANGED)) {
+            onSizeChangedNative(mViewPeer, w, h, oldw, oldh);
+        }
         super.onSizeChanged(w, h, oldw, oldh);
     }
 
-    private native boolean onKeyDownNative(long peer, int keyCode, KeyEvent event);
+    @Override
+    public WindowInsets onApplyWindowInsets(WindowInsets insets) {
+        if (!handles(CALLBACK_WINDOW_INSETS)) {
+            return super.onApplyWindowInsets(insets);
+        }
+        int[] consumed = onApplyWindowInsetsNative(mViewPeer, WindowInsetsSnapshots.pack(insets));
+        if (consumed == null) {
+            return super.onApplyWindowInsets(insets);
+        }
+        return WindowInsetsSnapshots.consume(insets, consumed);
+    }
 
     @Override
-    public boolean onKeyDown(int keyCode, KeyEvent event) {
-        return onKeyDownNative(mViewPeer, keyCode, event) || super.onKeyDown(keyCode, event);
+    protected void onConfigurationChanged(Configuration newConfig) {
+        super.onConfigurationChanged(newConfig);
+        if (handles(CALLBACK_CONFIGURATION_CHANGED)) {
+            onConfigurationChangedNative(mViewPeer, newConfig);
+        }
+        onDisplayChanged();
     }
 
-    private native boolean onKeyUpNative(long peer, int keyCode, KeyEvent event);
+    /**
+     * Tells the peer if the window has moved to another display since the last call. Moving
+     * displays always changes the configuration, and the view is attached again if the activity
+     * doesn't handle the change itself.
+     */
+    private void onDisplayChanged() {
+        Display display = getDisplay();
+        if (display == null) {
+            return;
+        }
+        int previousId = mDisplayId;
+        mDisplayId = display.getDisplayId();
+        if (previousId != Display.INVALID_DISPLAY
+                && previousId != mDisplayId
+                && handles(CALLBACK_DISPLAY_CHANGED)) {
+            onDisplayChangedNative(mViewPeer, display);
+        }
+    }
 
     @Override
-    public boolean onKeyUp(int keyCode, KeyEvent event) {
-        return onKeyUpNative(mViewPeer, keyCode, event) || super.onKeyUp(keyCode, event);
+    public boolean onKeyDown(int keyCode, KeyEvent event) {
+        return (handles(CALLBACK_KEY) && onKeyDownNative(mViewPeer, keyCode, event))
+                || super.onKeyDown(keyCode, event);
     }
 
-    private native boolean onTrackballEventNative(long peer, MotionEvent event);
+    @Override
+    public boolean onKeyUp(int keyCode, KeyEvent event) {
+        return (handles(CALLBACK_KEY) && onKeyUpNative(mViewPeer, keyCode, event))
+                || super.onKeyUp(keyCode, event);
+    }
 
     @Override
     public boolean onTrackballEvent(MotionEvent event) {
-        return onTrackballEventNative(mViewPeer, event) || super.onTrackballEvent(event);
+        return (handles(CALLBACK_TRACKBALL) && onTrackballEventNative(mViewPeer, event))
+                || super.onTrackballEvent(event);
     }
 
-    private native boolean onTouchEventNative(long peer, MotionEvent event);
-
     @Override
     public boolean onTouchEvent(MotionEvent event) {
-        return onTouchEventNative(mViewPeer, event) || super.onTouchEvent(event);
+        return (handles(CALLBACK_TOUCH) && onTouchEventNative(mViewPeer, event))
+                || super.onTouchEvent(event);
     }
 
-    private native boolean onGenericMotionEventNative(long peer, MotionEvent event);
-
     @Override
     public boolean onGenericMotionEvent(MotionEvent event) {
-        return onGenericMotionEventNative(mViewPeer, event) || super.onGenericMotionEvent(event);
+        return (handles(CALLBACK_GENERIC_MOTION) && onGenericMotionEventNative(mViewPeer, event))
+                || super.onGenericMotionEvent(event);
     }
 
-    private native boolean onHoverEventNative(long peer, MotionEvent event);
-
     @Override
     public boolean onHoverEvent(MotionEvent event) {
-        return onHoverEventNative(mViewPeer, event) || super.onHoverEvent(event);
+        return (handles(CALLBACK_HOVER) && onHoverEventNative(mViewPeer, event))
+                || super.onHoverEvent(event);
     }
 
-    private native void onFocusChangedNative(
-            long peer, boolean gainFocus, int direction, Rect previouslyFocusedRect);
-
     @Override
     protected void onFocusChanged(boolean gainFocus, int direction, Rect previouslyFocusedRect) {
         super.onFocusChanged(gainFocus, direction, previouslyFocusedRect);
-        onFocusChangedNative(mViewPeer, gainFocus, direction, previouslyFocusedRect);
+        if (handles(CALLBACK_FOCUS_CHANGED)) {
+            onFocusChangedNative(mViewPeer, gainFocus, direction, previouslyFocusedRect);
+        }
     }
 
-    private native void onWindowFocusChangedNative(long peer, boolean hasWindowFocus);
-
     @Override
     public void onWindowFocusChanged(boolean hasWindowFocus) {
         super.onWindowFocusChanged(hasWindowFocus);
-        onWindowFocusChangedNative(mViewPeer, hasWindowFocus);
+        if (handles(CALLBACK_WINDOW_FOCUS_CHANGED)) {
+            onWindowFocusChangedNative(mViewPeer, hasWindowFocus);
+        }
     }
 
-    private native void onAttachedToWindowNative(long peer);
-
     @Override
     protected void onAttachedToWindow() {
         super.onAttachedToWindow();
-        onAttachedToWindowNative(mViewPeer);
+        if (handles(CALLBACK_ATTACHED_TO_WINDOW)) {
+            onAttachedToWindowNative(mViewPeer);
+        }
+        onDisplayChanged();
     }
 
-    private native void onDetachedFromWindowNative(long peer);
-
     @Override
     protected void onDetachedFromWindow() {
         super.onDetachedFromWindow();
         onDetachedFromWindowNative(mViewPeer);
     }
 
-    private native void onWindowVisibilityChangedNative(long peer, int visibility);
-
     @Override
     protected void onWindowVisibilityChanged(int visibility) {
         super.onWindowVisibilityChanged(visibility);
-        onWindowVisibilityChangedNative(mViewPeer, visibility);
+        if (handles(CALLBACK_WINDOW_VISIBILITY_CHANGED)) {
+            onWindowVisibilityChangedNative(mViewPeer, visibility);
+        }
     }
 
-    private native void surfaceCreatedNative(long peer, SurfaceHolder holder);
-
     @Override
     public void surfaceCreated(SurfaceHolder holder) {
-        surfaceCreatedNative(mViewPeer, holder);
+        if (handles(CALLBACK_SURFACE)) {
+            surfaceCreatedNative(mViewPeer, holder);
+        }
     }
 
-    private native void surfaceChangedNative(
-            long peer, SurfaceHolder holder, int format, int width, int height);
-
     @Override
     public void surfaceChanged(SurfaceHolder holder, int format, int width, int height) {
-        surfaceChangedNative(mViewPeer, holder, format, width, height);
+        if (handles(CALLBACK_SURFACE)) {
+            surfaceChangedNative(mViewPeer, holder, format, width, height);
+        }
     }
 
-    private native void surfaceDestroyedNative(long peer, SurfaceHolder holder);
-
     @Override
     public void surfaceDestroyed(SurfaceHolder holder) {
-        surfaceDestroyedNative(mViewPeer, holder);
+        if (handles(CALLBACK_SURFACE)) {
+            surfaceDestroyedNative(mViewPeer, holder);
+        }
     }
 
+    private VsyncCallback mVsyncCallback;
+
     void postFrameCallback() {
         Choreographer c = Choreographer.getInstance();
+        if (Build.VERSION.SDK_INT >= 33) {
+            if (mVsyncCallback == null) {
+                mVsyncCallback = new VsyncCallback(this);
+            }
+            c.removeVsyncCallback(mVsyncCallback);
+            c.postVsyncCallback(mVsyncCallback);
+            return;
+        }
         c.removeFrameCallback(this);
         c.postFrameCallback(this);
     }
 
     void removeFrameCallback() {
-        Choreographer.getInstance().removeFrameCallback(this);
+        Choreographer c = Choreographer.getInstance();
+        if (mVsyncCallback != null) {
+            c.removeVsyncCallback(mVsyncCallback);
+        }
+        c.removeFrameCallback(this);
     }
 
-    private native void doFrameNative(long peer, long frameTimeNanos);
+    /** Returns the time between vsyncs at the current refresh rate of the display. */
+    long frameIntervalNanos() {
+        Display display = getDisplay();
+        float refreshRate = display != null ? display.getRefreshRate() : 60.0f;
+        return (long) (1_000_000_000L / refreshRate);
+    }
 
     @Override
     public void doFrame(long frameTimeNanos) {
-        doFrameNative(mViewPeer, frameTimeNanos);
+        // Before API 33 there is no frame timeline, so assume the frame is due
+        // by the next vsync and shown at the one after.
+        long interval = frameIntervalNanos();
+        doFrameNative(
+                mViewPeer,
+                frameTimeNanos,
+                frameTimeNanos + 2 * interval,
+                frameTimeNanos + interval,
+                interval);
+    }
+
+    private final HashMap<Long, Runnable> mTimers = new HashMap<>();
+
+    void startTimer(long token, long delayMillis) {
+        cancelTimer(token);
+        Runnable timer =
+                new Runnable() {
+                    @Override
+                    public void run() {
+                        mTimers.remove(token);
+                        timerNative(mViewPeer, token);
+                    }
+                };
+        mTimers.put(token, timer);
+        postDelayed(timer, delayMillis);
     }
 
-    private native void delayedCallbackNative(long peer);
+    boolean cancelTimer(long token) {
+        Runnable timer = mTimers.remove(token);
+        if (timer == null) {
+            return false;
+        }
+        removeCallbacks(timer);
+        return true;
+    }
+
+    void cancelAllTimers() {
+        for (Runnable timer : mTimers.values()) {
+            removeCallbacks(timer);
+        }
+        mTimers.clear();
+    }
 
-    private final Runnable mDelayedCallback =
+    private final Runnable mRunPosted =
             new Runnable() {
                 @Override
                 public void run() {
-                    delayedCallbackNative(mViewPeer);
+                    runPostedNative(mViewPeer);
                 }
             };
 
-    boolean postDelayed(long delayMillis) {
-        return postDelayed(mDelayedCallback, delayMillis);
-    }
-
-    boolean removeDelayedCallbacks() {
-        return removeCallbacks(mDelayedCallback);
+    boolean wakeForPosted() {
+        return post(mRunPosted);
     }
 
-    private native boolean hasAccessibilityNodeProviderNative(long peer);
-
-    private native AccessibilityNodeInfo createAccessibilityNodeInfoNative(
-            long peer, int virtualViewId);
-
-    private native AccessibilityNodeInfo accessibilityFindFocusNative(long peer, int virtualViewId);
-
-    private native boolean performAccessibilityActionNative(
-            long peer, int virtualViewId, int action, Bundle arguments);
-
     @Override
     public AccessibilityNodeProvider getAccessibilityNodeProvider() {
         if (!hasAccessibilityNodeProviderNative(mViewPeer)) {
@@ -232,8 +330,6 @@ public abstract class RustView extends SurfaceView
         };
     }
 
-    private native boolean onCreateInputConnectionNative(long peer, EditorInfo outAttrs);
-
     @Override
     public InputConnection onCreateInputConnection(EditorInfo outAttrs) {
         if (!onCreateInputConnectionNative(mViewPeer, outAttrs)) {
@@ -242,6 +338,105 @@ public abstract class RustView extends SurfaceView
         return new RustInputConnection(this);
     }
 
+    // BEGIN GENERATED NATIVES
+    // Generated by build.rs. Edit the table there and rebuild with
+    // ANDROID_VIEW_REGENERATE_JAVA=1 to update.
+
+    private static final int CALLBACK_MEASURE = 1 << 0;
+    private static final int CALLBACK_LAYOUT = 1 << 1;
+    private static final int CALLBACK_SIZE_CHANGED = 1 << 2;
+    private static final int CALLBACK_WINDOW_INSETS = 1 << 3;
+    private static final int CALLBACK_IME_ANIMATION = 1 << 4;
+    private static final int CALLBACK_CONFIGURATION_CHANGED = 1 << 5;
+    private static final int CALLBACK_DISPLAY_CHANGED = 1 << 6;
+    private static final int CALLBACK_KEY = 1 << 7;
+    private static final int CALLBACK_TRACKBALL = 1 << 8;
+    private static final int CALLBACK_TOUCH = 1 << 9;
+    private static final int CALLBACK_GENERIC_MOTION = 1 << 10;
+    private static final int CALLBACK_HOVER = 1 << 11;
+    private static final int CALLBACK_FOCUS_CHANGED = 1 << 12;
+    private static final int CALLBACK_WINDOW_FOCUS_CHANGED = 1 << 13;
+    private static final int CALLBACK_ATTACHED_TO_WINDOW = 1 << 14;
+    private static final int CALLBACK_WINDOW_VISIBILITY_CHANGED = 1 << 15;
+    private static final int CALLBACK_SURFACE = 1 << 16;
+
+    private native int callbacksNative(long peer);
+
+    private native int[] onMeasureNative(long peer, int widthSpec, int heightSpec);
+
+    private native void onLayoutNative(
+            long peer, boolean changed, int left, int top, int right, int bottom);
+
+    private native void onSizeChangedNative(long peer, int w, int h, int oldw, int oldh);
+
+    private native int[] onApplyWindowInsetsNative(long peer, int[] insets);
+
+    private native void onConfigurationChangedNative(long peer, Configuration newConfig);
+
+    private native void onDisplayChangedNative(long peer, Display display);
+
+    private native boolean onKeyDownNative(long peer, int keyCode, KeyEvent event);
+
+    private native boolean onKeyUpNative(long peer, int keyCode, KeyEvent event);
+
+    private native boolean onTrackballEventNative(long peer, MotionEvent event);
+
+    private native boolean onTouchEventNative(long peer, MotionEvent event);
+
+    private native boolean onGenericMotionEventNative(long peer, MotionEvent event);
+
+    private native boolean onHoverEventNative(long peer, MotionEvent event);
+
+    private native void onFocusChangedNative(
+            long peer, boolean gainFocus, int direction, Rect previouslyFocusedRect);
+
+    private native void onWindowFocusChangedNative(long peer, boolean hasWindowFocus);
+
+    private native void onAttachedToWindowNative(long peer);
+
+    private native void onDetachedFromWindowNative(long peer);
+
+    private native void onWindowVisibilityChangedNative(long peer, int visibility);
+
+    private native void surfaceCreatedNative(long peer, SurfaceHolder holder);
+
+    private native void surfaceChangedNative(
+            long peer, SurfaceHolder holder, int format, int width, int height);
+
+    private native void surfaceDestroyedNative(long peer, SurfaceHolder holder);
+
+    native void imeAnimationPrepareNative(long peer);
+
+    native void imeAnimationStartNative(long peer, int[] bounds, long durationMillis);
+
+    native void imeAnimationProgressNative(
+            long peer, int[] insets, float fraction, float interpolatedFraction, float alpha);
+
+    native void imeAnimationEndNative(long peer);
+
+    native void doFrameNative(
+            long peer,
+            long frameTimeNanos,
+            long expectedPresentationTimeNanos,
+            long deadlineNanos,
+            long frameIntervalNanos);
+
+    private native void timerNative(long peer, long token);
+
+    private native void runPostedNative(long peer);
+
+    private native boolean hasAccessibilityNodeProviderNative(long peer);
+
+    private native AccessibilityNodeInfo createAccessibilityNodeInfoNative(
+            long peer, int virtualViewId);
+
+    private native AccessibilityNodeInfo accessibilityFindFocusNative(long peer, int virtualViewId);
+
+    private native boolean performAccessibilityActionNative(
+            long peer, int virtualViewId, int action, Bundle arguments);
+
+    private native boolean onCreateInputConnectionNative(long peer, EditorInfo outAttrs);
+
     native String getTextBeforeCursorNative(long peer, int n);
 
     native String getTextAfterCursorNative(long peer, int n);
@@ -282,4 +477,5 @@ public abstract class RustView extends SurfaceView
     native boolean requestCursorUpdatesNative(long peer, int cursorUpdateMode);
 
     native void closeInputConnectionNative(long peer);
+    // END GENERATED NATIVES
 }
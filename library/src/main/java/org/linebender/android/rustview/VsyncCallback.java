@@ -0,0 +1,23 @@
+package org.linebender.android.rustview;
+
+import android.view.Choreographer;
+
+/** Forwards frames to the peer of a {@link RustView} with their timeline. Requires API 33. */
+final class VsyncCallback implements Choreographer.VsyncCallback {
+    private final RustView mView;
+
+    VsyncCallback(RustView view) {
+        mView = view;
+    }
+
+    @Override
+    public void onVsync(Choreographer.FrameData data) {
+        Choreographer.FrameTimeline timeline = data.getPreferredFrameTimeline();
+        mView.doFrameNative(
+                mView.mViewPeer,
+                data.getFrameTimeNanos(),
+                timeline.getExpectedPresentationTimeNanos(),
+                timeline.getDeadlineNanos(),
+                mView.frameIntervalNanos());
+    }
+}
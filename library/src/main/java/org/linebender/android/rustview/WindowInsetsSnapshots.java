@@ -0,0 +1,187 @@
+package org.linebender.android.rustview;
+
+import android.graphics.Insets;
+import android.graphics.Point;
+import android.graphics.Rect;
+import android.os.Build;
+import android.view.DisplayCutout;
+import android.view.RoundedCorner;
+import android.view.WindowInsets;
+import java.util.Collections;
+import java.util.List;
+
+/** Packs window insets into an int array so native code can copy them in one call. */
+final class WindowInsetsSnapshots {
+    /** Number of inset types, in the order documented on {@link #pack}. */
+    static final int TYPE_COUNT = 8;
+
+    /** Number of ints stored per type: visibility, insets and insets ignoring visibility. */
+    static final int TYPE_STRIDE = 9;
+
+    /** Number of ints stored per rounded corner: presence, radius and center. */
+    static final int CORNER_STRIDE = 4;
+
+    private WindowInsetsSnapshots() {}
+
+    /**
+     * Returns the insets of each type, in the order status bars, navigation bars, caption bar,
+     * IME, system gestures, mandatory system gestures, tappable element and display cutout,
+     * followed by the top-left, top-right, bottom-right and bottom-left rounded corners, then the
+     * number of bounding rectangles of the display cutout and the rectangles themselves.
+     */
+    static int[] pack(WindowInsets insets) {
+        DisplayCutout cutout = insets.getDisplayCutout();
+        List<Rect> cutoutRects =
+                cutout != null ? cutout.getBoundingRects() : Collections.<Rect>emptyList();
+        int[] result =
+                new int[TYPE_COUNT * TYPE_STRIDE + 4 * CORNER_STRIDE + 1 + 4 * cutoutRects.size()];
+        if (Build.VERSION.SDK_INT >= 30) {
+            packTypes(insets, result);
+        } else {
+            packLegacyTypes(insets, cutout, result);
+        }
+        int i = TYPE_COUNT * TYPE_STRIDE;
+        if (Build.VERSION.SDK_INT >= 31) {
+            packRoundedCorners(insets, result, i);
+        }
+        i += 4 * CORNER_STRIDE;
+        result[i++] = cutoutRects.size();
+        for (Rect rect : cutoutRects) {
+            result[i++] = rect.left;
+            result[i++] = rect.top;
+            result[i++] = rect.right;
+            result[i++] = rect.bottom;
+        }
+        return result;
+    }
+
+    /** Returns {@code insets} with {@code consumed} removed from the left, top, right and bottom. */
+    static WindowInsets consume(WindowInsets insets, int[] consumed) {
+        if (Build.VERSION.SDK_INT >= 29) {
+            return insets.inset(consumed[0], consumed[1], consumed[2], consumed[3]);
+        }
+        return insets.replaceSystemWindowInsets(
+                Math.max(0, insets.getSystemWindowInsetLeft() - consumed[0]),
+                Math.max(0, insets.getSystemWindowInsetTop() - consumed[1]),
+                Math.max(0, insets.getSystemWindowInsetRight() - consumed[2]),
+                Math.max(0, insets.getSystemWindowInsetBottom() - consumed[3]));
+    }
+
+    private static void putInsets(int[] result, int i, Insets insets) {
+        putInsets(result, i, insets.left, insets.top, insets.right, insets.bottom);
+    }
+
+    private static void putInsets(int[] result, int i, int left, int top, int right, int bottom) {
+        result[i] = left;
+        result[i + 1] = top;
+        result[i + 2] = right;
+        result[i + 3] = bottom;
+    }
+
+    private static void packTypes(WindowInsets insets, int[] result) {
+        int[] types = {
+            WindowInsets.Type.statusBars(),
+            WindowInsets.Type.navigationBars(),
+            WindowInsets.Type.captionBar(),
+            WindowInsets.Type.ime(),
+            WindowInsets.Type.systemGestures(),
+            WindowInsets.Type.mandatorySystemGestures(),
+            WindowInsets.Type.tappableElement(),
+            WindowInsets.Type.displayCutout(),
+        };
+        for (int t = 0; t < types.length; t++) {
+            int i = t * TYPE_STRIDE;
+            result[i] = insets.isVisible(types[t]) ? 1 : 0;
+            putInsets(result, i + 1, insets.getInsets(types[t]));
+            // The IME has no insets while hidden, and throws if asked.
+            if (types[t] != WindowInsets.Type.ime()) {
+                putInsets(result, i + 5, insets.getInsetsIgnoringVisibility(types[t]));
+            }
+        }
+    }
+
+    /**
+     * Approximates the insets of each type from the system window and stable insets, which cover
+     * the system bars and the IME together before API 30.
+     */
+    private static void packLegacyTypes(WindowInsets insets, DisplayCutout cutout, int[] result) {
+        int left = insets.getSystemWindowInsetLeft();
+        int top = insets.getSystemWindowInsetTop();
+        int right = insets.getSystemWindowInsetRight();
+        int bottom = insets.getSystemWindowInsetBottom();
+        int stableBottom = insets.getStableInsetBottom();
+        int navigationBottom = Math.min(bottom, stableBottom);
+
+        int i = 0;
+        result[i] = top > 0 ? 1 : 0;
+        putInsets(result, i + 1, 0, top, 0, 0);
+        putInsets(result, i + 5, 0, insets.getStableInsetTop(), 0, 0);
+
+        i += TYPE_STRIDE;
+        result[i] = left > 0 || right > 0 || navigationBottom > 0 ? 1 : 0;
+        putInsets(result, i + 1, left, 0, right, navigationBottom);
+        putInsets(
+                result,
+                i + 5,
+                insets.getStableInsetLeft(),
+                0,
+                insets.getStableInsetRight(),
+                stableBottom);
+
+        // The caption bar has no insets.
+        i += 2 * TYPE_STRIDE;
+        result[i] = bottom > stableBottom ? 1 : 0;
+        putInsets(result, i + 1, 0, 0, 0, Math.max(0, bottom - stableBottom));
+
+        for (int t = 0; t < 3; t++) {
+            i += TYPE_STRIDE;
+            result[i] = 1;
+            if (Build.VERSION.SDK_INT >= 29) {
+                Insets gestures =
+                        t == 0
+                                ? insets.getSystemGestureInsets()
+                                : t == 1
+                                        ? insets.getMandatorySystemGestureInsets()
+                                        : insets.getTappableElementInsets();
+                putInsets(result, i + 1, gestures);
+                putInsets(result, i + 5, gestures);
+            } else {
+                putInsets(result, i + 1, left, top, right, bottom);
+                putInsets(result, i + 5, left, top, right, bottom);
+            }
+        }
+
+        i += TYPE_STRIDE;
+        if (cutout != null) {
+            result[i] = 1;
+            putInsets(
+                    result,
+                    i + 1,
+                    cutout.getSafeInsetLeft(),
+                    cutout.getSafeInsetTop(),
+                    cutout.getSafeInsetRight(),
+                    cutout.getSafeInsetBottom());
+            System.arraycopy(result, i + 1, result, i + 5, 4);
+        }
+    }
+
+    private static void packRoundedCorners(WindowInsets insets, int[] result, int i) {
+        int[] positions = {
+            RoundedCorner.POSITION_TOP_LEFT,
+            RoundedCorner.POSITION_TOP_RIGHT,
+            RoundedCorner.POSITION_BOTTOM_RIGHT,
+            RoundedCorner.POSITION_BOTTOM_LEFT,
+        };
+        for (int position : positions) {
+            RoundedCorner corner = insets.getRoundedCorner(position);
+            if (corner != null) {
+                Point center = corner.getCenter();
+                result[i] = 1;
+                result[i + 1] = corner.getRadius();
+                result[i + 2] = center.x;
+                result[i + 3] = center.y;
+            }
+            i += CORNER_STRIDE;
+        }
+    }
+}
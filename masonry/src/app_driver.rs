@@ -1,10 +1,12 @@
 // Copyright 2025 the Xilem Authors
 // SPDX-License-Identifier: Apache-2.0
 
+use android_view::TimerToken;
 use masonry_core::{
     app::RenderRoot,
     core::{ErasedAction, WidgetId},
 };
+use std::time::Duration;
 
 use crate::MasonryState;
 
@@ -19,6 +21,16 @@ pub struct DriverCtx<'a> {
     // is not yet designed.
     #[doc(hidden)]
     pub render_root: &'a mut RenderRoot,
+    #[doc(hidden)]
+    pub timer_requests: &'a mut Vec<TimerRequest>,
+}
+
+/// A timer change requested through [`DriverCtx`], applied to the view
+/// once the [`AppDriver`] hook returns.
+#[doc(hidden)]
+pub enum TimerRequest {
+    Start(TimerToken, Duration),
+    Cancel(TimerToken),
 }
 
 /// A trait for defining how your app interacts with the Masonry widget tree.
@@ -34,10 +46,26 @@ pub trait AppDriver {
     ///
     /// Use cases include loading fonts.
     fn on_start(&mut self, state: &mut MasonryState) {}
+
+    #[expect(unused_variables, reason = "Default impl doesn't use arguments")]
+    /// A hook which will be executed when a timer started with
+    /// [`DriverCtx::start_timer`] fires.
+    fn on_timer(&mut self, ctx: &mut DriverCtx<'_>, token: TimerToken) {}
 }
 
 impl DriverCtx<'_> {
-    // TODO - Add method to create timer
+    /// Start a timer which calls [`AppDriver::on_timer`] with the returned
+    /// token after `delay`.
+    pub fn start_timer(&mut self, delay: Duration) -> TimerToken {
+        let token = TimerToken::next();
+        self.timer_requests.push(TimerRequest::Start(token, delay));
+        token
+    }
+
+    /// Cancel a timer started with [`start_timer`](Self::start_timer).
+    pub fn cancel_timer(&mut self, token: TimerToken) {
+        self.timer_requests.push(TimerRequest::Cancel(token));
+    }
 
     /// Access the [`RenderRoot`].
     pub fn render_root(&mut self) -> &mut RenderRoot {
@@ -56,7 +84,8 @@ mod doctests {
     /// ```no_run
     /// use masonry::app::DriverCtx;
     /// let _ctx = DriverCtx {
-    ///     render_root: unimplemented!()
+    ///     render_root: unimplemented!(),
+    ///     timer_requests: unimplemented!(),
     /// };
     /// ```
     const _DRIVER_CTX_EXTERNALLY_CONSTRUCTIBLE: () = {};
@@ -15,7 +15,6 @@ use masonry_core::{
     core::{DefaultProperties, Handled, NewWidget, TextEvent, Widget, WindowEvent},
     dpi::PhysicalSize,
     peniko::Color,
-    util::Instant,
     vello::{
         self, Renderer, RendererOptions, Scene,
         kurbo::Affine,
@@ -26,9 +25,12 @@ use masonry_core::{
         },
     },
 };
-use std::sync::{
-    Arc,
-    mpsc::{self, Receiver},
+use std::{
+    sync::{
+        Arc,
+        mpsc::{self, Receiver},
+    },
+    time::Duration,
 };
 use tracing::{debug, info, info_span};
 
@@ -67,10 +69,8 @@ fn create_vello_renderer(render_cx: &RenderContext, surface: &RenderSurface<'_>)
     .expect("Couldn't create renderer")
 }
 
-fn scale_factor<'local>(env: &mut JNIEnv<'local>, android_ctx: &Context<'local>) -> f64 {
-    let res = android_ctx.resources(env);
-    let metrics = res.display_metrics(env);
-    metrics.density(env) as f64
+fn scale_factor(ctx: &mut CallbackCtx) -> f64 {
+    ctx.display_metrics().density as f64
 }
 
 fn show_soft_input<'local>(env: &mut JNIEnv<'local>, view: &View<'local>) {
@@ -92,8 +92,12 @@ pub struct MasonryState {
     renderer: Option<Renderer>,
     render_surface: Option<RenderSurface<'static>>,
     // Is `Some` if the most recently displayed frame was an animation frame.
-    last_anim: Option<Instant>,
+    /// The frame time of the last animation frame, in nanoseconds.
+    last_anim: Option<i64>,
     accesskit_adapter: accesskit_android::Adapter,
+    timer_requests: Vec<TimerRequest>,
+    /// The display density, refreshed when the surface changes.
+    scale_factor: f64,
 }
 
 impl MasonryState {
@@ -126,6 +130,8 @@ impl MasonryState {
             render_surface: None,
             last_anim: None,
             accesskit_adapter: Default::default(),
+            timer_requests: vec![],
+            scale_factor,
         }
     }
 }
@@ -158,6 +164,20 @@ struct MasonryViewPeer<Driver: AppDriver> {
 }
 
 impl<Driver: AppDriver> MasonryViewPeer<Driver> {
+    fn apply_timer_requests(&mut self, ctx: &mut CallbackCtx) {
+        for request in self.state.timer_requests.drain(..) {
+            match request {
+                TimerRequest::Start(token, delay) => {
+                    ctx.view
+                        .start_timer_with_token(&mut ctx.env, token, delay.as_millis() as _);
+                }
+                TimerRequest::Cancel(token) => {
+                    ctx.cancel_timer(token);
+                }
+            }
+        }
+    }
+
     fn handle_signals(&mut self, ctx: &mut CallbackCtx) {
         let mut needs_redraw = false;
         while let Ok(signal) = self.state.signal_receiver.try_recv() {
@@ -165,6 +185,7 @@ impl<Driver: AppDriver> MasonryViewPeer<Driver> {
                 RenderRootSignal::Action(action, widget_id) => {
                     let mut driver_ctx = DriverCtx {
                         render_root: &mut self.state.render_root,
+                        timer_requests: &mut self.state.timer_requests,
                     };
                     debug!("Action {:?} on widget {:?}", action, widget_id);
                     self.app_driver
@@ -231,10 +252,13 @@ impl<Driver: AppDriver> MasonryViewPeer<Driver> {
             }
         }
 
+        self.apply_timer_requests(ctx);
+
         // If we're processing a lot of actions, we may have a lot of pending redraws.
-        // We batch them up to avoid redundant requests.
+        // The frame scheduler combines them into one frame, and holds it back
+        // while the window is hidden.
         if needs_redraw && self.state.render_surface.is_some() {
-            ctx.view.post_frame_callback(&mut ctx.env);
+            ctx.request_frame();
         }
     }
 
@@ -253,8 +277,7 @@ impl<Driver: AppDriver> MasonryViewPeer<Driver> {
             });
         }
 
-        let android_ctx = ctx.view.context(&mut ctx.env);
-        let scale_factor = scale_factor(&mut ctx.env, &android_ctx);
+        let scale_factor = self.state.scale_factor;
         let scene = if scale_factor == 1.0 {
             scene
         } else {
@@ -354,7 +377,17 @@ impl<Driver: AppDriver> MasonryViewPeer<Driver> {
     }
 }
 
-impl<Driver: AppDriver> ViewPeer for MasonryViewPeer<Driver> {
+impl<Driver: AppDriver + 'static> ViewPeer for MasonryViewPeer<Driver> {
+    fn callbacks(&self) -> ViewCallbacks {
+        ViewCallbacks::KEY
+            | ViewCallbacks::TOUCH
+            | ViewCallbacks::GENERIC_MOTION
+            | ViewCallbacks::HOVER
+            | ViewCallbacks::FOCUS_CHANGED
+            | ViewCallbacks::WINDOW_VISIBILITY_CHANGED
+            | ViewCallbacks::SURFACE
+    }
+
     fn on_key_down<'local>(
         &mut self,
         ctx: &mut CallbackCtx<'local>,
@@ -378,11 +411,14 @@ impl<Driver: AppDriver> ViewPeer for MasonryViewPeer<Driver> {
         ctx: &mut CallbackCtx<'local>,
         event: &MotionEvent<'local>,
     ) -> bool {
-        let Some(ev) = event.to_pointer_event(&mut ctx.env, &self.state.tap_counter.vc) else {
+        let events = event.to_pointer_events(&mut ctx.env, &self.state.tap_counter.vc);
+        if events.is_empty() {
             return false;
-        };
-        let ev = self.state.tap_counter.attach_count(ev);
-        self.state.render_root.handle_pointer_event(ev);
+        }
+        for ev in events {
+            let ev = self.state.tap_counter.attach_count(ev);
+            self.state.render_root.handle_pointer_event(ev);
+        }
         self.handle_signals(ctx);
         true
     }
@@ -437,8 +473,8 @@ impl<Driver: AppDriver> ViewPeer for MasonryViewPeer<Driver> {
         height: jint,
     ) {
         self.state.tap_counter = TapCounter::new(ctx.view.view_configuration(&mut ctx.env));
-        let android_ctx = ctx.view.context(&mut ctx.env);
-        let scale_factor = scale_factor(&mut ctx.env, &android_ctx);
+        let scale_factor = scale_factor(ctx);
+        self.state.scale_factor = scale_factor;
         self.state
             .render_root
             .handle_window_event(WindowEvent::Rescale(scale_factor));
@@ -495,19 +531,19 @@ impl<Driver: AppDriver> ViewPeer for MasonryViewPeer<Driver> {
         _holder: &SurfaceHolder<'local>,
     ) {
         self.state.render_surface = None;
-        ctx.view.remove_frame_callback(&mut ctx.env);
+        ctx.remove_frame_callback();
     }
 
-    fn do_frame(&mut self, ctx: &mut CallbackCtx, _frame_time_nanos: jlong) {
+    fn do_frame(&mut self, ctx: &mut CallbackCtx, frame: &FrameInfo) {
         let _span = info_span!("do_frame");
 
-        let now = Instant::now();
-        // TODO: this calculation uses wall-clock time of the paint call, which
-        // potentially has jitter.
-        //
-        // See https://github.com/linebender/druid/issues/85 for discussion.
+        // Use the vsync time rather than the current time, which would add
+        // the jitter of scheduling the callback.
+        let now = frame.frame_time_nanos;
         let last = self.state.last_anim.take();
-        let elapsed = last.map(|t| now.duration_since(t)).unwrap_or_default();
+        let elapsed = last
+            .map(|t| Duration::from_nanos(now.saturating_sub(t).max(0) as u64))
+            .unwrap_or_default();
         self.state
             .render_root
             .handle_window_event(WindowEvent::AnimFrame(elapsed));
@@ -524,6 +560,17 @@ impl<Driver: AppDriver> ViewPeer for MasonryViewPeer<Driver> {
         self.redraw(ctx);
     }
 
+    fn on_timer(&mut self, ctx: &mut CallbackCtx, token: TimerToken) {
+        // Masonry has no event to deliver long presses as, so they are dropped.
+        self.state.tap_counter.on_timer(ctx, token);
+        let mut driver_ctx = DriverCtx {
+            render_root: &mut self.state.render_root,
+            timer_requests: &mut self.state.timer_requests,
+        };
+        self.app_driver.on_timer(&mut driver_ctx, token);
+        self.handle_signals(ctx);
+    }
+
     fn as_accessibility_node_provider(&mut self) -> Option<&mut dyn AccessibilityNodeProvider> {
         Some(self)
     }
@@ -605,8 +652,9 @@ pub fn new_view_peer<'local>(
     mut app_driver: impl AppDriver + 'static,
     default_properties: Arc<DefaultProperties>,
 ) -> jlong {
-    let scale_factor = scale_factor(env, android_ctx);
+    let res = android_ctx.resources(env);
+    let scale_factor = res.display_metrics(env).density as f64;
     let mut state = MasonryState::new(root_widget, default_properties, scale_factor);
     app_driver.on_start(&mut state);
-    register_view_peer(MasonryViewPeer { state, app_driver })
+    register_view_peer(MasonryViewPeer { state, app_driver }).id()
 }
@@ -10,6 +10,12 @@ use crate::{bundle::*, callback_ctx::*, util::*, view::*};
 #[repr(transparent)]
 pub struct AccessibilityNodeInfo<'local>(pub JObject<'local>);
 
+impl<'local> CallbackResult<'local> for AccessibilityNodeInfo<'local> {
+    fn pop_local_frame(self, env: &mut JNIEnv<'local>) -> jni::errors::Result<Self> {
+        Ok(Self(self.0.pop_local_frame(env)?))
+    }
+}
+
 #[allow(unused_variables)]
 pub trait AccessibilityNodeProvider {
     fn create_accessibility_node_info<'local>(
@@ -33,7 +39,7 @@ pub trait AccessibilityNodeProvider {
     ) -> bool;
 }
 
-fn with_accessibility_node_provider<'local, F, T: Default>(
+fn with_accessibility_node_provider<'local, F, T: Default + CallbackResult<'local>>(
     env: JNIEnv<'local>,
     view: View<'local>,
     id: jlong,
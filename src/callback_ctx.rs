@@ -1,28 +1,96 @@
-use jni::JNIEnv;
+use jni::{
+    JNIEnv,
+    objects::{JIntArray, JObject, JString},
+    sys::{jboolean, jint, jlong},
+};
 use smallvec::SmallVec;
+use std::{cell::Cell, mem, rc::Rc, time::Duration};
 
-use crate::view::View;
+#[cfg(feature = "async")]
+use crate::executor::Executor;
+#[cfg(feature = "debug-local-refs")]
+use crate::local_refs;
+#[cfg(doc)]
+use crate::view::ViewPeer;
+use crate::{
+    choreographer::NativeFrames,
+    display::DisplayMetrics,
+    error::{Error, catch},
+    frame::FrameScheduler,
+    view::{TimerToken, View, ViewCallbacks, ViewHandle},
+};
+
+/// The number of local references reserved for each callback. Those it
+/// creates are deleted when it returns.
+const LOCAL_FRAME_CAPACITY: i32 = 16;
+
+type DynamicCallback<'local> = Box<dyn FnOnce(&mut JNIEnv<'local>, &View<'local>)>;
 
 enum DeferredCallback<'local> {
     Static(fn(&mut JNIEnv<'local>, &View<'local>)),
-    Dynamic(Box<dyn FnOnce(&mut JNIEnv<'local>, &View<'local>)>),
+    Dynamic(DynamicCallback<'local>),
 }
 
 pub struct CallbackCtx<'local> {
     pub env: JNIEnv<'local>,
     pub view: View<'local>,
+    peer: jlong,
+    /// Set if the peer uses native frame callbacks.
+    pub(crate) native_frames: Option<Rc<NativeFrames>>,
+    pub(crate) frame_scheduler: Option<Rc<FrameScheduler>>,
+    /// The callbacks the peer handles.
+    pub(crate) callbacks: ViewCallbacks,
+    /// Cleared when the configuration or display changes.
+    pub(crate) display_metrics_cache: Option<Rc<Cell<Option<DisplayMetrics>>>>,
+    #[cfg(feature = "async")]
+    pub(crate) executor: Option<Rc<Executor>>,
+    /// The name of the callback, for warnings about local references.
+    #[cfg(feature = "debug-local-refs")]
+    pub(crate) callback: &'static str,
     deferred_callbacks: SmallVec<[DeferredCallback<'local>; 4]>,
+    /// Frames pushed by [`with_local_frame`](Self::with_local_frame) that
+    /// haven't been popped yet.
+    local_frames: u32,
+    /// Whether [`finish`](Self::finish) has popped the local frame.
+    finished: bool,
 }
 
 impl<'local> CallbackCtx<'local> {
-    pub(crate) fn new(env: JNIEnv<'local>, view: View<'local>) -> Self {
+    /// Create a context with a new local reference frame, which is popped
+    /// by [`finish`](Self::finish), or when the context is dropped if the
+    /// callback panics.
+    pub(crate) fn new(mut env: JNIEnv<'local>, view: View<'local>, peer: jlong) -> Self {
+        #[cfg(feature = "debug-local-refs")]
+        local_refs::install(&env);
+        catch(&mut env, |env| env.push_local_frame(LOCAL_FRAME_CAPACITY)).unwrap();
         Self {
             env,
             view,
+            peer,
+            native_frames: None,
+            frame_scheduler: None,
+            callbacks: ViewCallbacks::all(),
+            display_metrics_cache: None,
+            #[cfg(feature = "async")]
+            executor: None,
+            #[cfg(feature = "debug-local-refs")]
+            callback: "callback",
             deferred_callbacks: SmallVec::new(),
+            local_frames: 0,
+            finished: false,
         }
     }
 
+    /// Get a handle which other threads can use to run code with this
+    /// view's peer on the UI thread.
+    pub fn view_handle(&mut self) -> ViewHandle {
+        self.try_view_handle().unwrap()
+    }
+
+    pub fn try_view_handle(&mut self) -> Result<ViewHandle, Error> {
+        ViewHandle::try_new(&mut self.env, &self.view, self.peer)
+    }
+
     pub fn push_static_deferred_callback(
         &mut self,
         callback: fn(&mut JNIEnv<'local>, &View<'local>),
@@ -38,15 +106,156 @@ impl<'local> CallbackCtx<'local> {
         self.deferred_callbacks
             .push(DeferredCallback::Dynamic(Box::new(callback)));
     }
+
+    /// Run `f` in a new local reference frame with room for `capacity`
+    /// references, deleting those it creates when it returns. Use this in
+    /// loops that create many references.
+    pub fn with_local_frame<T>(
+        &mut self,
+        capacity: i32,
+        f: impl FnOnce(&mut CallbackCtx) -> T,
+    ) -> T {
+        self.try_with_local_frame(capacity, f).unwrap()
+    }
+
+    pub fn try_with_local_frame<T>(
+        &mut self,
+        capacity: i32,
+        f: impl FnOnce(&mut CallbackCtx) -> T,
+    ) -> Result<T, Error> {
+        catch(&mut self.env, |env| env.push_local_frame(capacity))?;
+        self.local_frames += 1;
+        let result = f(self);
+        self.local_frames -= 1;
+        #[cfg(feature = "debug-local-refs")]
+        local_refs::check(&mut self.env, capacity, "with_local_frame");
+        // SAFETY: `f` works with a context of any lifetime, so it can't
+        // have kept references created in the frame.
+        catch(&mut self.env, |env| unsafe {
+            env.pop_local_frame(&JObject::null())
+        })?;
+        Ok(result)
+    }
+
+    /// Call [`ViewPeer::on_timer`] with the returned token after `delay`,
+    /// rounded down to whole milliseconds.
+    pub fn start_timer(&mut self, delay: Duration) -> TimerToken {
+        self.view.start_timer(&mut self.env, delay.as_millis() as _)
+    }
+
+    /// Returns `false` if the timer has already fired or been cancelled.
+    pub fn cancel_timer(&mut self, token: TimerToken) -> bool {
+        self.view.cancel_timer(&mut self.env, token)
+    }
+
+    /// Call [`ViewPeer::do_frame`] on the next frame, from the peer's
+    /// [`FrameSource`](crate::FrameSource).
+    pub fn post_frame_callback(&mut self) {
+        self.try_post_frame_callback().unwrap()
+    }
+
+    pub fn try_post_frame_callback(&mut self) -> Result<(), Error> {
+        match &self.native_frames {
+            Some(frames) => frames.post(&mut self.env, &self.view, self.peer),
+            None => self.view.try_post_frame_callback(&mut self.env),
+        }
+    }
+
+    pub fn remove_frame_callback(&mut self) {
+        self.try_remove_frame_callback().unwrap()
+    }
+
+    pub fn try_remove_frame_callback(&mut self) -> Result<(), Error> {
+        if let Some(scheduler) = &self.frame_scheduler {
+            scheduler.frame_removed();
+        }
+        match &self.native_frames {
+            Some(frames) => {
+                frames.remove();
+                Ok(())
+            }
+            None => self.view.try_remove_frame_callback(&mut self.env),
+        }
+    }
 }
 
-impl CallbackCtx<'_> {
-    pub(crate) fn finish(mut self) {
-        for callback in self.deferred_callbacks {
+impl<'local> CallbackCtx<'local> {
+    /// Run the deferred callbacks and pop the local frame, keeping only
+    /// `result`.
+    pub(crate) fn finish<T: CallbackResult<'local>>(mut self, result: T) -> T {
+        for callback in mem::take(&mut self.deferred_callbacks) {
             match callback {
                 DeferredCallback::Static(f) => f(&mut self.env, &self.view),
                 DeferredCallback::Dynamic(f) => f(&mut self.env, &self.view),
             }
         }
+        #[cfg(feature = "debug-local-refs")]
+        local_refs::check(&mut self.env, LOCAL_FRAME_CAPACITY, self.callback);
+        self.finished = true;
+        catch(&mut self.env, |env| result.pop_local_frame(env)).unwrap()
+    }
+}
+
+impl Drop for CallbackCtx<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            // The callback panicked, so there is no result to keep. Popping
+            // frames is allowed with an exception pending.
+            for _ in 0..=self.local_frames {
+                // SAFETY: No references are kept from the frames.
+                let _ = unsafe { self.env.pop_local_frame(&JObject::null()) };
+            }
+        }
+        #[cfg(feature = "debug-local-refs")]
+        local_refs::uninstall_if_idle(&self.env);
     }
 }
+
+/// A value returned by a callback, which is moved out of its local frame
+/// if it is a local reference.
+pub(crate) trait CallbackResult<'local>: Sized {
+    fn pop_local_frame(self, env: &mut JNIEnv<'local>) -> jni::errors::Result<Self>;
+}
+
+macro_rules! value_results {
+    ($($ty:ty),*) => {
+        $(
+            impl<'local> CallbackResult<'local> for $ty {
+                fn pop_local_frame(self, env: &mut JNIEnv<'local>) -> jni::errors::Result<Self> {
+                    // SAFETY: No references are kept from the frame.
+                    unsafe { env.pop_local_frame(&JObject::null()) }?;
+                    Ok(self)
+                }
+            }
+        )*
+    };
+}
+
+value_results!((), bool, jboolean, jint, jlong);
+
+/// Values returned by [`PeerHandle::with_ctx`](crate::PeerHandle::with_ctx),
+/// which can't refer to the callback's frame.
+impl<'local, T> CallbackResult<'local> for Option<T> {
+    fn pop_local_frame(self, env: &mut JNIEnv<'local>) -> jni::errors::Result<Self> {
+        // SAFETY: No references are kept from the frame.
+        unsafe { env.pop_local_frame(&JObject::null()) }?;
+        Ok(self)
+    }
+}
+
+macro_rules! reference_results {
+    ($($ty:ident),*) => {
+        $(
+            impl<'local> CallbackResult<'local> for $ty<'local> {
+                fn pop_local_frame(self, env: &mut JNIEnv<'local>) -> jni::errors::Result<Self> {
+                    // SAFETY: Only the returned reference is kept from the
+                    // frame, as a new reference in the enclosing one.
+                    let obj = unsafe { env.pop_local_frame(&self) }?;
+                    Ok(obj.into())
+                }
+            }
+        )*
+    };
+}
+
+reference_results!(JObject, JString, JIntArray);
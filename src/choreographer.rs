@@ -0,0 +1,291 @@
+//! Frame callbacks from the NDK's `AChoreographer`, which reach the peer
+//! without a round trip through Java.
+//!
+//! `AChoreographer` can't remove a posted callback, so each one carries the
+//! generation of its peer's frame state when it was posted, and is ignored
+//! if that has changed since, or if the peer has been removed.
+
+use jni::{
+    JNIEnv, JavaVM,
+    objects::{GlobalRef, JObject},
+    sys::jlong,
+};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::c_void,
+    mem,
+    sync::OnceLock,
+};
+
+#[cfg(doc)]
+use crate::{CallbackCtx, ViewPeer};
+use crate::{error::*, frame::*, view::*};
+
+/// Where a peer's frame callbacks come from. Chosen with
+/// [`ViewPeer::frame_source`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FrameSource {
+    /// `Choreographer.postFrameCallback`, through `RustView`.
+    #[default]
+    Java,
+    /// `AChoreographer` on the UI thread. Falls back to [`Java`](Self::Java)
+    /// before API 29.
+    Native,
+}
+
+/// The refresh interval assumed until the system reports one.
+const DEFAULT_FRAME_INTERVAL_NANOS: i64 = 1_000_000_000 / 60;
+
+type GetInstanceFn = unsafe extern "C" fn() -> *mut c_void;
+type FrameCallback64 = unsafe extern "C" fn(i64, *mut c_void);
+type PostFrameCallback64Fn = unsafe extern "C" fn(*mut c_void, FrameCallback64, *mut c_void);
+type VsyncCallback = unsafe extern "C" fn(*const c_void, *mut c_void);
+type PostVsyncCallbackFn = unsafe extern "C" fn(*mut c_void, VsyncCallback, *mut c_void);
+type RefreshRateCallback = unsafe extern "C" fn(i64, *mut c_void);
+type RegisterRefreshRateCallbackFn =
+    unsafe extern "C" fn(*mut c_void, RefreshRateCallback, *mut c_void);
+type FrameDataFn = unsafe extern "C" fn(*const c_void) -> i64;
+type FrameDataLenFn = unsafe extern "C" fn(*const c_void) -> usize;
+type FrameTimelineFn = unsafe extern "C" fn(*const c_void, usize) -> i64;
+
+/// The functions behind `AChoreographerFrameCallbackData`, from API 33.
+struct FrameDataFns {
+    post_vsync_callback: PostVsyncCallbackFn,
+    frame_time_nanos: FrameDataFn,
+    timelines_length: FrameDataLenFn,
+    preferred_timeline_index: FrameDataLenFn,
+    expected_presentation_time_nanos: FrameTimelineFn,
+    deadline_nanos: FrameTimelineFn,
+}
+
+struct ChoreographerFns {
+    get_instance: GetInstanceFn,
+    post_frame_callback_64: PostFrameCallback64Fn,
+    /// From API 30.
+    register_refresh_rate_callback: Option<RegisterRefreshRateCallbackFn>,
+    frame_data: Option<FrameDataFns>,
+}
+
+/// Look up a function in `libandroid`, which can't be linked directly
+/// while the minimum supported API level is lower than the one adding it.
+///
+/// # Safety
+///
+/// `F` must be the function pointer type declared for `name` in
+/// `<android/choreographer.h>`.
+unsafe fn lookup<F>(name: &std::ffi::CStr) -> Option<F> {
+    // SAFETY: `name` is NUL-terminated.
+    let symbol = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr()) };
+    // SAFETY: The caller guarantees the signature.
+    (!symbol.is_null()).then(|| unsafe { mem::transmute_copy::<*mut c_void, F>(&symbol) })
+}
+
+/// The `AChoreographer` functions, or `None` before API 29.
+fn choreographer_fns() -> Option<&'static ChoreographerFns> {
+    static FNS: OnceLock<Option<ChoreographerFns>> = OnceLock::new();
+    FNS.get_or_init(|| {
+        // SAFETY: Each type matches the declaration of its function.
+        unsafe {
+            let frame_data = || {
+                Some(FrameDataFns {
+                    post_vsync_callback: lookup(c"AChoreographer_postVsyncCallback")?,
+                    frame_time_nanos: lookup(c"AChoreographerFrameCallbackData_getFrameTimeNanos")?,
+                    timelines_length: lookup(
+                        c"AChoreographerFrameCallbackData_getFrameTimelinesLength",
+                    )?,
+                    preferred_timeline_index: lookup(
+                        c"AChoreographerFrameCallbackData_getPreferredFrameTimelineIndex",
+                    )?,
+                    expected_presentation_time_nanos: lookup(
+                        c"AChoreographerFrameCallbackData_getFrameTimelineExpectedPresentationTimeNanos",
+                    )?,
+                    deadline_nanos: lookup(
+                        c"AChoreographerFrameCallbackData_getFrameTimelineDeadlineNanos",
+                    )?,
+                })
+            };
+            Some(ChoreographerFns {
+                get_instance: lookup(c"AChoreographer_getInstance")?,
+                post_frame_callback_64: lookup(c"AChoreographer_postFrameCallback64")?,
+                register_refresh_rate_callback: lookup(
+                    c"AChoreographer_registerRefreshRateCallback",
+                ),
+                frame_data: frame_data(),
+            })
+        }
+    })
+    .as_ref()
+}
+
+static VM: OnceLock<JavaVM> = OnceLock::new();
+
+thread_local! {
+    /// The vsync period reported to this thread's refresh rate callback.
+    static FRAME_INTERVAL_NANOS: Cell<Option<i64>> = const { Cell::new(None) };
+}
+
+unsafe extern "C" fn refresh_rate_changed(vsync_period_nanos: i64, _data: *mut c_void) {
+    FRAME_INTERVAL_NANOS.set(Some(vsync_period_nanos));
+}
+
+fn frame_interval_nanos() -> i64 {
+    FRAME_INTERVAL_NANOS
+        .get()
+        .unwrap_or(DEFAULT_FRAME_INTERVAL_NANOS)
+}
+
+/// The native frame state of a peer that uses [`FrameSource::Native`].
+pub(crate) struct NativeFrames {
+    fns: &'static ChoreographerFns,
+    /// Incremented to cancel the callbacks already posted.
+    generation: Cell<u64>,
+    /// Whether a callback of the current generation is pending.
+    pending: Cell<bool>,
+    /// The view, held while callbacks are posted so that `do_frame` can be
+    /// given one.
+    view: RefCell<Option<GlobalRef>>,
+}
+
+/// The data passed with a posted callback.
+struct Posted {
+    peer: jlong,
+    generation: u64,
+}
+
+impl NativeFrames {
+    /// Returns `None` if `AChoreographer` frame callbacks aren't available.
+    pub(crate) fn new() -> Option<Self> {
+        Some(Self {
+            fns: choreographer_fns()?,
+            generation: Cell::new(0),
+            pending: Cell::new(false),
+            view: RefCell::new(None),
+        })
+    }
+
+    /// Post a frame callback for `peer`, unless one is already pending.
+    /// Must be called on the UI thread.
+    pub(crate) fn post(&self, env: &mut JNIEnv, view: &View, peer: jlong) -> Result<(), Error> {
+        if self.pending.get() {
+            return Ok(());
+        }
+        if self.view.borrow().is_none() {
+            let global = catch(env, |env| {
+                if VM.get().is_none() {
+                    let _ = VM.set(env.get_java_vm()?);
+                }
+                env.new_global_ref(&view.0)
+            })?;
+            *self.view.borrow_mut() = Some(global);
+        }
+        let fns = self.fns;
+        let data = Box::into_raw(Box::new(Posted {
+            peer,
+            generation: self.generation.get(),
+        }));
+        // SAFETY: This is the UI thread, whose looper the choreographer
+        // uses. `data` is reclaimed by the callback, which is always called.
+        unsafe {
+            let choreographer = (fns.get_instance)();
+            if let Some(frame_data) = &fns.frame_data {
+                (frame_data.post_vsync_callback)(choreographer, on_vsync, data.cast());
+            } else {
+                if let Some(register) = fns.register_refresh_rate_callback
+                    && FRAME_INTERVAL_NANOS.get().is_none()
+                {
+                    FRAME_INTERVAL_NANOS.set(Some(DEFAULT_FRAME_INTERVAL_NANOS));
+                    register(choreographer, refresh_rate_changed, std::ptr::null_mut());
+                }
+                (fns.post_frame_callback_64)(choreographer, on_frame, data.cast());
+            }
+        }
+        self.pending.set(true);
+        Ok(())
+    }
+
+    /// Cancel the pending callback, if any, and release the view.
+    pub(crate) fn remove(&self) {
+        self.generation.set(self.generation.get() + 1);
+        self.pending.set(false);
+        self.view.take();
+    }
+}
+
+unsafe extern "C" fn on_frame(frame_time_nanos: i64, data: *mut c_void) {
+    // SAFETY: `data` came from `Box::into_raw` in `post`.
+    let posted = unsafe { Box::from_raw(data.cast::<Posted>()) };
+    let interval = frame_interval_nanos();
+    // As in `RustView.doFrame`, assume the frame is due by the next vsync
+    // and shown at the one after.
+    deliver(
+        &posted,
+        FrameInfo {
+            frame_time_nanos,
+            expected_presentation_time_nanos: frame_time_nanos + 2 * interval,
+            deadline_nanos: frame_time_nanos + interval,
+            frame_interval_nanos: interval,
+        },
+    );
+}
+
+unsafe extern "C" fn on_vsync(frame_data: *const c_void, data: *mut c_void) {
+    // SAFETY: `data` came from `Box::into_raw` in `post`.
+    let posted = unsafe { Box::from_raw(data.cast::<Posted>()) };
+    let fns = choreographer_fns().unwrap().frame_data.as_ref().unwrap();
+    // SAFETY: `frame_data` is valid for the duration of this callback, and
+    // the indices are within the number of timelines.
+    let frame = unsafe {
+        let preferred = (fns.preferred_timeline_index)(frame_data);
+        // Timelines are one vsync apart.
+        let frame_interval_nanos = if (fns.timelines_length)(frame_data) > preferred + 1 {
+            (fns.expected_presentation_time_nanos)(frame_data, preferred + 1)
+                - (fns.expected_presentation_time_nanos)(frame_data, preferred)
+        } else {
+            frame_interval_nanos()
+        };
+        FrameInfo {
+            frame_time_nanos: (fns.frame_time_nanos)(frame_data),
+            expected_presentation_time_nanos: (fns.expected_presentation_time_nanos)(
+                frame_data, preferred,
+            ),
+            deadline_nanos: (fns.deadline_nanos)(frame_data, preferred),
+            frame_interval_nanos,
+        }
+    };
+    deliver(&posted, frame);
+}
+
+fn deliver(posted: &Posted, frame: FrameInfo) {
+    let Some(frames) = native_frames(posted.peer) else {
+        return;
+    };
+    if frames.generation.get() != posted.generation || !frames.pending.get() {
+        return;
+    }
+    frames.pending.set(false);
+    let Some(global) = frames.view.borrow().clone() else {
+        return;
+    };
+    let Some(env) = VM.get().and_then(|vm| vm.get_env().ok()) else {
+        return;
+    };
+    // There is no native method call here to delete the local references
+    // created on return, so make a frame for them.
+    if env.push_local_frame(1).is_err() {
+        return;
+    }
+    if let Ok(view) = env.new_local_ref(&global) {
+        // SAFETY: `env` isn't used again until `deliver_frame` returns.
+        let callback_env = unsafe { env.unsafe_clone() };
+        deliver_frame(callback_env, View(view), posted.peer, frame);
+    }
+    // With no Java caller to receive it, an exception from the peer, such
+    // as a panic, would otherwise be left pending for whatever JNI call
+    // comes next. The callback's own frame has been popped either way.
+    if env.exception_check().unwrap_or(false) {
+        let _ = env.exception_describe();
+        let _ = env.exception_clear();
+    }
+    // SAFETY: No references are kept from the frame.
+    let _ = unsafe { env.pop_local_frame(&JObject::null()) };
+}
@@ -0,0 +1,131 @@
+//! An owned copy of `android.content.res.Configuration`.
+
+use jni::{
+    JNIEnv,
+    objects::{JObject, JString},
+    sys::jlong,
+};
+use ndk::configuration::{
+    Keyboard, KeysHidden, LayoutDir, NavHidden, Navigation, Orientation, UiModeNight, UiModeType,
+};
+use num_enum::FromPrimitive;
+
+use crate::{error::*, view::*};
+
+const UI_MODE_TYPE_MASK: i32 = 0x0f;
+const UI_MODE_NIGHT_MASK: i32 = 0x30;
+const UI_MODE_NIGHT_SHIFT: i32 = 4;
+const SCREENLAYOUT_LAYOUTDIR_MASK: i32 = 0xc0;
+const SCREENLAYOUT_LAYOUTDIR_SHIFT: i32 = 6;
+
+/// The parts of a `Configuration` that affect how a view draws and
+/// handles input.
+///
+/// The `ndk` enums are used for the fields that have one, since
+/// `AConfiguration` shares their values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Configuration {
+    pub font_scale: f32,
+    /// The user's preferred locales, most preferred first, as BCP 47
+    /// language tags.
+    pub locales: Vec<String>,
+    pub orientation: Orientation,
+    pub ui_mode_type: UiModeType,
+    pub ui_mode_night: UiModeNight,
+    pub screen_width_dp: i32,
+    pub screen_height_dp: i32,
+    pub smallest_screen_width_dp: i32,
+    pub density_dpi: i32,
+    /// The kind of hardware keyboard attached, if any.
+    pub keyboard: Keyboard,
+    /// Whether any keyboard, including the soft keyboard, is available.
+    pub keyboard_hidden: KeysHidden,
+    /// Whether the hardware keyboard is available.
+    pub hard_keyboard_hidden: KeysHidden,
+    /// The kind of navigation device attached, such as a D-pad.
+    pub navigation: Navigation,
+    pub navigation_hidden: NavHidden,
+    pub layout_direction: LayoutDir,
+}
+
+impl Configuration {
+    pub fn is_night_mode(&self) -> bool {
+        self.ui_mode_night == UiModeNight::Yes
+    }
+
+    pub fn is_rtl(&self) -> bool {
+        self.layout_direction == LayoutDir::Rtl
+    }
+
+    /// Copy the fields of a Java `Configuration`.
+    pub fn from_java(env: &mut JNIEnv, config: &JObject) -> Self {
+        Self::try_from_java(env, config).unwrap()
+    }
+
+    pub fn try_from_java(env: &mut JNIEnv, config: &JObject) -> Result<Self, Error> {
+        catch(env, |env| {
+            let mut int_field = |name| env.get_field(config, name, "I")?.i();
+            let ui_mode = int_field("uiMode")?;
+            let screen_layout = int_field("screenLayout")?;
+            let orientation = int_field("orientation")?;
+            let screen_width_dp = int_field("screenWidthDp")?;
+            let screen_height_dp = int_field("screenHeightDp")?;
+            let smallest_screen_width_dp = int_field("smallestScreenWidthDp")?;
+            let density_dpi = int_field("densityDpi")?;
+            let keyboard = int_field("keyboard")?;
+            let keyboard_hidden = int_field("keyboardHidden")?;
+            let hard_keyboard_hidden = int_field("hardKeyboardHidden")?;
+            let navigation = int_field("navigation")?;
+            let navigation_hidden = int_field("navigationHidden")?;
+            let font_scale = env.get_field(config, "fontScale", "F")?.f()?;
+            let locales = env
+                .call_method(config, "getLocales", "()Landroid/os/LocaleList;", &[])?
+                .l()?;
+            let tags: JString = env
+                .call_method(&locales, "toLanguageTags", "()Ljava/lang/String;", &[])?
+                .l()?
+                .into();
+            let tags: String = env.get_string(&tags)?.into();
+            Ok(Self {
+                font_scale,
+                locales: tags
+                    .split(',')
+                    .filter(|tag| !tag.is_empty())
+                    .map(String::from)
+                    .collect(),
+                orientation: Orientation::from_primitive(orientation),
+                ui_mode_type: UiModeType::from_primitive(ui_mode & UI_MODE_TYPE_MASK),
+                ui_mode_night: UiModeNight::from_primitive(
+                    (ui_mode & UI_MODE_NIGHT_MASK) >> UI_MODE_NIGHT_SHIFT,
+                ),
+                screen_width_dp,
+                screen_height_dp,
+                smallest_screen_width_dp,
+                density_dpi,
+                keyboard: Keyboard::from_primitive(keyboard),
+                keyboard_hidden: KeysHidden::from_primitive(keyboard_hidden),
+                hard_keyboard_hidden: KeysHidden::from_primitive(hard_keyboard_hidden),
+                navigation: Navigation::from_primitive(navigation),
+                navigation_hidden: NavHidden::from_primitive(navigation_hidden),
+                layout_direction: LayoutDir::from_primitive(
+                    (screen_layout & SCREENLAYOUT_LAYOUTDIR_MASK) >> SCREENLAYOUT_LAYOUTDIR_SHIFT,
+                ),
+            })
+        })
+    }
+}
+
+pub(crate) extern "system" fn on_configuration_changed<'local>(
+    env: JNIEnv<'local>,
+    view: View<'local>,
+    peer: jlong,
+    new_config: JObject<'local>,
+) {
+    with_peer(env, view, peer, |ctx, peer| {
+        ctx.invalidate_display_metrics();
+        if ctx.callbacks.contains(ViewCallbacks::CONFIGURATION_CHANGED) {
+            let config = Configuration::from_java(&mut ctx.env, &new_config);
+            peer.on_configuration_changed(ctx, &config);
+        }
+    })
+}
@@ -1,21 +1,36 @@
-use jni::{JNIEnv, objects::JObject, sys::jfloat};
+use jni::{JNIEnv, objects::JObject};
+
+use crate::{configuration::*, display::*, error::*};
 
 #[repr(transparent)]
 pub struct Context<'local>(pub JObject<'local>);
 
 impl<'local> Context<'local> {
     pub fn resources(&self, env: &mut JNIEnv<'local>) -> Resources<'local> {
-        Resources(
-            env.call_method(
-                &self.0,
-                "getResources",
-                "()Landroid/content/res/Resources;",
-                &[],
-            )
-            .unwrap()
-            .l()
-            .unwrap(),
-        )
+        self.try_resources(env).unwrap()
+    }
+
+    pub fn try_resources(&self, env: &mut JNIEnv<'local>) -> Result<Resources<'local>, Error> {
+        catch(env, |env| {
+            Ok(Resources(
+                env.call_method(
+                    &self.0,
+                    "getResources",
+                    "()Landroid/content/res/Resources;",
+                    &[],
+                )?
+                .l()?,
+            ))
+        })
+    }
+
+    /// The current configuration of this context's resources.
+    pub fn configuration(&self, env: &mut JNIEnv<'local>) -> Configuration {
+        self.try_configuration(env).unwrap()
+    }
+
+    pub fn try_configuration(&self, env: &mut JNIEnv<'local>) -> Result<Configuration, Error> {
+        self.try_resources(env)?.try_configuration(env)
     }
 
     // TODO: more methods?
@@ -25,26 +40,37 @@ impl<'local> Context<'local> {
 pub struct Resources<'local>(pub JObject<'local>);
 
 impl<'local> Resources<'local> {
-    pub fn display_metrics(&self, env: &mut JNIEnv<'local>) -> DisplayMetrics<'local> {
-        DisplayMetrics(
+    pub fn display_metrics(&self, env: &mut JNIEnv<'local>) -> DisplayMetrics {
+        self.try_display_metrics(env).unwrap()
+    }
+
+    pub fn try_display_metrics(&self, env: &mut JNIEnv<'local>) -> Result<DisplayMetrics, Error> {
+        let metrics = catch(env, |env| {
             env.call_method(
                 &self.0,
                 "getDisplayMetrics",
                 "()Landroid/util/DisplayMetrics;",
                 &[],
-            )
-            .unwrap()
+            )?
             .l()
-            .unwrap(),
-        )
+        })?;
+        DisplayMetrics::try_from_java(env, &metrics)
     }
-}
 
-#[repr(transparent)]
-pub struct DisplayMetrics<'local>(pub JObject<'local>);
+    pub fn configuration(&self, env: &mut JNIEnv<'local>) -> Configuration {
+        self.try_configuration(env).unwrap()
+    }
 
-impl<'local> DisplayMetrics<'local> {
-    pub fn density(&self, env: &mut JNIEnv<'local>) -> jfloat {
-        env.get_field(&self.0, "density", "F").unwrap().f().unwrap()
+    pub fn try_configuration(&self, env: &mut JNIEnv<'local>) -> Result<Configuration, Error> {
+        let config = catch(env, |env| {
+            env.call_method(
+                &self.0,
+                "getConfiguration",
+                "()Landroid/content/res/Configuration;",
+                &[],
+            )?
+            .l()
+        })?;
+        Configuration::try_from_java(env, &config)
     }
 }
@@ -0,0 +1,320 @@
+//! Wrappers for `android.view.Display` and `android.util.DisplayMetrics`.
+
+use jni::{
+    JNIEnv,
+    objects::{JFloatArray, JIntArray, JObject, JObjectArray},
+    sys::{jint, jlong},
+};
+
+use crate::{callback_ctx::*, error::*, util::*, view::*};
+
+/// An owned copy of `android.util.DisplayMetrics`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DisplayMetrics {
+    /// The logical density, as the number of pixels per dp.
+    pub density: f32,
+    /// The screen density in dots per inch, rounded to a density bucket.
+    pub density_dpi: i32,
+    /// The density used for text, which includes the user's font scale.
+    pub scaled_density: f32,
+    /// The exact physical pixels per inch in the X dimension.
+    pub xdpi: f32,
+    /// The exact physical pixels per inch in the Y dimension.
+    pub ydpi: f32,
+    pub width_pixels: i32,
+    pub height_pixels: i32,
+}
+
+impl DisplayMetrics {
+    /// Copy the fields of a Java `DisplayMetrics`.
+    pub fn from_java(env: &mut JNIEnv, metrics: &JObject) -> Self {
+        Self::try_from_java(env, metrics).unwrap()
+    }
+
+    pub fn try_from_java(env: &mut JNIEnv, metrics: &JObject) -> Result<Self, Error> {
+        catch(env, |env| {
+            Ok(Self {
+                density: env.get_field(metrics, "density", "F")?.f()?,
+                density_dpi: env.get_field(metrics, "densityDpi", "I")?.i()?,
+                scaled_density: env.get_field(metrics, "scaledDensity", "F")?.f()?,
+                xdpi: env.get_field(metrics, "xdpi", "F")?.f()?,
+                ydpi: env.get_field(metrics, "ydpi", "F")?.f()?,
+                width_pixels: env.get_field(metrics, "widthPixels", "I")?.i()?,
+                height_pixels: env.get_field(metrics, "heightPixels", "I")?.i()?,
+            })
+        })
+    }
+}
+
+impl CallbackCtx<'_> {
+    /// The display metrics of the view's resources, cached until the
+    /// configuration or display changes.
+    pub fn display_metrics(&mut self) -> DisplayMetrics {
+        self.try_display_metrics().unwrap()
+    }
+
+    pub fn try_display_metrics(&mut self) -> Result<DisplayMetrics, Error> {
+        if let Some(metrics) = self.display_metrics_cache.as_ref().and_then(|c| c.get()) {
+            return Ok(metrics);
+        }
+        let metrics = self
+            .view
+            .try_context(&mut self.env)?
+            .try_resources(&mut self.env)?
+            .try_display_metrics(&mut self.env)?;
+        if let Some(cache) = &self.display_metrics_cache {
+            cache.set(Some(metrics));
+        }
+        Ok(metrics)
+    }
+
+    pub(crate) fn invalidate_display_metrics(&self) {
+        if let Some(cache) = &self.display_metrics_cache {
+            cache.set(None);
+        }
+    }
+}
+
+/// The rotation of a display from its natural orientation, as returned by
+/// `Display.getRotation`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    #[default]
+    Rotation0,
+    Rotation90,
+    Rotation180,
+    Rotation270,
+}
+
+impl Rotation {
+    fn from_java(rotation: jint) -> Self {
+        match rotation {
+            1 => Self::Rotation90,
+            2 => Self::Rotation180,
+            3 => Self::Rotation270,
+            _ => Self::Rotation0,
+        }
+    }
+
+    /// The rotation in degrees, clockwise.
+    pub fn degrees(self) -> u32 {
+        match self {
+            Self::Rotation0 => 0,
+            Self::Rotation90 => 90,
+            Self::Rotation180 => 180,
+            Self::Rotation270 => 270,
+        }
+    }
+}
+
+/// A combination of resolution and refresh rate supported by a display, as
+/// in `Display.Mode`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DisplayMode {
+    pub mode_id: i32,
+    pub physical_width: i32,
+    pub physical_height: i32,
+    pub refresh_rate: f32,
+    /// Refresh rates the display can switch to from this mode without a
+    /// visible interruption. Always empty before API 31.
+    pub alternative_refresh_rates: Vec<f32>,
+}
+
+impl DisplayMode {
+    fn query(env: &mut JNIEnv, mode: &JObject) -> jni::errors::Result<Self> {
+        let alternative_refresh_rates = if sdk_int(env) >= 31 {
+            let rates: JFloatArray = env
+                .call_method(mode, "getAlternativeRefreshRates", "()[F", &[])?
+                .l()?
+                .into();
+            let mut buf = vec![0.0; env.get_array_length(&rates)? as usize];
+            env.get_float_array_region(&rates, 0, &mut buf)?;
+            env.delete_local_ref(rates)?;
+            buf
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            mode_id: env.call_method(mode, "getModeId", "()I", &[])?.i()?,
+            physical_width: env.call_method(mode, "getPhysicalWidth", "()I", &[])?.i()?,
+            physical_height: env
+                .call_method(mode, "getPhysicalHeight", "()I", &[])?
+                .i()?,
+            refresh_rate: env.call_method(mode, "getRefreshRate", "()F", &[])?.f()?,
+            alternative_refresh_rates,
+        })
+    }
+}
+
+/// A type of HDR content, as in the `HDR_TYPE_` constants of
+/// `Display.HdrCapabilities`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HdrType {
+    DolbyVision,
+    Hdr10,
+    Hlg,
+    Hdr10Plus,
+    Unknown(i32),
+}
+
+impl HdrType {
+    fn from_java(hdr_type: jint) -> Self {
+        match hdr_type {
+            1 => Self::DolbyVision,
+            2 => Self::Hdr10,
+            3 => Self::Hlg,
+            4 => Self::Hdr10Plus,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// The HDR capabilities of a display, as in `Display.HdrCapabilities`.
+/// Luminance values are in nits, and are 0 if unknown.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HdrCapabilities {
+    pub supported_hdr_types: Vec<HdrType>,
+    pub desired_max_luminance: f32,
+    pub desired_max_average_luminance: f32,
+    pub desired_min_luminance: f32,
+}
+
+#[repr(transparent)]
+pub struct Display<'local>(pub JObject<'local>);
+
+impl<'local> Display<'local> {
+    pub fn display_id(&self, env: &mut JNIEnv<'local>) -> jint {
+        self.try_display_id(env).unwrap()
+    }
+
+    pub fn try_display_id(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getDisplayId", "()I", &[])?.i()
+        })
+    }
+
+    pub fn rotation(&self, env: &mut JNIEnv<'local>) -> Rotation {
+        self.try_rotation(env).unwrap()
+    }
+
+    pub fn try_rotation(&self, env: &mut JNIEnv<'local>) -> Result<Rotation, Error> {
+        catch(env, |env| {
+            Ok(Rotation::from_java(
+                env.call_method(&self.0, "getRotation", "()I", &[])?.i()?,
+            ))
+        })
+    }
+
+    /// The refresh rate of the current mode, in frames per second.
+    pub fn refresh_rate(&self, env: &mut JNIEnv<'local>) -> f32 {
+        self.try_refresh_rate(env).unwrap()
+    }
+
+    pub fn try_refresh_rate(&self, env: &mut JNIEnv<'local>) -> Result<f32, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getRefreshRate", "()F", &[])?.f()
+        })
+    }
+
+    pub fn mode(&self, env: &mut JNIEnv<'local>) -> DisplayMode {
+        self.try_mode(env).unwrap()
+    }
+
+    pub fn try_mode(&self, env: &mut JNIEnv<'local>) -> Result<DisplayMode, Error> {
+        catch(env, |env| {
+            let mode = env
+                .call_method(&self.0, "getMode", "()Landroid/view/Display$Mode;", &[])?
+                .l()?;
+            let result = DisplayMode::query(env, &mode);
+            env.delete_local_ref(mode)?;
+            result
+        })
+    }
+
+    pub fn supported_modes(&self, env: &mut JNIEnv<'local>) -> Vec<DisplayMode> {
+        self.try_supported_modes(env).unwrap()
+    }
+
+    pub fn try_supported_modes(&self, env: &mut JNIEnv<'local>) -> Result<Vec<DisplayMode>, Error> {
+        catch(env, |env| {
+            let modes: JObjectArray = env
+                .call_method(
+                    &self.0,
+                    "getSupportedModes",
+                    "()[Landroid/view/Display$Mode;",
+                    &[],
+                )?
+                .l()?
+                .into();
+            let len = env.get_array_length(&modes)?;
+            let mut result = Vec::with_capacity(len as usize);
+            for i in 0..len {
+                let mode = env.get_object_array_element(&modes, i)?;
+                result.push(DisplayMode::query(env, &mode)?);
+                env.delete_local_ref(mode)?;
+            }
+            env.delete_local_ref(modes)?;
+            Ok(result)
+        })
+    }
+
+    /// The HDR capabilities of the display, or `None` if it doesn't
+    /// support HDR.
+    pub fn hdr_capabilities(&self, env: &mut JNIEnv<'local>) -> Option<HdrCapabilities> {
+        self.try_hdr_capabilities(env).unwrap()
+    }
+
+    pub fn try_hdr_capabilities(
+        &self,
+        env: &mut JNIEnv<'local>,
+    ) -> Result<Option<HdrCapabilities>, Error> {
+        catch(env, |env| {
+            let caps = env
+                .call_method(
+                    &self.0,
+                    "getHdrCapabilities",
+                    "()Landroid/view/Display$HdrCapabilities;",
+                    &[],
+                )?
+                .l()?;
+            if caps.is_null() {
+                return Ok(None);
+            }
+            let types: JIntArray = env
+                .call_method(&caps, "getSupportedHdrTypes", "()[I", &[])?
+                .l()?
+                .into();
+            let mut buf = vec![0; env.get_array_length(&types)? as usize];
+            env.get_int_array_region(&types, 0, &mut buf)?;
+            env.delete_local_ref(types)?;
+            let result = HdrCapabilities {
+                supported_hdr_types: buf.into_iter().map(HdrType::from_java).collect(),
+                desired_max_luminance: env
+                    .call_method(&caps, "getDesiredMaxLuminance", "()F", &[])?
+                    .f()?,
+                desired_max_average_luminance: env
+                    .call_method(&caps, "getDesiredMaxAverageLuminance", "()F", &[])?
+                    .f()?,
+                desired_min_luminance: env
+                    .call_method(&caps, "getDesiredMinLuminance", "()F", &[])?
+                    .f()?,
+            };
+            env.delete_local_ref(caps)?;
+            Ok(Some(result))
+        })
+    }
+}
+
+pub(crate) extern "system" fn on_display_changed<'local>(
+    env: JNIEnv<'local>,
+    view: View<'local>,
+    peer: jlong,
+    display: Display<'local>,
+) {
+    with_peer(env, view, peer, |ctx, peer| {
+        ctx.invalidate_display_metrics();
+        if ctx.callbacks.contains(ViewCallbacks::DISPLAY_CHANGED) {
+            peer.on_display_changed(ctx, &display);
+        }
+    })
+}
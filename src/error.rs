@@ -0,0 +1,132 @@
+//! Errors from calls into the Java side of a view.
+
+use jni::{JNIEnv, objects::JObject};
+use std::fmt;
+
+/// A Java exception that was pending after a JNI call.
+///
+/// By the time this value exists, the exception has been cleared,
+/// so further JNI calls can be made safely.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JavaException {
+    /// Fully qualified class name of the exception,
+    /// e.g. `java.lang.IllegalStateException`.
+    pub class: String,
+    /// The result of `Throwable.getMessage`, if any.
+    pub message: Option<String>,
+}
+
+impl fmt::Display for JavaException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.class, message),
+            None => f.write_str(&self.class),
+        }
+    }
+}
+
+/// An error returned by the fallible (`try_*`) wrappers in this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// The Java side threw an exception, which has been cleared.
+    JavaException(JavaException),
+    /// A JNI failure that did not leave an exception pending,
+    /// such as a null object or a wrong return type.
+    Jni(jni::errors::Error),
+    /// A view class was registered in a way that conflicts with an earlier
+    /// registration.
+    ConflictingRegistration(String),
+    /// A view class was registered with a base class that it doesn't extend.
+    InvalidViewClass(String),
+}
+
+impl Error {
+    /// Convert a JNI error into an [`Error`], taking and clearing
+    /// any pending Java exception.
+    ///
+    /// This is useful for applications that make their own JNI calls
+    /// and want to handle failures the same way as this crate.
+    pub fn from_jni(env: &mut JNIEnv, err: jni::errors::Error) -> Self {
+        match take_pending_exception(env) {
+            Some(exception) => Self::JavaException(exception),
+            None => Self::Jni(err),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::JavaException(exception) => write!(f, "Java exception: {exception}"),
+            Self::Jni(err) => write!(f, "JNI error: {err}"),
+            Self::ConflictingRegistration(message) => {
+                write!(f, "conflicting view class registration: {message}")
+            }
+            Self::InvalidViewClass(message) => write!(f, "invalid view class: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::JavaException(_)
+            | Self::ConflictingRegistration(_)
+            | Self::InvalidViewClass(_) => None,
+            Self::Jni(err) => Some(err),
+        }
+    }
+}
+
+/// Run a sequence of JNI calls, converting any failure into an [`Error`].
+pub(crate) fn catch<'local, T>(
+    env: &mut JNIEnv<'local>,
+    f: impl FnOnce(&mut JNIEnv<'local>) -> jni::errors::Result<T>,
+) -> Result<T, Error> {
+    f(env).map_err(|err| Error::from_jni(env, err))
+}
+
+fn take_pending_exception(env: &mut JNIEnv) -> Option<JavaException> {
+    if !env.exception_check().unwrap_or(false) {
+        return None;
+    }
+    let throwable = env.exception_occurred().ok()?;
+    env.exception_clear().ok()?;
+    let class = string_result(env, &throwable, |env, throwable| {
+        let class = env
+            .call_method(throwable, "getClass", "()Ljava/lang/Class;", &[])?
+            .l()?;
+        env.call_method(&class, "getName", "()Ljava/lang/String;", &[])?
+            .l()
+    });
+    let message = string_result(env, &throwable, |env, throwable| {
+        env.call_method(throwable, "getMessage", "()Ljava/lang/String;", &[])?
+            .l()
+    });
+    Some(JavaException {
+        class: class.unwrap_or_else(|| "java.lang.Throwable".into()),
+        message,
+    })
+}
+
+/// Call a method returning a Java string while describing an exception,
+/// making sure that a failure doesn't leave a second exception pending.
+fn string_result<'local>(
+    env: &mut JNIEnv<'local>,
+    throwable: &JObject<'local>,
+    f: impl FnOnce(&mut JNIEnv<'local>, &JObject<'local>) -> jni::errors::Result<JObject<'local>>,
+) -> Option<String> {
+    let result = f(env, throwable).and_then(|s| {
+        if s.as_raw().is_null() {
+            return Ok(None);
+        }
+        Ok(Some(env.get_string(&s.into())?.into()))
+    });
+    match result {
+        Ok(s) => s,
+        Err(_) => {
+            let _ = env.exception_clear();
+            None
+        }
+    }
+}
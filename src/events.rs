@@ -1,145 +1,242 @@
-use dpi::PhysicalPosition;
 use jni::{
     JNIEnv,
-    objects::JObject,
-    sys::{jfloat, jint, jlong},
+    objects::{GlobalRef, JFloatArray, JLongArray, JObject},
+    sys::{jfloat, jint, jlong, jobject},
 };
 use ndk::event::{
     Axis, ButtonState, KeyAction, KeyEventFlags, Keycode, MetaState, MotionAction,
     MotionEventFlags, Source, ToolType,
 };
 use num_enum::FromPrimitive;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    ffi::c_void,
+    mem,
+    ops::Deref,
+    ptr::NonNull,
+    sync::OnceLock,
+    time::Duration,
+};
 use ui_events::{
-    ScrollDelta,
     keyboard::{KeyboardEvent, Modifiers},
-    pointer::{ContactGeometry, PointerEvent, PointerId, PointerState, PointerUpdate},
+    pointer::{PointerEvent, PointerId, PointerState, PointerUpdate},
+};
+
+#[cfg(doc)]
+use crate::View;
+use crate::{
+    CallbackCtx, GestureEvent, GestureRecognizer, KeyEventSnapshot, LongPressRecognizer,
+    MotionEventSnapshot, TimerToken, ViewConfiguration, error::*, util::uptime_nanos,
 };
 
-use crate::ViewConfiguration;
+/// `ViewConfiguration.DEFAULT_LONG_PRESS_TIMEOUT`, in milliseconds.
+const DEFAULT_LONG_PRESS_TIMEOUT: i32 = 400;
+/// `ViewConfiguration.DEFAULT_MULTI_PRESS_TIMEOUT`, in milliseconds.
+const DEFAULT_MULTI_PRESS_TIMEOUT: i32 = 300;
+
+const EVENT_SNAPSHOTS_CLASS: &str = "org/linebender/android/rustview/EventSnapshots";
+
+/// The `EventSnapshots` class next to the first registered base class,
+/// used outside of callbacks.
+static EVENT_SNAPSHOTS: OnceLock<GlobalRef> = OnceLock::new();
+
+thread_local! {
+    /// The `EventSnapshots` class next to the base class of the view whose
+    /// callback is running on this thread.
+    static CURRENT_EVENT_SNAPSHOTS: RefCell<Option<GlobalRef>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn set_event_snapshots_class(class: GlobalRef) {
+    let _ = EVENT_SNAPSHOTS.set(class);
+}
+
+/// Restores the previous `EventSnapshots` class of the thread when dropped.
+pub(crate) struct EventSnapshotsScope(Option<GlobalRef>);
+
+impl EventSnapshotsScope {
+    /// Use `class` for the snapshots taken on this thread until the scope
+    /// is dropped, such as for the duration of a callback.
+    pub(crate) fn enter(class: Option<GlobalRef>) -> Self {
+        Self(CURRENT_EVENT_SNAPSHOTS.replace(class))
+    }
+}
+
+impl Drop for EventSnapshotsScope {
+    fn drop(&mut self) {
+        CURRENT_EVENT_SNAPSHOTS.set(self.0.take());
+    }
+}
+
+fn event_snapshots_class(env: &mut JNIEnv) -> jni::errors::Result<GlobalRef> {
+    if let Some(class) = CURRENT_EVENT_SNAPSHOTS.with_borrow(|class| class.clone()) {
+        return Ok(class);
+    }
+    if let Some(class) = EVENT_SNAPSHOTS.get() {
+        return Ok(class.clone());
+    }
+    let class = env.find_class(EVENT_SNAPSHOTS_CLASS)?;
+    let class = env.new_global_ref(class)?;
+    Ok(EVENT_SNAPSHOTS.get_or_init(|| class).clone())
+}
 
 #[repr(transparent)]
 pub struct KeyEvent<'local>(pub JObject<'local>);
 
 impl<'local> KeyEvent<'local> {
     pub fn device_id(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.call_method(&self.0, "getDeviceId", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_device_id(env).unwrap()
+    }
+
+    pub fn try_device_id(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getDeviceId", "()I", &[])?.i()
+        })
     }
 
     pub fn source(&self, env: &mut JNIEnv<'local>) -> Source {
-        Source::from_primitive(
-            env.call_method(&self.0, "getSource", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap(),
-        )
+        self.try_source(env).unwrap()
+    }
+
+    pub fn try_source(&self, env: &mut JNIEnv<'local>) -> Result<Source, Error> {
+        catch(env, |env| {
+            Ok(Source::from_primitive(
+                env.call_method(&self.0, "getSource", "()I", &[])?.i()?,
+            ))
+        })
     }
 
     pub fn action(&self, env: &mut JNIEnv<'local>) -> KeyAction {
-        KeyAction::from_primitive(
-            env.call_method(&self.0, "getAction", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap(),
-        )
+        self.try_action(env).unwrap()
+    }
+
+    pub fn try_action(&self, env: &mut JNIEnv<'local>) -> Result<KeyAction, Error> {
+        catch(env, |env| {
+            Ok(KeyAction::from_primitive(
+                env.call_method(&self.0, "getAction", "()I", &[])?.i()?,
+            ))
+        })
     }
 
     pub fn event_time(&self, env: &mut JNIEnv<'local>) -> jlong {
-        env.call_method(&self.0, "getEventTime", "()J", &[])
-            .unwrap()
-            .j()
-            .unwrap()
+        self.try_event_time(env).unwrap()
+    }
+
+    pub fn try_event_time(&self, env: &mut JNIEnv<'local>) -> Result<jlong, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getEventTime", "()J", &[])?.j()
+        })
     }
 
     pub fn down_time(&self, env: &mut JNIEnv<'local>) -> jlong {
-        env.call_method(&self.0, "getDownTime", "()J", &[])
-            .unwrap()
-            .j()
-            .unwrap()
+        self.try_down_time(env).unwrap()
+    }
+
+    pub fn try_down_time(&self, env: &mut JNIEnv<'local>) -> Result<jlong, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getDownTime", "()J", &[])?.j()
+        })
     }
 
     pub fn flags(&self, env: &mut JNIEnv<'local>) -> KeyEventFlags {
-        KeyEventFlags(
-            env.call_method(&self.0, "getFlags", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap() as u32,
-        )
+        self.try_flags(env).unwrap()
+    }
+
+    pub fn try_flags(&self, env: &mut JNIEnv<'local>) -> Result<KeyEventFlags, Error> {
+        catch(env, |env| {
+            Ok(KeyEventFlags(
+                env.call_method(&self.0, "getFlags", "()I", &[])?.i()? as u32,
+            ))
+        })
     }
 
     pub fn meta_state(&self, env: &mut JNIEnv<'local>) -> MetaState {
-        MetaState(
-            env.call_method(&self.0, "getMetaState", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap() as u32,
-        )
+        self.try_meta_state(env).unwrap()
+    }
+
+    pub fn try_meta_state(&self, env: &mut JNIEnv<'local>) -> Result<MetaState, Error> {
+        catch(env, |env| {
+            Ok(MetaState(
+                env.call_method(&self.0, "getMetaState", "()I", &[])?.i()? as u32,
+            ))
+        })
     }
 
     pub fn repeat_count(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.call_method(&self.0, "getRepeatCount", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_repeat_count(env).unwrap()
+    }
+
+    pub fn try_repeat_count(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getRepeatCount", "()I", &[])?.i()
+        })
     }
 
     pub fn key_code(&self, env: &mut JNIEnv<'local>) -> Keycode {
-        Keycode::from_primitive(
-            env.call_method(&self.0, "getKeyCode", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap(),
-        )
+        self.try_key_code(env).unwrap()
+    }
+
+    pub fn try_key_code(&self, env: &mut JNIEnv<'local>) -> Result<Keycode, Error> {
+        catch(env, |env| {
+            Ok(Keycode::from_primitive(
+                env.call_method(&self.0, "getKeyCode", "()I", &[])?.i()?,
+            ))
+        })
     }
 
     pub fn scan_code(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.call_method(&self.0, "getScanCode", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_scan_code(env).unwrap()
+    }
+
+    pub fn try_scan_code(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getScanCode", "()I", &[])?.i()
+        })
     }
 
     pub fn unicode_char(&self, env: &mut JNIEnv<'local>) -> Option<char> {
-        let i = env
-            .call_method(&self.0, "getUnicodeChar", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap();
+        self.try_unicode_char(env).unwrap()
+    }
+
+    pub fn try_unicode_char(&self, env: &mut JNIEnv<'local>) -> Result<Option<char>, Error> {
+        let i = catch(env, |env| {
+            env.call_method(&self.0, "getUnicodeChar", "()I", &[])?.i()
+        })?;
         if i <= 0 {
-            return None;
+            return Ok(None);
         }
-        char::from_u32(i as _)
+        Ok(char::from_u32(i as _))
     }
 
     pub fn to_keyboard_event(&self, env: &mut JNIEnv<'local>) -> KeyboardEvent {
-        use ui_events::keyboard::{Key, KeyState, NamedKey, android};
+        self.try_to_keyboard_event(env).unwrap()
+    }
 
-        let key_code = self.key_code(env);
+    pub fn try_to_keyboard_event(&self, env: &mut JNIEnv<'local>) -> Result<KeyboardEvent, Error> {
+        Ok(self.try_snapshot(env)?.to_keyboard_event())
+    }
 
-        KeyboardEvent {
-            state: if self.action(env) == KeyAction::Down {
-                KeyState::Down
-            } else {
-                KeyState::Up
-            },
-            key: match android::keycode_to_named_key(key_code.into()) {
-                NamedKey::Unidentified => {
-                    if let Some(c) = self.unicode_char(env) {
-                        Key::Character(c.to_string())
-                    } else {
-                        Key::Named(NamedKey::Unidentified)
-                    }
-                }
-                nk => Key::Named(nk),
-            },
-            code: android::keycode_to_code(key_code.into()),
-            location: android::keycode_to_location(key_code.into()),
-            modifiers: meta_state_to_modifiers(self.meta_state(env)),
-            repeat: self.repeat_count(env) != 0,
-            is_composing: false,
-        }
+    /// Copy every field of this event into an owned [`KeyEventSnapshot`].
+    pub fn snapshot(&self, env: &mut JNIEnv<'local>) -> KeyEventSnapshot {
+        self.try_snapshot(env).unwrap()
+    }
+
+    pub fn try_snapshot(&self, env: &mut JNIEnv<'local>) -> Result<KeyEventSnapshot, Error> {
+        catch(env, |env| {
+            let class = event_snapshots_class(env)?;
+            let packed = JLongArray::from(
+                env.call_static_method(
+                    &class,
+                    "packKeyEvent",
+                    "(Landroid/view/KeyEvent;)[J",
+                    &[(&self.0).into()],
+                )?
+                .l()?,
+            );
+            let mut buf = vec![0; env.get_array_length(&packed)? as usize];
+            env.get_long_array_region(&packed, 0, &mut buf)?;
+            Ok(KeyEventSnapshot::from_packed(&buf))
+        })
     }
 }
 
@@ -148,174 +245,256 @@ pub struct MotionEvent<'local>(pub JObject<'local>);
 
 impl<'local> MotionEvent<'local> {
     pub fn device_id(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.call_method(&self.0, "getDeviceId", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_device_id(env).unwrap()
+    }
+
+    pub fn try_device_id(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getDeviceId", "()I", &[])?.i()
+        })
     }
 
     pub fn source(&self, env: &mut JNIEnv<'local>) -> Source {
-        Source::from_primitive(
-            env.call_method(&self.0, "getSource", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap(),
-        )
+        self.try_source(env).unwrap()
+    }
+
+    pub fn try_source(&self, env: &mut JNIEnv<'local>) -> Result<Source, Error> {
+        catch(env, |env| {
+            Ok(Source::from_primitive(
+                env.call_method(&self.0, "getSource", "()I", &[])?.i()?,
+            ))
+        })
     }
 
     pub fn action(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.call_method(&self.0, "getAction", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_action(env).unwrap()
+    }
+
+    pub fn try_action(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getAction", "()I", &[])?.i()
+        })
     }
 
     pub fn action_button(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.call_method(&self.0, "getActionButton", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_action_button(env).unwrap()
+    }
+
+    pub fn try_action_button(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getActionButton", "()I", &[])?.i()
+        })
     }
 
     pub fn action_masked(&self, env: &mut JNIEnv<'local>) -> MotionAction {
-        MotionAction::from_primitive(
-            env.call_method(&self.0, "getActionMasked", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap(),
-        )
+        self.try_action_masked(env).unwrap()
+    }
+
+    pub fn try_action_masked(&self, env: &mut JNIEnv<'local>) -> Result<MotionAction, Error> {
+        catch(env, |env| {
+            Ok(MotionAction::from_primitive(
+                env.call_method(&self.0, "getActionMasked", "()I", &[])?
+                    .i()?,
+            ))
+        })
     }
 
     pub fn action_index(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.call_method(&self.0, "getActionIndex", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_action_index(env).unwrap()
+    }
+
+    pub fn try_action_index(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getActionIndex", "()I", &[])?.i()
+        })
     }
 
     pub fn button_state(&self, env: &mut JNIEnv<'local>) -> ButtonState {
-        ButtonState(
-            env.call_method(&self.0, "getButtonState", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap() as u32,
-        )
+        self.try_button_state(env).unwrap()
+    }
+
+    pub fn try_button_state(&self, env: &mut JNIEnv<'local>) -> Result<ButtonState, Error> {
+        catch(env, |env| {
+            Ok(ButtonState(
+                env.call_method(&self.0, "getButtonState", "()I", &[])?
+                    .i()? as u32,
+            ))
+        })
     }
 
     pub fn event_time(&self, env: &mut JNIEnv<'local>) -> jlong {
-        env.call_method(&self.0, "getEventTime", "()J", &[])
-            .unwrap()
-            .j()
-            .unwrap()
+        self.try_event_time(env).unwrap()
+    }
+
+    pub fn try_event_time(&self, env: &mut JNIEnv<'local>) -> Result<jlong, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getEventTime", "()J", &[])?.j()
+        })
     }
 
     pub fn event_time_nanos(&self, env: &mut JNIEnv<'local>) -> jlong {
-        env.call_method(&self.0, "getEventTimeNanos", "()J", &[])
-            .unwrap()
-            .j()
-            .unwrap()
+        self.try_event_time_nanos(env).unwrap()
+    }
+
+    pub fn try_event_time_nanos(&self, env: &mut JNIEnv<'local>) -> Result<jlong, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getEventTimeNanos", "()J", &[])?
+                .j()
+        })
     }
 
     pub fn historical_event_time_nanos(&self, env: &mut JNIEnv<'local>, pos: i32) -> jlong {
-        env.call_method(
-            &self.0,
-            "getHistoricalEventTimeNanos",
-            "(I)J",
-            &[pos.into()],
-        )
-        .unwrap()
-        .j()
-        .unwrap()
+        self.try_historical_event_time_nanos(env, pos).unwrap()
     }
 
-    pub fn down_time(&self, env: &mut JNIEnv<'local>) -> jlong {
-        env.call_method(&self.0, "getDownTime", "()J", &[])
-            .unwrap()
+    pub fn try_historical_event_time_nanos(
+        &self,
+        env: &mut JNIEnv<'local>,
+        pos: i32,
+    ) -> Result<jlong, Error> {
+        catch(env, |env| {
+            env.call_method(
+                &self.0,
+                "getHistoricalEventTimeNanos",
+                "(I)J",
+                &[pos.into()],
+            )?
             .j()
-            .unwrap()
+        })
+    }
+
+    pub fn down_time(&self, env: &mut JNIEnv<'local>) -> jlong {
+        self.try_down_time(env).unwrap()
+    }
+
+    pub fn try_down_time(&self, env: &mut JNIEnv<'local>) -> Result<jlong, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getDownTime", "()J", &[])?.j()
+        })
     }
 
     pub fn flags(&self, env: &mut JNIEnv<'local>) -> MotionEventFlags {
-        MotionEventFlags(
-            env.call_method(&self.0, "getFlags", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap() as u32,
-        )
+        self.try_flags(env).unwrap()
+    }
+
+    pub fn try_flags(&self, env: &mut JNIEnv<'local>) -> Result<MotionEventFlags, Error> {
+        catch(env, |env| {
+            Ok(MotionEventFlags(
+                env.call_method(&self.0, "getFlags", "()I", &[])?.i()? as u32,
+            ))
+        })
     }
 
     pub fn meta_state(&self, env: &mut JNIEnv<'local>) -> MetaState {
-        MetaState(
-            env.call_method(&self.0, "getMetaState", "()I", &[])
-                .unwrap()
-                .i()
-                .unwrap() as u32,
-        )
+        self.try_meta_state(env).unwrap()
+    }
+
+    pub fn try_meta_state(&self, env: &mut JNIEnv<'local>) -> Result<MetaState, Error> {
+        catch(env, |env| {
+            Ok(MetaState(
+                env.call_method(&self.0, "getMetaState", "()I", &[])?.i()? as u32,
+            ))
+        })
     }
 
     pub fn pointer_count(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.call_method(&self.0, "getPointerCount", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_pointer_count(env).unwrap()
+    }
+
+    pub fn try_pointer_count(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getPointerCount", "()I", &[])?.i()
+        })
     }
 
     pub fn pointer_id(&self, env: &mut JNIEnv<'local>, pointer_index: jint) -> jint {
-        env.call_method(&self.0, "getPointerId", "(I)I", &[pointer_index.into()])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_pointer_id(env, pointer_index).unwrap()
     }
 
-    pub fn tool_type(&self, env: &mut JNIEnv<'local>, pointer_index: jint) -> ToolType {
-        ToolType::from(
-            env.call_method(&self.0, "getToolType", "(I)I", &[pointer_index.into()])
-                .unwrap()
+    pub fn try_pointer_id(
+        &self,
+        env: &mut JNIEnv<'local>,
+        pointer_index: jint,
+    ) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getPointerId", "(I)I", &[pointer_index.into()])?
                 .i()
-                .unwrap(),
-        )
+        })
+    }
+
+    pub fn tool_type(&self, env: &mut JNIEnv<'local>, pointer_index: jint) -> ToolType {
+        self.try_tool_type(env, pointer_index).unwrap()
+    }
+
+    pub fn try_tool_type(
+        &self,
+        env: &mut JNIEnv<'local>,
+        pointer_index: jint,
+    ) -> Result<ToolType, Error> {
+        catch(env, |env| {
+            Ok(ToolType::from(
+                env.call_method(&self.0, "getToolType", "(I)I", &[pointer_index.into()])?
+                    .i()?,
+            ))
+        })
     }
 
     pub fn x(&self, env: &mut JNIEnv<'local>) -> jfloat {
-        env.call_method(&self.0, "getX", "()F", &[])
-            .unwrap()
-            .f()
-            .unwrap()
+        self.try_x(env).unwrap()
+    }
+
+    pub fn try_x(&self, env: &mut JNIEnv<'local>) -> Result<jfloat, Error> {
+        catch(env, |env| env.call_method(&self.0, "getX", "()F", &[])?.f())
     }
 
     pub fn x_at(&self, env: &mut JNIEnv<'local>, pointer_index: jint) -> jfloat {
-        env.call_method(&self.0, "getX", "(I)F", &[pointer_index.into()])
-            .unwrap()
-            .f()
-            .unwrap()
+        self.try_x_at(env, pointer_index).unwrap()
+    }
+
+    pub fn try_x_at(&self, env: &mut JNIEnv<'local>, pointer_index: jint) -> Result<jfloat, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getX", "(I)F", &[pointer_index.into()])?
+                .f()
+        })
     }
 
     pub fn y(&self, env: &mut JNIEnv<'local>) -> jfloat {
-        env.call_method(&self.0, "getY", "()F", &[])
-            .unwrap()
-            .f()
-            .unwrap()
+        self.try_y(env).unwrap()
+    }
+
+    pub fn try_y(&self, env: &mut JNIEnv<'local>) -> Result<jfloat, Error> {
+        catch(env, |env| env.call_method(&self.0, "getY", "()F", &[])?.f())
     }
 
     pub fn y_at(&self, env: &mut JNIEnv<'local>, pointer_index: jint) -> jfloat {
-        env.call_method(&self.0, "getY", "(I)F", &[pointer_index.into()])
-            .unwrap()
-            .f()
-            .unwrap()
+        self.try_y_at(env, pointer_index).unwrap()
+    }
+
+    pub fn try_y_at(&self, env: &mut JNIEnv<'local>, pointer_index: jint) -> Result<jfloat, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getY", "(I)F", &[pointer_index.into()])?
+                .f()
+        })
     }
 
     pub fn pressure(&self, env: &mut JNIEnv<'local>) -> jfloat {
-        env.call_method(&self.0, "getPressure", "()F", &[])
-            .unwrap()
-            .f()
-            .unwrap()
+        self.try_pressure(env).unwrap()
+    }
+
+    pub fn try_pressure(&self, env: &mut JNIEnv<'local>) -> Result<jfloat, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getPressure", "()F", &[])?.f()
+        })
     }
 
     pub fn history_size(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.call_method(&self.0, "getHistorySize", "()I", &[])
-            .unwrap()
-            .i()
-            .unwrap()
+        self.try_history_size(env).unwrap()
+    }
+
+    pub fn try_history_size(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getHistorySize", "()I", &[])?.i()
+        })
     }
 
     pub fn historical_axis(
@@ -325,27 +504,47 @@ impl<'local> MotionEvent<'local> {
         pointer_index: i32,
         pos: i32,
     ) -> jfloat {
-        env.call_method(
-            &self.0,
-            "getHistoricalAxisValue",
-            "(III)F",
-            &[i32::from(axis).into(), pointer_index.into(), pos.into()],
-        )
-        .unwrap()
-        .f()
-        .unwrap()
+        self.try_historical_axis(env, axis, pointer_index, pos)
+            .unwrap()
+    }
+
+    pub fn try_historical_axis(
+        &self,
+        env: &mut JNIEnv<'local>,
+        axis: Axis,
+        pointer_index: i32,
+        pos: i32,
+    ) -> Result<jfloat, Error> {
+        catch(env, |env| {
+            env.call_method(
+                &self.0,
+                "getHistoricalAxisValue",
+                "(III)F",
+                &[i32::from(axis).into(), pointer_index.into(), pos.into()],
+            )?
+            .f()
+        })
     }
 
     pub fn axis(&self, env: &mut JNIEnv<'local>, axis: Axis, pointer_index: jint) -> jfloat {
-        env.call_method(
-            &self.0,
-            "getAxisValue",
-            "(II)F",
-            &[i32::from(axis).into(), pointer_index.into()],
-        )
-        .unwrap()
-        .f()
-        .unwrap()
+        self.try_axis(env, axis, pointer_index).unwrap()
+    }
+
+    pub fn try_axis(
+        &self,
+        env: &mut JNIEnv<'local>,
+        axis: Axis,
+        pointer_index: jint,
+    ) -> Result<jfloat, Error> {
+        catch(env, |env| {
+            env.call_method(
+                &self.0,
+                "getAxisValue",
+                "(II)F",
+                &[i32::from(axis).into(), pointer_index.into()],
+            )?
+            .f()
+        })
     }
 
     pub fn to_pointer_event(
@@ -353,201 +552,152 @@ impl<'local> MotionEvent<'local> {
         env: &mut JNIEnv<'local>,
         vc: &ViewConfiguration,
     ) -> Option<PointerEvent> {
-        use ui_events::pointer::{
-            PersistentDeviceId, PointerButton, PointerButtons, PointerId, PointerInfo,
-            PointerOrientation, PointerState, PointerType, PointerUpdate,
-        };
+        self.try_to_pointer_event(env, vc).unwrap()
+    }
 
-        let time = self.event_time_nanos(env) as u64;
-        let action = self.action_masked(env);
+    pub fn try_to_pointer_event(
+        &self,
+        env: &mut JNIEnv<'local>,
+        vc: &ViewConfiguration,
+    ) -> Result<Option<PointerEvent>, Error> {
+        Ok(self.try_snapshot(env)?.to_pointer_event(vc))
+    }
+
+    /// Convert to one [`PointerEvent`] for each pointer in this event.
+    ///
+    /// See [`MotionEventSnapshot::to_pointer_events`] for details.
+    pub fn to_pointer_events(
+        &self,
+        env: &mut JNIEnv<'local>,
+        vc: &ViewConfiguration,
+    ) -> Vec<PointerEvent> {
+        self.try_to_pointer_events(env, vc).unwrap()
+    }
 
-        let action_index = self.action_index(env);
-        let tool_type = self.tool_type(env, action_index);
-        if tool_type == ToolType::Palm {
-            // I don't think we have any useful way of handling this.
-            return None;
+    pub fn try_to_pointer_events(
+        &self,
+        env: &mut JNIEnv<'local>,
+        vc: &ViewConfiguration,
+    ) -> Result<Vec<PointerEvent>, Error> {
+        Ok(self.try_snapshot(env)?.to_pointer_events(vc))
+    }
+
+    /// Copy this event into a native `AMotionEvent`, which can then be read
+    /// without any further JNI calls.
+    ///
+    /// Returns `None` before API level 31, where `AMotionEvent_fromJava`
+    /// is not available.
+    pub fn to_native(&self, env: &mut JNIEnv<'local>) -> Option<NativeMotionEvent> {
+        let fns = native_input_fns()?;
+        // SAFETY: `env` is attached to this thread and `self.0` is a
+        // `MotionEvent`, as `AMotionEvent_fromJava` requires.
+        let ptr = unsafe { (fns.motion_event_from_java)(env.get_raw(), self.0.as_raw()) };
+        let ptr = NonNull::new(ptr.cast_mut())?;
+        // SAFETY: `ptr` is a valid motion event, which is owned by the
+        // returned wrapper until it is released on drop.
+        let event = unsafe { ndk::event::MotionEvent::from_ptr(ptr.cast()) };
+        Some(NativeMotionEvent {
+            event,
+            release: fns.input_event_release,
+        })
+    }
+
+    /// Copy every pointer, axis and historical sample of this event into
+    /// an owned [`MotionEventSnapshot`].
+    pub fn snapshot(&self, env: &mut JNIEnv<'local>) -> MotionEventSnapshot {
+        self.try_snapshot(env).unwrap()
+    }
+
+    pub fn try_snapshot(&self, env: &mut JNIEnv<'local>) -> Result<MotionEventSnapshot, Error> {
+        if let Some(native) = self.to_native(env) {
+            let action_button = self.try_action_button(env)?;
+            return Ok(MotionEventSnapshot::from_native(&native, action_button));
         }
-        let pointer = PointerInfo {
-            pointer_id: match self.pointer_id(env, action_index) {
-                n if n < 0 => None,
-                n => PointerId::new(n as u64 + 1),
-            },
-            persistent_device_id: PersistentDeviceId::new(self.device_id(env) as u64),
-            pointer_type: match tool_type {
-                ToolType::Mouse => PointerType::Mouse,
-                ToolType::Finger => PointerType::Touch,
-                ToolType::Stylus | ToolType::Eraser => PointerType::Pen,
-                _ => PointerType::Unknown,
-            },
-        };
-        let buttons = {
-            let mut pb = PointerButtons::default();
-            let bs = self.button_state(env);
-            if bs.primary() {
-                pb |= PointerButton::Primary;
-            }
-            if bs.stylus_primary() {
-                pb |= if tool_type == ToolType::Eraser {
-                    PointerButton::PenEraser
-                } else {
-                    PointerButton::Primary
-                };
-            }
-            if bs.secondary() || bs.stylus_secondary() {
-                pb |= PointerButton::Secondary;
-            }
-            if bs.teriary() {
-                pb |= PointerButton::Auxiliary;
-            }
-            if bs.back() {
-                pb |= PointerButton::X1;
-            }
-            if bs.forward() {
-                pb |= PointerButton::X2;
-            }
-            // TODO: verify this behavior.
-            if tool_type == ToolType::Eraser && self.axis(env, Axis::Pressure, action_index) > 0.0 {
-                pb |= PointerButton::PenEraser;
-            }
-            pb
-        };
-        let modifiers = meta_state_to_modifiers(self.meta_state(env));
-        let orientation = if matches!(tool_type, ToolType::Stylus | ToolType::Eraser) {
-            use core::f32::consts::FRAC_PI_2;
-            let axis_orientation = self.axis(env, Axis::Orientation, action_index);
-            let axis_tilt = self.axis(env, Axis::Tilt, action_index);
-            let altitude = FRAC_PI_2 - axis_tilt;
-            let azimuth = (-axis_orientation + 3.0 * FRAC_PI_2).rem_euclid(4.0 * FRAC_PI_2);
-            PointerOrientation { altitude, azimuth }
-        } else {
-            Default::default()
-        };
-        let contact_geometry = if pointer.pointer_type == PointerType::Touch {
-            let height = self.axis(env, Axis::TouchMajor, action_index) as f64;
-            let width = self.axis(env, Axis::TouchMinor, action_index) as f64;
-            (height > 0.0 && width > 0.0)
-                .then_some(ContactGeometry { width, height })
-                .unwrap_or_default()
-        } else {
-            Default::default()
-        };
-        let state = PointerState {
-            time,
-            position: PhysicalPosition::<f64> {
-                x: self.axis(env, Axis::X, action_index) as f64,
-                y: self.axis(env, Axis::Y, action_index) as f64,
-            },
-            buttons,
-            // `TapCounter` will attach an appropriate count.
-            count: 0,
-            modifiers,
-            contact_geometry,
-            orientation,
-            pressure: self.axis(env, Axis::Pressure, action_index) * 0.5,
-            tangential_pressure: 0.0,
-        };
+        catch(env, |env| {
+            let class = event_snapshots_class(env)?;
+            let header = JLongArray::from(
+                env.call_static_method(
+                    &class,
+                    "packMotionEventHeader",
+                    "(Landroid/view/MotionEvent;)[J",
+                    &[(&self.0).into()],
+                )?
+                .l()?,
+            );
+            let mut header_buf = vec![0; env.get_array_length(&header)? as usize];
+            env.get_long_array_region(&header, 0, &mut header_buf)?;
+            let axes = JFloatArray::from(
+                env.call_static_method(
+                    &class,
+                    "packMotionEventAxes",
+                    "(Landroid/view/MotionEvent;)[F",
+                    &[(&self.0).into()],
+                )?
+                .l()?,
+            );
+            let mut axes_buf = vec![0.0; env.get_array_length(&axes)? as usize];
+            env.get_float_array_region(&axes, 0, &mut axes_buf)?;
+            Ok(MotionEventSnapshot::from_packed(&header_buf, axes_buf))
+        })
+    }
+}
 
-        let button = {
-            // Button constants from <https://developer.android.com/reference/android/view/MotionEvent>.
-            const BUTTON_PRIMARY: jint = 0b1;
-            const BUTTON_STYLUS_PRIMARY: jint = 0b100000;
-            const BUTTON_SECONDARY: jint = 0b10;
-            const BUTTON_STYLUS_SECONDARY: jint = 0b1000000;
-            const BUTTON_TERTIARY: jint = 0b100;
-            const BUTTON_BACK: jint = 0b1000;
-            const BUTTON_FORWARD: jint = 0b10000;
-            match self.action_button(env) {
-                BUTTON_PRIMARY | BUTTON_STYLUS_PRIMARY => Some(PointerButton::Primary),
-                BUTTON_SECONDARY | BUTTON_STYLUS_SECONDARY => Some(PointerButton::Secondary),
-                BUTTON_TERTIARY => Some(PointerButton::Auxiliary),
-                BUTTON_BACK => Some(PointerButton::X1),
-                BUTTON_FORWARD => Some(PointerButton::X2),
-                _ => (tool_type == ToolType::Eraser).then_some(PointerButton::PenEraser),
-            }
-        };
+type MotionEventFromJavaFn = unsafe extern "C" fn(*mut jni::sys::JNIEnv, jobject) -> *const c_void;
+type InputEventReleaseFn = unsafe extern "C" fn(*const c_void);
 
-        Some(match action {
-            MotionAction::Down | MotionAction::PointerDown => PointerEvent::Down {
-                pointer,
-                state,
-                button,
-            },
-            MotionAction::Up | MotionAction::PointerUp => PointerEvent::Up {
-                pointer,
-                state,
-                button,
-            },
-            MotionAction::Move | MotionAction::HoverMove => {
-                let hsz = self.history_size(env);
-                let mut coalesced: Vec<PointerState> = vec![state.clone(); hsz as usize];
-                for pos in 0..hsz {
-                    let i = pos as usize;
-                    coalesced[i].time = self.historical_event_time_nanos(env, pos) as u64;
-                    coalesced[i].position = PhysicalPosition::<f64> {
-                        x: self.historical_axis(env, Axis::X, action_index, pos) as f64,
-                        y: self.historical_axis(env, Axis::Y, action_index, pos) as f64,
-                    };
-                    coalesced[i].contact_geometry = if pointer.pointer_type == PointerType::Touch {
-                        let height =
-                            self.historical_axis(env, Axis::TouchMajor, action_index, pos) as f64;
-                        let width =
-                            self.historical_axis(env, Axis::TouchMinor, action_index, pos) as f64;
-                        (height > 0.0 && width > 0.0)
-                            .then_some(ContactGeometry { width, height })
-                            .unwrap_or_default()
-                    } else {
-                        Default::default()
-                    };
-                    coalesced[i].pressure =
-                        self.historical_axis(env, Axis::Pressure, action_index, pos) * 0.5;
-                    coalesced[i].orientation =
-                        if matches!(tool_type, ToolType::Stylus | ToolType::Eraser) {
-                            use core::f32::consts::FRAC_PI_2;
-                            let axis_orientation =
-                                self.historical_axis(env, Axis::Orientation, action_index, pos);
-                            let axis_tilt =
-                                self.historical_axis(env, Axis::Tilt, action_index, pos);
-                            let altitude = FRAC_PI_2 - axis_tilt;
-                            let azimuth =
-                                (-axis_orientation + 3.0 * FRAC_PI_2).rem_euclid(4.0 * FRAC_PI_2);
-                            PointerOrientation { altitude, azimuth }
-                        } else {
-                            Default::default()
-                        };
-                }
+struct NativeInputFns {
+    motion_event_from_java: MotionEventFromJavaFn,
+    input_event_release: InputEventReleaseFn,
+}
 
-                PointerEvent::Move(PointerUpdate {
-                    pointer,
-                    current: state,
-                    coalesced,
-                    // TODO: map predicted events
-                    predicted: vec![],
-                })
-            }
-            MotionAction::Cancel => PointerEvent::Cancel(pointer),
-            MotionAction::HoverEnter => PointerEvent::Enter(pointer),
-            MotionAction::HoverExit => PointerEvent::Leave(pointer),
-            MotionAction::Scroll => PointerEvent::Scroll {
-                pointer,
-                delta: ScrollDelta::PixelDelta(PhysicalPosition::<f64> {
-                    x: (self.axis(env, Axis::Hscroll, action_index)
-                        * vc.scaled_horizontal_scroll_factor) as f64,
-                    y: (self.axis(env, Axis::Vscroll, action_index)
-                        * vc.scaled_vertical_scroll_factor) as f64,
-                }),
-                state,
-            },
-            _ => {
-                // Other current `MotionAction` values relate to gamepad/joystick buttons;
-                // ui-events doesn't currently have types for these, so consider them unhandled.
+/// Look up the API level 31 input functions in `libandroid`, which
+/// can't be linked directly while the minimum supported API level is lower.
+fn native_input_fns() -> Option<&'static NativeInputFns> {
+    static FNS: OnceLock<Option<NativeInputFns>> = OnceLock::new();
+    FNS.get_or_init(|| {
+        // SAFETY: Both names are NUL-terminated, and the symbols, if found,
+        // have the signatures declared in `<android/input.h>`.
+        unsafe {
+            let from_java = libc::dlsym(libc::RTLD_DEFAULT, c"AMotionEvent_fromJava".as_ptr());
+            let release = libc::dlsym(libc::RTLD_DEFAULT, c"AInputEvent_release".as_ptr());
+            if from_java.is_null() || release.is_null() {
                 return None;
             }
-        })
+            Some(NativeInputFns {
+                motion_event_from_java: mem::transmute::<*mut c_void, MotionEventFromJavaFn>(
+                    from_java,
+                ),
+                input_event_release: mem::transmute::<*mut c_void, InputEventReleaseFn>(release),
+            })
+        }
+    })
+    .as_ref()
+}
+
+/// A native copy of a Java `MotionEvent`, created by [`MotionEvent::to_native`]
+/// and released when dropped.
+pub struct NativeMotionEvent {
+    event: ndk::event::MotionEvent,
+    release: InputEventReleaseFn,
+}
+
+impl Deref for NativeMotionEvent {
+    type Target = ndk::event::MotionEvent;
+
+    fn deref(&self) -> &Self::Target {
+        &self.event
+    }
+}
+
+impl Drop for NativeMotionEvent {
+    fn drop(&mut self) {
+        // SAFETY: The event came from `AMotionEvent_fromJava` and is released once.
+        unsafe { (self.release)(self.event.ptr().as_ptr().cast()) }
     }
 }
 
 /// Convert `MetaState` to `Modifiers`.
-fn meta_state_to_modifiers(s: MetaState) -> Modifiers {
+pub(crate) fn meta_state_to_modifiers(s: MetaState) -> Modifiers {
     let mut m = Modifiers::default();
     if s.caps_lock_on() {
         m |= Modifiers::CAPS_LOCK;
@@ -602,23 +752,99 @@ struct TapState {
 }
 
 /// Track and apply tap counts for `PointerEvent`.
-#[derive(Default)]
 pub struct TapCounter {
     /// The `ViewConfiguration` which configures tap counting.
     pub vc: ViewConfiguration,
     /// Recent taps which can be used for tap counting.
     taps: Vec<TapState>,
+    long_press: LongPressRecognizer,
+    /// Whether `schedule_long_press` has been called, after which long
+    /// presses are kept until `on_timer` returns them.
+    long_press_scheduled: bool,
+    /// Long presses recognized but not yet returned from `on_timer`.
+    long_presses: Vec<GestureEvent>,
+    /// The timer started for the pending long press, and its deadline.
+    timer: Option<(TimerToken, u64)>,
 }
 
 impl TapCounter {
     /// Make a new `TapCounter` with `ViewConfiguration` from your view.
     pub fn new(vc: ViewConfiguration) -> Self {
-        Self { vc, taps: vec![] }
+        Self {
+            long_press: LongPressRecognizer::new(&vc),
+            vc,
+            taps: vec![],
+            long_press_scheduled: false,
+            long_presses: vec![],
+            timer: None,
+        }
+    }
+
+    /// Start or cancel the timer for a pending long press.
+    ///
+    /// Call this after [`attach_count`](Self::attach_count) to receive long
+    /// presses from [`on_timer`](Self::on_timer); they are not recognized
+    /// until it is first called.
+    pub fn schedule_long_press(&mut self, ctx: &mut CallbackCtx) {
+        self.long_press_scheduled = true;
+        let deadline = self.long_press_deadline();
+        if self.timer.map(|(_, d)| d) == deadline {
+            return;
+        }
+        if let Some((token, _)) = self.timer.take() {
+            ctx.cancel_timer(token);
+        }
+        let Some(deadline) = deadline else {
+            return;
+        };
+        let delay_nanos = deadline.saturating_sub(uptime_nanos());
+        let token = ctx.start_timer(Duration::from_millis(delay_nanos.div_ceil(1_000_000)));
+        self.timer = Some((token, deadline));
+    }
+
+    /// Call from [`ViewPeer::on_timer`] to receive [`GestureEvent::LongPress`]
+    /// once a press exceeds `long_press_timeout`.
+    ///
+    /// Returns nothing for timers that this `TapCounter` didn't start.
+    ///
+    /// [`ViewPeer::on_timer`]: crate::ViewPeer::on_timer
+    pub fn on_timer(&mut self, ctx: &mut CallbackCtx, token: TimerToken) -> Vec<GestureEvent> {
+        if self.timer.is_none_or(|(t, _)| t != token) {
+            return vec![];
+        }
+        self.timer = None;
+        let long_presses = self.take_long_presses(uptime_nanos());
+        self.schedule_long_press(ctx);
+        long_presses
+    }
+
+    /// When the long press timer should fire, or `None` if it shouldn't run.
+    fn long_press_deadline(&self) -> Option<u64> {
+        if self.long_presses.is_empty() {
+            self.long_press.next_deadline()
+        } else {
+            Some(0)
+        }
+    }
+
+    /// Long presses recognized by `now`.
+    fn take_long_presses(&mut self, now: u64) -> Vec<GestureEvent> {
+        self.long_press.tick(now, &mut self.long_presses);
+        mem::take(&mut self.long_presses)
     }
 
     /// Enhance a `PointerEvent` with `count`.
     ///
+    // The lint's suggestion of dropping `cloned` leaves `..u` borrowed.
+    #[allow(clippy::redundant_iter_cloned)]
     pub fn attach_count(&mut self, e: PointerEvent) -> PointerEvent {
+        let long_presses = if self.long_press_scheduled {
+            &mut self.long_presses
+        } else {
+            // Nothing will take them.
+            &mut vec![]
+        };
+        self.long_press.pointer_event(&e, long_presses);
         match e {
             PointerEvent::Down {
                 button,
@@ -754,3 +980,493 @@ impl TapCounter {
         );
     }
 }
+
+impl Default for TapCounter {
+    /// A counter with Android's default timeouts, for use until the
+    /// `ViewConfiguration` of the view is known.
+    fn default() -> Self {
+        Self::new(ViewConfiguration {
+            long_press_timeout: DEFAULT_LONG_PRESS_TIMEOUT,
+            multi_press_timeout: DEFAULT_MULTI_PRESS_TIMEOUT,
+            ..Default::default()
+        })
+    }
+}
+
+/// A button on a gamepad or joystick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GamepadButton {
+    A,
+    B,
+    C,
+    X,
+    Y,
+    Z,
+    L1,
+    R1,
+    L2,
+    R2,
+    ThumbL,
+    ThumbR,
+    Start,
+    Select,
+    Mode,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+    DpadCenter,
+    /// One of the generic `BUTTON_1` to `BUTTON_16` keys, numbered from 1.
+    Generic(u8),
+}
+
+impl GamepadButton {
+    /// Map a key code to a gamepad button, if it is one.
+    pub fn from_keycode(key_code: Keycode) -> Option<Self> {
+        Some(match key_code {
+            Keycode::ButtonA => Self::A,
+            Keycode::ButtonB => Self::B,
+            Keycode::ButtonC => Self::C,
+            Keycode::ButtonX => Self::X,
+            Keycode::ButtonY => Self::Y,
+            Keycode::ButtonZ => Self::Z,
+            Keycode::ButtonL1 => Self::L1,
+            Keycode::ButtonR1 => Self::R1,
+            Keycode::ButtonL2 => Self::L2,
+            Keycode::ButtonR2 => Self::R2,
+            Keycode::ButtonThumbl => Self::ThumbL,
+            Keycode::ButtonThumbr => Self::ThumbR,
+            Keycode::ButtonStart => Self::Start,
+            Keycode::ButtonSelect => Self::Select,
+            Keycode::ButtonMode => Self::Mode,
+            Keycode::DpadUp => Self::DpadUp,
+            Keycode::DpadDown => Self::DpadDown,
+            Keycode::DpadLeft => Self::DpadLeft,
+            Keycode::DpadRight => Self::DpadRight,
+            Keycode::DpadCenter => Self::DpadCenter,
+            _ => {
+                let code = i32::from(key_code);
+                let first = i32::from(Keycode::Button1);
+                if (first..=i32::from(Keycode::Button16)).contains(&code) {
+                    Self::Generic((code - first + 1) as u8)
+                } else {
+                    return None;
+                }
+            }
+        })
+    }
+}
+
+/// An analog control on a gamepad or joystick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+    /// The horizontal D-pad axis, for controllers that report the D-pad as a hat.
+    HatX,
+    /// The vertical D-pad axis, for controllers that report the D-pad as a hat.
+    HatY,
+}
+
+impl GamepadAxis {
+    const ALL: [Self; 8] = [
+        Self::LeftStickX,
+        Self::LeftStickY,
+        Self::RightStickX,
+        Self::RightStickY,
+        Self::LeftTrigger,
+        Self::RightTrigger,
+        Self::HatX,
+        Self::HatY,
+    ];
+
+    /// Read this control from one sample of a joystick motion event.
+    ///
+    /// Triggers are reported as either `LTRIGGER`/`RTRIGGER` or `BRAKE`/`GAS`
+    /// depending on the controller, so the larger of the two is used.
+    fn read(self, event: &MotionEventSnapshot, pos: usize) -> f32 {
+        let axis = |axis| event.historical_axis(axis, 0, pos);
+        match self {
+            Self::LeftStickX => axis(Axis::X),
+            Self::LeftStickY => axis(Axis::Y),
+            Self::RightStickX => axis(Axis::Z),
+            Self::RightStickY => axis(Axis::Rz),
+            Self::LeftTrigger => axis(Axis::Ltrigger).max(axis(Axis::Brake)),
+            Self::RightTrigger => axis(Axis::Rtrigger).max(axis(Axis::Gas)),
+            Self::HatX => axis(Axis::HatX),
+            Self::HatY => axis(Axis::HatY),
+        }
+    }
+}
+
+/// A change in the state of a gamepad, produced by [`GamepadState`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum GamepadEvent {
+    /// The first input from a device.
+    Connected { device_id: i32 },
+    /// A device was reported as removed with [`GamepadState::disconnect`].
+    Disconnected { device_id: i32 },
+    Button {
+        device_id: i32,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    /// The value of an axis changed. Sticks and hats range from -1 to 1,
+    /// and triggers from 0 to 1.
+    Axis {
+        device_id: i32,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+#[derive(Default)]
+struct GamepadDevice {
+    buttons: BTreeSet<GamepadButton>,
+    axes: BTreeMap<GamepadAxis, f32>,
+}
+
+/// Tracks the state of connected gamepads and reports changes as [`GamepadEvent`]s.
+///
+/// Feed it the events from `on_generic_motion_event`, `on_key_down` and
+/// `on_key_up`; events from other sources produce no gamepad events.
+pub struct GamepadState {
+    /// Axis values with a smaller magnitude than this are reported as 0.
+    /// For sticks, this is the distance of the stick from its center.
+    pub dead_zone: f32,
+    devices: BTreeMap<i32, GamepadDevice>,
+}
+
+impl Default for GamepadState {
+    fn default() -> Self {
+        Self::new(0.1)
+    }
+}
+
+impl GamepadState {
+    pub fn new(dead_zone: f32) -> Self {
+        Self {
+            dead_zone,
+            devices: BTreeMap::new(),
+        }
+    }
+
+    /// IDs of the devices that have produced input since they were connected.
+    pub fn devices(&self) -> impl Iterator<Item = i32> + '_ {
+        self.devices.keys().copied()
+    }
+
+    pub fn is_pressed(&self, device_id: i32, button: GamepadButton) -> bool {
+        self.devices
+            .get(&device_id)
+            .is_some_and(|d| d.buttons.contains(&button))
+    }
+
+    /// The current value of `axis`, after dead-zone handling.
+    pub fn axis(&self, device_id: i32, axis: GamepadAxis) -> f32 {
+        self.devices
+            .get(&device_id)
+            .and_then(|d| d.axes.get(&axis).copied())
+            .unwrap_or_default()
+    }
+
+    /// Forget a device, such as when `InputManager` reports it as removed.
+    pub fn disconnect(&mut self, device_id: i32) -> Option<GamepadEvent> {
+        self.devices
+            .remove(&device_id)
+            .map(|_| GamepadEvent::Disconnected { device_id })
+    }
+
+    fn device(&mut self, device_id: i32, events: &mut Vec<GamepadEvent>) -> &mut GamepadDevice {
+        self.devices.entry(device_id).or_insert_with(|| {
+            events.push(GamepadEvent::Connected { device_id });
+            GamepadDevice::default()
+        })
+    }
+
+    fn set_button(
+        &mut self,
+        device_id: i32,
+        button: GamepadButton,
+        pressed: bool,
+        events: &mut Vec<GamepadEvent>,
+    ) {
+        let device = self.device(device_id, events);
+        let changed = if pressed {
+            device.buttons.insert(button)
+        } else {
+            device.buttons.remove(&button)
+        };
+        if changed {
+            events.push(GamepadEvent::Button {
+                device_id,
+                button,
+                pressed,
+            });
+        }
+    }
+
+    /// Process a joystick motion event, including its batched history.
+    pub fn handle_motion_event(&mut self, event: &MotionEventSnapshot) -> Vec<GamepadEvent> {
+        let mut events = vec![];
+        if !event.is_from_gamepad() || event.pointer_count() == 0 {
+            return events;
+        }
+        let device_id = event.device_id;
+        for pos in 0..=event.history_size() {
+            let mut values = GamepadAxis::ALL.map(|axis| axis.read(event, pos));
+            self.apply_dead_zone(&mut values);
+            let mut hat_changed = false;
+            for (axis, value) in GamepadAxis::ALL.into_iter().zip(values) {
+                let device = self.device(device_id, &mut events);
+                let previous = device.axes.insert(axis, value).unwrap_or_default();
+                if previous != value {
+                    hat_changed |= matches!(axis, GamepadAxis::HatX | GamepadAxis::HatY);
+                    events.push(GamepadEvent::Axis {
+                        device_id,
+                        axis,
+                        value,
+                    });
+                }
+            }
+            // Report hat axes as D-pad buttons too, so that the D-pad behaves
+            // the same whichever way the controller reports it. Only do so
+            // when the hat moves, so that D-pad keys aren't released by
+            // motion of the other axes.
+            if !hat_changed {
+                continue;
+            }
+            let hat_x = self.axis(device_id, GamepadAxis::HatX);
+            let hat_y = self.axis(device_id, GamepadAxis::HatY);
+            for (button, pressed) in [
+                (GamepadButton::DpadLeft, hat_x < -0.5),
+                (GamepadButton::DpadRight, hat_x > 0.5),
+                (GamepadButton::DpadUp, hat_y < -0.5),
+                (GamepadButton::DpadDown, hat_y > 0.5),
+            ] {
+                self.set_button(device_id, button, pressed, &mut events);
+            }
+        }
+        events
+    }
+
+    /// Zero the values within the dead zone, given in the order of
+    /// `GamepadAxis::ALL`. The dead zone of each stick is a circle, so that
+    /// diagonals don't snap to the axes.
+    fn apply_dead_zone(&self, values: &mut [f32; 8]) {
+        let (sticks, others) = values.split_at_mut(4);
+        for stick in sticks.chunks_exact_mut(2) {
+            if stick[0].hypot(stick[1]) < self.dead_zone {
+                stick.fill(0.0);
+            }
+        }
+        for value in others {
+            if value.abs() < self.dead_zone {
+                *value = 0.0;
+            }
+        }
+    }
+
+    /// Process a key event from a gamepad button.
+    pub fn handle_key_event(&mut self, event: &KeyEventSnapshot) -> Vec<GamepadEvent> {
+        let mut events = vec![];
+        if !event.is_from_gamepad() {
+            return events;
+        }
+        let Some(button) = GamepadButton::from_keycode(event.key_code()) else {
+            return events;
+        };
+        let pressed = event.action() == KeyAction::Down;
+        self.set_button(event.device_id, button, pressed, &mut events);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MOTION_EVENT_AXIS_COUNT;
+
+    const DEVICE: i32 = 7;
+    const SOURCE_JOYSTICK: i32 = 0x1000010;
+    const SOURCE_GAMEPAD: i32 = 0x401;
+
+    fn joystick(values: &[(Axis, f32)]) -> MotionEventSnapshot {
+        let mut axis_values = vec![0.0; MOTION_EVENT_AXIS_COUNT];
+        for &(axis, value) in values {
+            axis_values[i32::from(axis) as usize] = value;
+        }
+        MotionEventSnapshot {
+            device_id: DEVICE,
+            source: SOURCE_JOYSTICK,
+            action: MotionAction::Move.into(),
+            pointers: vec![crate::PointerProperties {
+                id: 0,
+                tool_type: ToolType::Unknown.into(),
+            }],
+            sample_times: vec![0],
+            axis_values,
+            ..Default::default()
+        }
+    }
+
+    fn key(key_code: Keycode, action: KeyAction) -> KeyEventSnapshot {
+        KeyEventSnapshot {
+            device_id: DEVICE,
+            source: SOURCE_GAMEPAD,
+            action: action.into(),
+            key_code: key_code.into(),
+            ..Default::default()
+        }
+    }
+
+    fn buttons(events: &[GamepadEvent]) -> Vec<(GamepadButton, bool)> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                GamepadEvent::Button {
+                    button, pressed, ..
+                } => Some((*button, *pressed)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stick_dead_zone_is_radial() {
+        let mut state = GamepadState::new(0.2);
+        // Each component is below the dead zone, but the stick isn't.
+        state.handle_motion_event(&joystick(&[(Axis::X, 0.15), (Axis::Y, 0.15)]));
+        assert_eq!(state.axis(DEVICE, GamepadAxis::LeftStickX), 0.15);
+        assert_eq!(state.axis(DEVICE, GamepadAxis::LeftStickY), 0.15);
+
+        state.handle_motion_event(&joystick(&[(Axis::X, 0.1), (Axis::Y, 0.1)]));
+        assert_eq!(state.axis(DEVICE, GamepadAxis::LeftStickX), 0.0);
+        assert_eq!(state.axis(DEVICE, GamepadAxis::LeftStickY), 0.0);
+
+        // Triggers keep a dead zone of their own.
+        state.handle_motion_event(&joystick(&[(Axis::Ltrigger, 0.1)]));
+        assert_eq!(state.axis(DEVICE, GamepadAxis::LeftTrigger), 0.0);
+    }
+
+    #[test]
+    fn hat_reports_dpad_buttons() {
+        let mut state = GamepadState::default();
+        let events = state.handle_motion_event(&joystick(&[(Axis::HatX, -1.0)]));
+        assert_eq!(buttons(&events), [(GamepadButton::DpadLeft, true)]);
+        let events = state.handle_motion_event(&joystick(&[(Axis::HatY, 1.0)]));
+        assert_eq!(
+            buttons(&events),
+            [
+                (GamepadButton::DpadLeft, false),
+                (GamepadButton::DpadDown, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn stick_motion_keeps_dpad_keys() {
+        let mut state = GamepadState::default();
+        let events = state.handle_key_event(&key(Keycode::DpadUp, KeyAction::Down));
+        assert_eq!(buttons(&events), [(GamepadButton::DpadUp, true)]);
+        let events = state.handle_motion_event(&joystick(&[(Axis::X, 0.8)]));
+        assert!(buttons(&events).is_empty());
+        assert!(state.is_pressed(DEVICE, GamepadButton::DpadUp));
+        let events = state.handle_key_event(&key(Keycode::DpadUp, KeyAction::Up));
+        assert_eq!(buttons(&events), [(GamepadButton::DpadUp, false)]);
+    }
+
+    const MS: u64 = 1_000_000;
+
+    fn finger() -> ui_events::pointer::PointerInfo {
+        ui_events::pointer::PointerInfo {
+            pointer_id: PointerId::new(1),
+            persistent_device_id: None,
+            pointer_type: ui_events::pointer::PointerType::Touch,
+        }
+    }
+
+    fn at(time: u64) -> PointerState {
+        PointerState {
+            time,
+            ..Default::default()
+        }
+    }
+
+    fn down(time: u64) -> PointerEvent {
+        PointerEvent::Down {
+            pointer: finger(),
+            state: at(time),
+            button: None,
+        }
+    }
+
+    fn up(time: u64) -> PointerEvent {
+        PointerEvent::Up {
+            pointer: finger(),
+            state: at(time),
+            button: None,
+        }
+    }
+
+    /// A counter that has opted in to long presses, as `schedule_long_press` does.
+    fn scheduled() -> TapCounter {
+        TapCounter {
+            long_press_scheduled: true,
+            ..TapCounter::default()
+        }
+    }
+
+    #[test]
+    fn long_press_fires_at_deadline() {
+        let mut counter = scheduled();
+        counter.attach_count(down(1_000 * MS));
+        assert_eq!(counter.long_press_deadline(), Some(1_400 * MS));
+        assert!(counter.take_long_presses(1_399 * MS).is_empty());
+        let fired = counter.take_long_presses(1_400 * MS);
+        assert!(matches!(
+            fired[..],
+            [GestureEvent::LongPress { pointer, .. }] if pointer == finger()
+        ));
+        assert_eq!(counter.long_press_deadline(), None);
+    }
+
+    #[test]
+    fn long_press_cancelled_by_up_and_cancel() {
+        let mut counter = scheduled();
+        counter.attach_count(down(0));
+        counter.attach_count(up(100 * MS));
+        assert_eq!(counter.long_press_deadline(), None);
+        assert!(counter.take_long_presses(1_000 * MS).is_empty());
+
+        counter.attach_count(down(2_000 * MS));
+        counter.attach_count(PointerEvent::Cancel(finger()));
+        assert_eq!(counter.long_press_deadline(), None);
+        assert!(counter.take_long_presses(3_000 * MS).is_empty());
+    }
+
+    #[test]
+    fn long_press_reported_by_later_event_waits_for_timer() {
+        let mut counter = scheduled();
+        counter.attach_count(down(0));
+        // The timer was late, so the up recognizes the long press.
+        counter.attach_count(up(500 * MS));
+        assert_eq!(counter.long_press_deadline(), Some(0));
+        assert_eq!(counter.take_long_presses(500 * MS).len(), 1);
+        assert_eq!(counter.long_press_deadline(), None);
+    }
+
+    #[test]
+    fn long_presses_not_kept_until_scheduled() {
+        let mut counter = TapCounter::default();
+        for i in 0..3 {
+            counter.attach_count(down(i * 1_000 * MS));
+            counter.attach_count(up(i * 1_000 * MS + 500 * MS));
+        }
+        assert!(counter.long_presses.is_empty());
+    }
+}
@@ -0,0 +1,448 @@
+//! A single-threaded executor driven by the `Looper` of a view.
+//!
+//! Futures spawned with [`CallbackCtx::spawn_local`] are polled on the UI
+//! thread after each callback to the peer, while the peer is not borrowed.
+//! Their wakers are `Send`, so they can be woken by channels and other
+//! primitives completed from background threads. All of a peer's futures
+//! are dropped when the peer is removed in `on_detached_from_window`.
+
+use jni::sys::jlong;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    mem,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
+    time::Duration,
+};
+
+use crate::{CallbackCtx, TimerToken, ViewHandle, error::Error, util::uptime_nanos};
+
+thread_local! {
+    /// The executor whose tasks are being polled on this thread.
+    static CURRENT: RefCell<Option<Rc<Executor>>> = const { RefCell::new(None) };
+}
+
+fn current() -> Rc<Executor> {
+    CURRENT
+        .with(|current| current.borrow().clone())
+        .expect("view futures must be polled by the executor of a view")
+}
+
+/// Identifies a future spawned with [`CallbackCtx::spawn_local`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(u64);
+
+type LocalTask = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Wakes the view to run the executor, returning whether it will run.
+type WakeView = Arc<dyn Fn() -> bool + Send + Sync>;
+
+/// The woken tasks, shared with their wakers.
+#[derive(Default)]
+struct Ready {
+    tasks: BTreeSet<u64>,
+    /// Whether the view has been woken to run them.
+    woken: bool,
+}
+
+struct TaskWaker {
+    id: u64,
+    ready: Arc<Mutex<Ready>>,
+    wake_view: WakeView,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let needs_wake = {
+            let mut ready = self.ready.lock().unwrap();
+            ready.tasks.insert(self.id);
+            !mem::replace(&mut ready.woken, true)
+        };
+        if needs_wake && !(self.wake_view)() {
+            // Let the next wake try again. The tasks stay ready, so they
+            // are still polled if the executor runs anyway.
+            self.ready.lock().unwrap().woken = false;
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Executor {
+    tasks: RefCell<BTreeMap<u64, LocalTask>>,
+    next_task: Cell<u64>,
+    ready: Arc<Mutex<Ready>>,
+    /// Created by the first spawn, which has the JNI environment to do so.
+    handle: RefCell<Option<ViewHandle>>,
+    /// The task being polled.
+    polling: Cell<u64>,
+    /// Deadlines of the sleeping tasks, with their IDs and wakers.
+    sleepers: RefCell<Vec<(u64, u64, Waker)>>,
+    /// The timer started for the earliest sleeper, and its deadline.
+    timer: Cell<Option<(TimerToken, u64)>>,
+    frame_waiters: RefCell<Vec<(u64, Waker)>>,
+    /// Number of frames seen and the time of the latest one.
+    frame: Cell<(u64, jlong)>,
+}
+
+impl Executor {
+    fn spawn(&self, ctx: &mut CallbackCtx, future: LocalTask) -> Result<TaskId, Error> {
+        if self.handle.borrow().is_none() {
+            *self.handle.borrow_mut() = Some(ctx.try_view_handle()?);
+        }
+        Ok(self.insert(future))
+    }
+
+    /// Add a task to be polled by the next run.
+    fn insert(&self, future: LocalTask) -> TaskId {
+        let id = self.next_task.get();
+        self.next_task.set(id + 1);
+        self.tasks.borrow_mut().insert(id, future);
+        self.ready.lock().unwrap().tasks.insert(id);
+        TaskId(id)
+    }
+
+    fn cancel(&self, task: TaskId) -> bool {
+        self.ready.lock().unwrap().tasks.remove(&task.0);
+        let removed = self.tasks.borrow_mut().remove(&task.0);
+        self.forget_waits(task.0);
+        removed.is_some()
+    }
+
+    /// Drop the timer and frame waits of a task that has finished or been
+    /// cancelled, so that they don't keep requesting callbacks.
+    fn forget_waits(&self, id: u64) {
+        self.sleepers
+            .borrow_mut()
+            .retain(|&(_, task, _)| task != id);
+        self.frame_waiters
+            .borrow_mut()
+            .retain(|&(task, _)| task != id);
+    }
+
+    /// Drop every task, such as when the peer is removed.
+    pub(crate) fn clear(&self) {
+        // Take the tasks out first, since dropping a future may reenter
+        // the executor.
+        let tasks = self.tasks.take();
+        drop(tasks);
+        self.ready.lock().unwrap().tasks.clear();
+        self.sleepers.borrow_mut().clear();
+        self.frame_waiters.borrow_mut().clear();
+    }
+
+    /// Wake the sleepers if `token` is the executor's timer, returning
+    /// whether it was.
+    pub(crate) fn timer_fired(&self, token: TimerToken) -> bool {
+        if self.timer.get().is_none_or(|(t, _)| t != token) {
+            return false;
+        }
+        self.timer.set(None);
+        self.wake_sleepers(uptime_nanos());
+        true
+    }
+
+    fn wake_sleepers(&self, now: u64) {
+        self.sleepers.borrow_mut().retain(|(deadline, _, waker)| {
+            let expired = *deadline <= now;
+            if expired {
+                waker.wake_by_ref();
+            }
+            !expired
+        });
+    }
+
+    pub(crate) fn frame(&self, frame_time_nanos: jlong) {
+        let (count, _) = self.frame.get();
+        self.frame.set((count + 1, frame_time_nanos));
+        for (_, waker) in self.frame_waiters.take() {
+            waker.wake();
+        }
+    }
+
+    /// Poll every woken task until none are left, then request the timer
+    /// and frame callbacks that the remaining tasks are waiting for.
+    pub(crate) fn run(self: &Rc<Self>, ctx: &mut CallbackCtx) {
+        let Some(handle) = self.handle.borrow().clone() else {
+            return;
+        };
+        // Running the posted callbacks also drives the executor.
+        let wake_view: WakeView =
+            Arc::new(move || handle.try_post_raw(Box::new(|_, _| {})).unwrap_or(false));
+        self.poll_ready(&wake_view);
+        self.schedule(ctx);
+    }
+
+    /// Poll every woken task until none are left.
+    fn poll_ready(self: &Rc<Self>, wake_view: &WakeView) {
+        let previous = CURRENT.with(|current| current.replace(Some(Rc::clone(self))));
+        loop {
+            let ready = {
+                let mut ready = self.ready.lock().unwrap();
+                ready.woken = false;
+                mem::take(&mut ready.tasks)
+            };
+            if ready.is_empty() {
+                break;
+            }
+            for id in ready {
+                // The task is taken out while it is polled so that it can
+                // spawn or cancel other tasks.
+                let Some(mut task) = self.tasks.borrow_mut().remove(&id) else {
+                    continue;
+                };
+                let waker = Waker::from(Arc::new(TaskWaker {
+                    id,
+                    ready: Arc::clone(&self.ready),
+                    wake_view: Arc::clone(wake_view),
+                }));
+                self.polling.set(id);
+                if task
+                    .as_mut()
+                    .poll(&mut Context::from_waker(&waker))
+                    .is_pending()
+                {
+                    self.tasks.borrow_mut().insert(id, task);
+                } else {
+                    drop(task);
+                    self.forget_waits(id);
+                }
+            }
+        }
+        CURRENT.with(|current| current.replace(previous));
+    }
+
+    /// The earliest deadline of the sleeping tasks.
+    fn next_deadline(&self) -> Option<u64> {
+        self.sleepers.borrow().iter().map(|&(d, ..)| d).min()
+    }
+
+    fn schedule(&self, ctx: &mut CallbackCtx) {
+        let deadline = self.next_deadline();
+        if self.timer.get().map(|(_, d)| d) != deadline {
+            if let Some((token, _)) = self.timer.take() {
+                ctx.cancel_timer(token);
+            }
+            if let Some(deadline) = deadline {
+                let delay_nanos = deadline.saturating_sub(uptime_nanos());
+                let token = ctx.start_timer(Duration::from_millis(delay_nanos.div_ceil(1_000_000)));
+                self.timer.set(Some((token, deadline)));
+            }
+        }
+        if !self.frame_waiters.borrow().is_empty() {
+            ctx.request_frame();
+        }
+    }
+}
+
+impl CallbackCtx<'_> {
+    /// Poll `future` on the UI thread until it completes, is cancelled,
+    /// or the peer is removed.
+    pub fn spawn_local(&mut self, future: impl 'static + Future<Output = ()>) -> TaskId {
+        self.try_spawn_local(future).unwrap()
+    }
+
+    pub fn try_spawn_local(
+        &mut self,
+        future: impl 'static + Future<Output = ()>,
+    ) -> Result<TaskId, Error> {
+        let executor = self.executor();
+        executor.spawn(self, Box::pin(future))
+    }
+
+    /// Drop a spawned future. Returns `false` if it had already completed.
+    pub fn cancel_task(&mut self, task: TaskId) -> bool {
+        self.executor().cancel(task)
+    }
+
+    fn executor(&self) -> Rc<Executor> {
+        // Set for every context handed to a peer.
+        self.executor.clone().unwrap()
+    }
+}
+
+/// Wait until `duration` has passed, using the view's `Looper`.
+///
+/// Panics if polled outside the executor of a view.
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: uptime_nanos() + duration.as_nanos() as u64,
+    }
+}
+
+/// Future returned by [`sleep`].
+pub struct Sleep {
+    deadline: u64,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if uptime_nanos() >= self.deadline {
+            return Poll::Ready(());
+        }
+        let executor = current();
+        let mut sleepers = executor.sleepers.borrow_mut();
+        if !sleepers
+            .iter()
+            .any(|(d, _, w)| *d == self.deadline && w.will_wake(cx.waker()))
+        {
+            sleepers.push((self.deadline, executor.polling.get(), cx.waker().clone()));
+        }
+        Poll::Pending
+    }
+}
+
+/// Wait for the next frame callback, resolving to its frame time in
+/// nanoseconds.
+///
+/// This requests a frame as with
+/// [`CallbackCtx::request_frame`], which is also delivered to
+/// [`ViewPeer::do_frame`](crate::ViewPeer::do_frame) and is held back while
+/// the window is hidden.
+///
+/// Panics if polled outside the executor of a view.
+pub fn next_frame() -> NextFrame {
+    NextFrame { after: None }
+}
+
+/// Future returned by [`next_frame`].
+pub struct NextFrame {
+    /// The frame count when first polled.
+    after: Option<u64>,
+}
+
+impl Future for NextFrame {
+    type Output = jlong;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<jlong> {
+        let executor = current();
+        let (count, time) = executor.frame.get();
+        if self.after.is_some_and(|after| count > after) {
+            return Poll::Ready(time);
+        }
+        self.after.get_or_insert(count);
+        executor
+            .frame_waiters
+            .borrow_mut()
+            .push((executor.polling.get(), cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A view wake that counts its calls and succeeds if `succeeds`.
+    fn wake_view(succeeds: bool) -> (WakeView, Arc<AtomicUsize>) {
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&wakes);
+        let wake: WakeView = Arc::new(move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+            succeeds
+        });
+        (wake, wakes)
+    }
+
+    /// The waker that `poll_ready` gives to a task.
+    fn waker(executor: &Executor, id: u64, wake_view: &WakeView) -> Waker {
+        Waker::from(Arc::new(TaskWaker {
+            id,
+            ready: Arc::clone(&executor.ready),
+            wake_view: Arc::clone(wake_view),
+        }))
+    }
+
+    #[test]
+    fn failed_wake_is_retried() {
+        let executor = Executor::default();
+        let (wake, wakes) = wake_view(false);
+        waker(&executor, 0, &wake).wake();
+        waker(&executor, 1, &wake).wake();
+        assert_eq!(wakes.load(Ordering::Relaxed), 2);
+
+        let (wake, wakes) = wake_view(true);
+        waker(&executor, 2, &wake).wake();
+        waker(&executor, 3, &wake).wake();
+        assert_eq!(wakes.load(Ordering::Relaxed), 1);
+        assert_eq!(executor.ready.lock().unwrap().tasks.len(), 4);
+    }
+
+    #[test]
+    fn sleep_reschedules_to_next_deadline() {
+        let executor = Rc::new(Executor::default());
+        let (wake, _) = wake_view(true);
+        let start = uptime_nanos();
+        executor.insert(Box::pin(sleep(Duration::from_millis(1))));
+        executor.insert(Box::pin(sleep(Duration::from_secs(3600))));
+        executor.poll_ready(&wake);
+        let first = executor.next_deadline().unwrap();
+        assert!(first >= start + 1_000_000 && first < start + 3_600_000_000_000);
+
+        std::thread::sleep(Duration::from_millis(2));
+        executor.wake_sleepers(uptime_nanos());
+        executor.poll_ready(&wake);
+        assert_eq!(executor.tasks.borrow().len(), 1);
+        assert!(executor.next_deadline().unwrap() >= start + 3_600_000_000_000);
+    }
+
+    #[test]
+    fn cancel_task_drops_waits() {
+        let executor = Rc::new(Executor::default());
+        let (wake, _) = wake_view(true);
+        let sleeper = executor.insert(Box::pin(sleep(Duration::from_secs(3600))));
+        let frame = executor.insert(Box::pin(async {
+            next_frame().await;
+        }));
+        executor.poll_ready(&wake);
+        assert!(executor.next_deadline().is_some());
+        assert!(!executor.frame_waiters.borrow().is_empty());
+
+        assert!(executor.cancel(sleeper));
+        assert!(executor.cancel(frame));
+        assert!(!executor.cancel(frame));
+        assert_eq!(executor.next_deadline(), None);
+        assert!(executor.frame_waiters.borrow().is_empty());
+    }
+
+    #[test]
+    fn next_frame_resolves_once_per_frame() {
+        let executor = Rc::new(Executor::default());
+        let (wake, _) = wake_view(true);
+        let frames = Rc::new(RefCell::new(vec![]));
+        let seen = Rc::clone(&frames);
+        executor.insert(Box::pin(async move {
+            loop {
+                let time = next_frame().await;
+                seen.borrow_mut().push(time);
+            }
+        }));
+        executor.poll_ready(&wake);
+        assert!(frames.borrow().is_empty());
+
+        executor.frame(100);
+        executor.poll_ready(&wake);
+        assert_eq!(*frames.borrow(), [100]);
+
+        // Without another frame, a spurious wake leaves the next wait pending.
+        for (_, waker) in executor.frame_waiters.take() {
+            waker.wake();
+        }
+        executor.poll_ready(&wake);
+        assert_eq!(*frames.borrow(), [100]);
+
+        executor.frame(200);
+        executor.poll_ready(&wake);
+        assert_eq!(*frames.borrow(), [100, 200]);
+    }
+}
@@ -0,0 +1,254 @@
+//! Timing of the frames delivered to [`ViewPeer::do_frame`], and the
+//! scheduler that requests them.
+
+use std::{cell::Cell, time::Duration};
+
+#[cfg(doc)]
+use crate::ViewPeer;
+use crate::{callback_ctx::*, util::*};
+
+/// `View.VISIBLE`.
+const VISIBLE: i32 = 0;
+
+/// The timing of a frame. All times are in nanoseconds on
+/// `CLOCK_MONOTONIC`, the time base of `System.nanoTime` and input events.
+///
+/// From API 33, these come from the preferred frame timeline of
+/// `Choreographer.FrameData`. Before that, only the vsync time is reported
+/// by the system, and the deadline and presentation time are estimated from
+/// the refresh rate of the display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FrameInfo {
+    /// The time of the vsync that started this frame. Animations should
+    /// use this rather than the current time, so they advance evenly.
+    pub frame_time_nanos: i64,
+    /// The time the frame is expected to be shown, if it is rendered by
+    /// the deadline.
+    pub expected_presentation_time_nanos: i64,
+    /// The time by which rendering must finish to be shown at
+    /// `expected_presentation_time_nanos`.
+    pub deadline_nanos: i64,
+    /// The time between vsyncs at the display's current refresh rate.
+    pub frame_interval_nanos: i64,
+}
+
+impl FrameInfo {
+    /// The time between vsyncs at the display's current refresh rate.
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_nanos(self.frame_interval_nanos.max(0) as u64)
+    }
+
+    /// The time left until the deadline, as of `now_nanos`, or zero if it
+    /// has passed.
+    pub fn time_until_deadline(&self, now_nanos: i64) -> Duration {
+        Duration::from_nanos(self.deadline_nanos.saturating_sub(now_nanos).max(0) as u64)
+    }
+}
+
+/// When the frame scheduler of a peer requests frames. Set with
+/// [`CallbackCtx::set_frame_mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FrameMode {
+    /// Only after [`CallbackCtx::request_frame`].
+    #[default]
+    OnDemand,
+    /// Every frame, until the mode is changed.
+    Continuous,
+    /// Every frame for the given time after the mode is set, then on
+    /// demand.
+    ContinuousFor(Duration),
+}
+
+/// Posts the frame callbacks of a peer, at most once per callback and not
+/// while its window is hidden.
+pub(crate) struct FrameScheduler {
+    mode: Cell<FrameMode>,
+    /// The uptime in nanoseconds when `FrameMode::ContinuousFor` ends.
+    continuous_until: Cell<u64>,
+    /// Whether a frame has been requested since the last one.
+    requested: Cell<bool>,
+    /// Whether a frame callback is pending.
+    posted: Cell<bool>,
+    visible: Cell<bool>,
+}
+
+impl Default for FrameScheduler {
+    fn default() -> Self {
+        Self {
+            mode: Cell::new(FrameMode::OnDemand),
+            continuous_until: Cell::new(0),
+            requested: Cell::new(false),
+            posted: Cell::new(false),
+            // Until `onWindowVisibilityChanged`, which is always forwarded.
+            visible: Cell::new(true),
+        }
+    }
+}
+
+impl FrameScheduler {
+    fn request(&self) {
+        self.requested.set(true);
+    }
+
+    fn set_mode(&self, mode: FrameMode, now: u64) {
+        if let FrameMode::ContinuousFor(duration) = mode {
+            self.continuous_until
+                .set(now.saturating_add(duration.as_nanos() as u64));
+        }
+        self.mode.set(mode);
+    }
+
+    fn wants_frame(&self, now: u64) -> bool {
+        match self.mode.get() {
+            FrameMode::OnDemand => self.requested.get(),
+            FrameMode::Continuous => true,
+            FrameMode::ContinuousFor(_) => {
+                if now < self.continuous_until.get() {
+                    return true;
+                }
+                self.mode.set(FrameMode::OnDemand);
+                self.requested.get()
+            }
+        }
+    }
+
+    /// Called before a frame is delivered to the peer, which satisfies the
+    /// requests made so far.
+    pub(crate) fn frame_delivered(&self) {
+        self.posted.set(false);
+        self.requested.set(false);
+    }
+
+    /// Called when the frame callback is removed, by the scheduler or the
+    /// peer.
+    pub(crate) fn frame_removed(&self) {
+        self.posted.set(false);
+    }
+
+    /// Called when the visibility of the window changes. Frames stop while
+    /// it is hidden, and resume when it is shown if still wanted.
+    pub(crate) fn set_window_visibility(&self, ctx: &mut CallbackCtx, visibility: i32) {
+        if self.set_visible(visibility == VISIBLE) {
+            ctx.remove_frame_callback();
+        }
+    }
+
+    /// Returns whether the pending frame callback should be removed.
+    fn set_visible(&self, visible: bool) -> bool {
+        self.visible.set(visible);
+        !visible && self.posted.get()
+    }
+
+    /// Post a frame callback if one is wanted and none is pending. Called
+    /// when each callback returns.
+    pub(crate) fn flush(&self, ctx: &mut CallbackCtx) {
+        if self.take_post(uptime_nanos()) {
+            ctx.post_frame_callback();
+        }
+    }
+
+    /// Returns whether to post a frame callback, which is then pending.
+    fn take_post(&self, now: u64) -> bool {
+        let post = self.visible.get() && !self.posted.get() && self.wants_frame(now);
+        if post {
+            self.posted.set(true);
+        }
+        post
+    }
+}
+
+impl CallbackCtx<'_> {
+    /// Ask for a call to [`ViewPeer::do_frame`]. Unlike
+    /// [`post_frame_callback`](Self::post_frame_callback), requests are
+    /// combined into one frame callback when this callback returns, and
+    /// held back while the window is hidden.
+    pub fn request_frame(&mut self) {
+        if let Some(scheduler) = &self.frame_scheduler {
+            scheduler.request();
+        }
+    }
+
+    pub fn frame_mode(&self) -> FrameMode {
+        self.frame_scheduler
+            .as_ref()
+            .map_or(FrameMode::OnDemand, |scheduler| scheduler.mode.get())
+    }
+
+    /// Change when frames are requested. The continuous modes request a
+    /// frame after each one, so [`ViewPeer::do_frame`] doesn't have to.
+    pub fn set_frame_mode(&mut self, mode: FrameMode) {
+        if let Some(scheduler) = &self.frame_scheduler {
+            scheduler.set_mode(mode, uptime_nanos());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MILLIS: u64 = 1_000_000;
+
+    #[test]
+    fn requests_are_combined() {
+        let scheduler = FrameScheduler::default();
+        assert!(!scheduler.take_post(0));
+        scheduler.request();
+        scheduler.request();
+        assert!(scheduler.take_post(0));
+        // Already pending.
+        scheduler.request();
+        assert!(!scheduler.take_post(0));
+        scheduler.frame_delivered();
+        assert!(!scheduler.take_post(0));
+    }
+
+    #[test]
+    fn continuous_requests_every_frame() {
+        let scheduler = FrameScheduler::default();
+        scheduler.set_mode(FrameMode::Continuous, 0);
+        for frame in 0..3 {
+            assert!(scheduler.take_post(frame * 16 * MILLIS));
+            scheduler.frame_delivered();
+        }
+        scheduler.set_mode(FrameMode::OnDemand, 0);
+        assert!(!scheduler.take_post(64 * MILLIS));
+    }
+
+    #[test]
+    fn continuous_for_expires() {
+        let scheduler = FrameScheduler::default();
+        scheduler.set_mode(FrameMode::ContinuousFor(Duration::from_millis(50)), 0);
+        assert!(scheduler.take_post(16 * MILLIS));
+        scheduler.frame_delivered();
+        assert!(scheduler.take_post(48 * MILLIS));
+        scheduler.frame_delivered();
+        assert!(!scheduler.take_post(64 * MILLIS));
+        assert_eq!(scheduler.mode.get(), FrameMode::OnDemand);
+        // Requests still work once it has ended.
+        scheduler.request();
+        assert!(scheduler.take_post(80 * MILLIS));
+    }
+
+    #[test]
+    fn hidden_window_holds_back_frames() {
+        let scheduler = FrameScheduler::default();
+        scheduler.request();
+        assert!(!scheduler.set_visible(false));
+        assert!(!scheduler.take_post(0));
+        assert!(!scheduler.set_visible(true));
+        assert!(scheduler.take_post(0));
+    }
+
+    #[test]
+    fn hiding_removes_pending_frame() {
+        let scheduler = FrameScheduler::default();
+        scheduler.set_mode(FrameMode::Continuous, 0);
+        assert!(scheduler.take_post(0));
+        assert!(scheduler.set_visible(false));
+        scheduler.frame_removed();
+        assert!(!scheduler.take_post(16 * MILLIS));
+        scheduler.set_visible(true);
+        assert!(scheduler.take_post(32 * MILLIS));
+    }
+}
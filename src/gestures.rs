@@ -0,0 +1,673 @@
+//! Gesture recognizers built on [`PointerEvent`]s.
+//!
+//! Recognizers only look at event timestamps, never at a clock, so they can
+//! be driven from recorded events. Those that fire after a delay report when
+//! they next need to run through [`GestureRecognizer::next_deadline`], and are
+//! then driven with [`GestureRecognizer::tick`].
+
+use dpi::PhysicalPosition;
+use std::collections::{BTreeMap, BTreeSet};
+use ui_events::pointer::{PointerEvent, PointerInfo, PointerState};
+
+use crate::{Velocity, VelocityTracker, ViewConfiguration};
+
+/// A high-level gesture recognized from pointer events.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GestureEvent {
+    /// A pointer has been held down without moving beyond the slop.
+    LongPress {
+        pointer: PointerInfo,
+        position: PhysicalPosition<f64>,
+    },
+    /// A pointer moved beyond the slop. `start` is where it went down.
+    DragStart {
+        pointer: PointerInfo,
+        start: PhysicalPosition<f64>,
+        position: PhysicalPosition<f64>,
+    },
+    /// A dragging pointer moved by `delta`.
+    DragMove {
+        pointer: PointerInfo,
+        position: PhysicalPosition<f64>,
+        delta: PhysicalPosition<f64>,
+    },
+    /// A dragging pointer was lifted.
+    DragEnd {
+        pointer: PointerInfo,
+        position: PhysicalPosition<f64>,
+        velocity: Velocity,
+    },
+    /// A drag was cancelled by the system.
+    DragCancel { pointer: PointerInfo },
+    /// Two or more pointers started a pinch. `span` is the distance
+    /// between them in pixels.
+    PinchStart {
+        focus: PhysicalPosition<f64>,
+        span: f64,
+    },
+    /// The pointers of a pinch moved. `scale` and `rotation` (in radians,
+    /// clockwise) are relative to the previous update.
+    PinchUpdate {
+        focus: PhysicalPosition<f64>,
+        scale: f64,
+        rotation: f64,
+    },
+    /// Fewer than two pointers remain.
+    PinchEnd,
+}
+
+/// Turns pointer events and the passage of time into [`GestureEvent`]s.
+pub trait GestureRecognizer {
+    fn pointer_event(&mut self, event: &PointerEvent, out: &mut Vec<GestureEvent>);
+
+    /// Advance to `time`, in the time base of [`PointerState::time`].
+    fn tick(&mut self, time: u64, out: &mut Vec<GestureEvent>) {
+        let _ = (time, out);
+    }
+
+    /// The next time [`tick`](Self::tick) needs to be called, if any.
+    fn next_deadline(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl GestureRecognizer for Vec<Box<dyn GestureRecognizer>> {
+    fn pointer_event(&mut self, event: &PointerEvent, out: &mut Vec<GestureEvent>) {
+        for recognizer in self {
+            recognizer.pointer_event(event, out);
+        }
+    }
+
+    fn tick(&mut self, time: u64, out: &mut Vec<GestureEvent>) {
+        for recognizer in self {
+            recognizer.tick(time, out);
+        }
+    }
+
+    fn next_deadline(&self) -> Option<u64> {
+        self.iter().filter_map(|r| r.next_deadline()).min()
+    }
+}
+
+fn distance(a: PhysicalPosition<f64>, b: PhysicalPosition<f64>) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+struct Press {
+    pointer: PointerInfo,
+    position: PhysicalPosition<f64>,
+    deadline: u64,
+}
+
+/// Recognizes a single pointer held still for `long_press_timeout`.
+#[derive(Default)]
+pub struct LongPressRecognizer {
+    timeout_nanos: u64,
+    slop: f64,
+    press: Option<Press>,
+    pointers_down: usize,
+}
+
+impl LongPressRecognizer {
+    pub fn new(vc: &ViewConfiguration) -> Self {
+        Self::with_params(
+            vc.long_press_timeout as u64 * 1_000_000,
+            vc.scaled_touch_slop as f64,
+        )
+    }
+
+    pub fn with_params(timeout_nanos: u64, slop: f64) -> Self {
+        Self {
+            timeout_nanos,
+            slop,
+            press: None,
+            pointers_down: 0,
+        }
+    }
+}
+
+impl GestureRecognizer for LongPressRecognizer {
+    fn pointer_event(&mut self, event: &PointerEvent, out: &mut Vec<GestureEvent>) {
+        match event {
+            PointerEvent::Down { pointer, state, .. } => {
+                self.tick(state.time, out);
+                self.pointers_down += 1;
+                // A second pointer turns the press into some other gesture.
+                self.press = (self.pointers_down == 1).then(|| Press {
+                    pointer: *pointer,
+                    position: state.position,
+                    deadline: state.time + self.timeout_nanos,
+                });
+            }
+            PointerEvent::Move(update) => {
+                for state in update.coalesced.iter().chain([&update.current]) {
+                    self.tick(state.time, out);
+                    if self.press.as_ref().is_some_and(|p| {
+                        p.pointer == update.pointer
+                            && distance(p.position, state.position) > self.slop
+                    }) {
+                        self.press = None;
+                    }
+                }
+            }
+            PointerEvent::Up { state, .. } => {
+                self.tick(state.time, out);
+                self.pointers_down = self.pointers_down.saturating_sub(1);
+                self.press = None;
+            }
+            PointerEvent::Cancel(..) => {
+                // A cancellation ends the whole gesture, even if it is
+                // only reported for one of its pointers.
+                self.pointers_down = 0;
+                self.press = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, time: u64, out: &mut Vec<GestureEvent>) {
+        if let Some(press) = self.press.take_if(|p| time >= p.deadline) {
+            out.push(GestureEvent::LongPress {
+                pointer: press.pointer,
+                position: press.position,
+            });
+        }
+    }
+
+    fn next_deadline(&self) -> Option<u64> {
+        self.press.as_ref().map(|p| p.deadline)
+    }
+}
+
+struct DragState {
+    start: PhysicalPosition<f64>,
+    last: PhysicalPosition<f64>,
+    dragging: bool,
+}
+
+/// Recognizes pointers that move beyond a slop distance after going down.
+pub struct DragRecognizer {
+    slop: f64,
+    pointers: BTreeMap<PointerInfo, DragState>,
+    velocity_tracker: VelocityTracker,
+}
+
+impl DragRecognizer {
+    /// Recognize drags beyond `scaled_touch_slop`.
+    pub fn new(vc: &ViewConfiguration) -> Self {
+        Self::with_slop(vc.scaled_touch_slop as f64)
+    }
+
+    /// Recognize drags beyond `scaled_paging_touch_slop`, for paging
+    /// between screens of content.
+    pub fn paging(vc: &ViewConfiguration) -> Self {
+        Self::with_slop(vc.scaled_paging_touch_slop as f64)
+    }
+
+    pub fn with_slop(slop: f64) -> Self {
+        Self {
+            slop,
+            pointers: BTreeMap::new(),
+            velocity_tracker: VelocityTracker::new(),
+        }
+    }
+
+    fn moved(&mut self, pointer: PointerInfo, state: &PointerState, out: &mut Vec<GestureEvent>) {
+        let Some(drag) = self.pointers.get_mut(&pointer) else {
+            return;
+        };
+        let position = state.position;
+        if !drag.dragging {
+            if distance(drag.start, position) <= self.slop {
+                return;
+            }
+            drag.dragging = true;
+            out.push(GestureEvent::DragStart {
+                pointer,
+                start: drag.start,
+                position,
+            });
+        } else {
+            out.push(GestureEvent::DragMove {
+                pointer,
+                position,
+                delta: PhysicalPosition::new(position.x - drag.last.x, position.y - drag.last.y),
+            });
+        }
+        drag.last = position;
+    }
+}
+
+impl GestureRecognizer for DragRecognizer {
+    fn pointer_event(&mut self, event: &PointerEvent, out: &mut Vec<GestureEvent>) {
+        self.velocity_tracker.add_event(event);
+        match event {
+            PointerEvent::Down { pointer, state, .. } => {
+                self.pointers.insert(
+                    *pointer,
+                    DragState {
+                        start: state.position,
+                        last: state.position,
+                        dragging: false,
+                    },
+                );
+            }
+            PointerEvent::Move(update) => {
+                for state in update.coalesced.iter().chain([&update.current]) {
+                    self.moved(update.pointer, state, out);
+                }
+            }
+            PointerEvent::Up { pointer, state, .. } => {
+                self.moved(*pointer, state, out);
+                if self.pointers.remove(pointer).is_some_and(|d| d.dragging) {
+                    out.push(GestureEvent::DragEnd {
+                        pointer: *pointer,
+                        position: state.position,
+                        velocity: self.velocity_tracker.velocity(pointer).unwrap_or_default(),
+                    });
+                }
+            }
+            PointerEvent::Cancel(pointer)
+                if self.pointers.remove(pointer).is_some_and(|d| d.dragging) =>
+            {
+                out.push(GestureEvent::DragCancel { pointer: *pointer });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Recognizes two-finger scaling and rotation.
+///
+/// The focus is the centroid of all pointers that are down, and the scale
+/// follows their average distance from it, as in `ScaleGestureDetector`.
+/// Rotation follows the line between the first two pointers.
+///
+/// The moves of all pointers at the same time, as produced from one
+/// `MotionEvent`, are combined into a single update.
+pub struct PinchRecognizer {
+    min_span: f64,
+    pointers: BTreeMap<PointerInfo, PhysicalPosition<f64>>,
+    /// Span and angle at the last update, while a pinch is in progress.
+    active: Option<(f64, f64)>,
+    /// The time of the moves not yet reported, and the pointers they moved.
+    batch: Option<(u64, BTreeSet<PointerInfo>)>,
+}
+
+impl PinchRecognizer {
+    pub fn new(vc: &ViewConfiguration) -> Self {
+        Self::with_min_span(vc.scaled_minimum_scaling_span as f64)
+    }
+
+    pub fn with_min_span(min_span: f64) -> Self {
+        Self {
+            min_span,
+            pointers: BTreeMap::new(),
+            active: None,
+            batch: None,
+        }
+    }
+
+    /// Focus, span and angle of the pointers that are down.
+    fn measure(&self) -> Option<(PhysicalPosition<f64>, f64, f64)> {
+        if self.pointers.len() < 2 {
+            return None;
+        }
+        let n = self.pointers.len() as f64;
+        let (sx, sy) = self
+            .pointers
+            .values()
+            .fold((0.0, 0.0), |(x, y), p| (x + p.x, y + p.y));
+        let focus = PhysicalPosition::new(sx / n, sy / n);
+        let (dx, dy) = self.pointers.values().fold((0.0, 0.0), |(x, y), p| {
+            (x + (p.x - focus.x).abs(), y + (p.y - focus.y).abs())
+        });
+        let span = 2.0 * (dx / n).hypot(dy / n);
+        let mut first_two = self.pointers.values();
+        let (a, b) = (first_two.next()?, first_two.next()?);
+        let angle = (b.y - a.y).atan2(b.x - a.x);
+        Some((focus, span, angle))
+    }
+
+    fn update(&mut self, out: &mut Vec<GestureEvent>) {
+        let Some((focus, span, angle)) = self.measure() else {
+            if self.active.take().is_some() {
+                out.push(GestureEvent::PinchEnd);
+            }
+            return;
+        };
+        match self.active {
+            None if span >= self.min_span => {
+                out.push(GestureEvent::PinchStart { focus, span });
+                self.active = Some((span, angle));
+            }
+            None => {}
+            Some((previous_span, previous_angle)) => {
+                use core::f64::consts::{PI, TAU};
+                let rotation = (angle - previous_angle + PI).rem_euclid(TAU) - PI;
+                out.push(GestureEvent::PinchUpdate {
+                    focus,
+                    scale: if previous_span > 0.0 {
+                        span / previous_span
+                    } else {
+                        1.0
+                    },
+                    rotation,
+                });
+                self.active = Some((span, angle));
+            }
+        }
+    }
+}
+
+impl GestureRecognizer for PinchRecognizer {
+    fn pointer_event(&mut self, event: &PointerEvent, out: &mut Vec<GestureEvent>) {
+        if !matches!(event, PointerEvent::Move(_)) && self.batch.take().is_some() {
+            self.update(out);
+        }
+        match event {
+            PointerEvent::Down { pointer, state, .. } => {
+                // Restart so that the new pointer doesn't register as a jump.
+                if self.active.take().is_some() {
+                    out.push(GestureEvent::PinchEnd);
+                }
+                self.pointers.insert(*pointer, state.position);
+            }
+            PointerEvent::Move(update) => {
+                let Some(position) = self.pointers.get_mut(&update.pointer) else {
+                    return;
+                };
+                *position = update.current.position;
+                let time = update.current.time;
+                if self.batch.as_ref().is_some_and(|(t, _)| *t != time) {
+                    // The previous batch didn't include every pointer.
+                    self.update(out);
+                    self.batch = None;
+                }
+                let (_, moved) = self.batch.get_or_insert_with(|| (time, BTreeSet::new()));
+                moved.insert(update.pointer);
+                if moved.len() < self.pointers.len() {
+                    return;
+                }
+                self.batch = None;
+            }
+            PointerEvent::Up { pointer, .. } | PointerEvent::Cancel(pointer) => {
+                self.pointers.remove(pointer);
+                if self.active.take().is_some() {
+                    out.push(GestureEvent::PinchEnd);
+                }
+            }
+            _ => return,
+        }
+        self.update(out);
+    }
+
+    fn tick(&mut self, time: u64, out: &mut Vec<GestureEvent>) {
+        // Report a batch that didn't move every pointer once it is over.
+        if self.batch.take_if(|(t, _)| *t < time).is_some() {
+            self.update(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ui_events::pointer::{PointerId, PointerType, PointerUpdate};
+
+    const MS: u64 = 1_000_000;
+
+    fn pointer(id: u64) -> PointerInfo {
+        PointerInfo {
+            pointer_id: PointerId::new(id),
+            persistent_device_id: None,
+            pointer_type: PointerType::Touch,
+        }
+    }
+
+    fn state(time: u64, x: f64, y: f64) -> PointerState {
+        PointerState {
+            time,
+            position: PhysicalPosition::new(x, y),
+            ..Default::default()
+        }
+    }
+
+    fn down(id: u64, time: u64, x: f64, y: f64) -> PointerEvent {
+        PointerEvent::Down {
+            pointer: pointer(id),
+            button: None,
+            state: state(time, x, y),
+        }
+    }
+
+    fn move_to(id: u64, time: u64, x: f64, y: f64) -> PointerEvent {
+        PointerEvent::Move(PointerUpdate {
+            pointer: pointer(id),
+            current: state(time, x, y),
+            coalesced: vec![],
+            predicted: vec![],
+        })
+    }
+
+    fn up(id: u64, time: u64, x: f64, y: f64) -> PointerEvent {
+        PointerEvent::Up {
+            pointer: pointer(id),
+            button: None,
+            state: state(time, x, y),
+        }
+    }
+
+    fn run(recognizer: &mut impl GestureRecognizer, events: &[PointerEvent]) -> Vec<GestureEvent> {
+        let mut out = vec![];
+        for event in events {
+            recognizer.pointer_event(event, &mut out);
+        }
+        out
+    }
+
+    fn vc() -> ViewConfiguration {
+        ViewConfiguration {
+            long_press_timeout: 400,
+            scaled_touch_slop: 8,
+            scaled_paging_touch_slop: 16,
+            scaled_minimum_scaling_span: 20,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn long_press_within_slop() {
+        let mut recognizer = LongPressRecognizer::new(&vc());
+        let out = run(
+            &mut recognizer,
+            &[down(1, 0, 0.0, 0.0), move_to(1, 100 * MS, 5.0, 5.0)],
+        );
+        assert!(out.is_empty());
+        assert_eq!(recognizer.next_deadline(), Some(400 * MS));
+        let mut out = vec![];
+        recognizer.tick(400 * MS, &mut out);
+        assert_eq!(
+            out,
+            [GestureEvent::LongPress {
+                pointer: pointer(1),
+                position: PhysicalPosition::new(0.0, 0.0),
+            }]
+        );
+        assert_eq!(recognizer.next_deadline(), None);
+    }
+
+    #[test]
+    fn long_press_beyond_slop() {
+        let mut recognizer = LongPressRecognizer::new(&vc());
+        run(
+            &mut recognizer,
+            &[down(1, 0, 0.0, 0.0), move_to(1, 100 * MS, 9.0, 0.0)],
+        );
+        assert_eq!(recognizer.next_deadline(), None);
+    }
+
+    #[test]
+    fn long_press_after_multi_pointer_cancel() {
+        let mut recognizer = LongPressRecognizer::new(&vc());
+        run(
+            &mut recognizer,
+            &[
+                down(1, 0, 0.0, 0.0),
+                down(2, 10 * MS, 50.0, 0.0),
+                // Reported for the action pointer only.
+                PointerEvent::Cancel(pointer(1)),
+                down(1, 500 * MS, 0.0, 0.0),
+            ],
+        );
+        assert_eq!(recognizer.next_deadline(), Some(900 * MS));
+    }
+
+    #[test]
+    fn drag_slop() {
+        let events = [
+            down(1, 0, 0.0, 0.0),
+            move_to(1, 10 * MS, 8.0, 0.0),
+            move_to(1, 20 * MS, 12.0, 0.0),
+            move_to(1, 30 * MS, 20.0, 0.0),
+        ];
+        let out = run(&mut DragRecognizer::new(&vc()), &events);
+        assert_eq!(
+            out,
+            [
+                GestureEvent::DragStart {
+                    pointer: pointer(1),
+                    start: PhysicalPosition::new(0.0, 0.0),
+                    position: PhysicalPosition::new(12.0, 0.0),
+                },
+                GestureEvent::DragMove {
+                    pointer: pointer(1),
+                    position: PhysicalPosition::new(20.0, 0.0),
+                    delta: PhysicalPosition::new(8.0, 0.0),
+                },
+            ]
+        );
+
+        // The paging slop is larger.
+        let out = run(&mut DragRecognizer::paging(&vc()), &events);
+        assert_eq!(
+            out,
+            [GestureEvent::DragStart {
+                pointer: pointer(1),
+                start: PhysicalPosition::new(0.0, 0.0),
+                position: PhysicalPosition::new(20.0, 0.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn drag_end_and_cancel() {
+        let mut recognizer = DragRecognizer::new(&vc());
+        let out = run(
+            &mut recognizer,
+            &[
+                down(1, 0, 0.0, 0.0),
+                move_to(1, 8 * MS, 20.0, 0.0),
+                move_to(1, 16 * MS, 40.0, 0.0),
+                up(1, 16 * MS, 40.0, 0.0),
+            ],
+        );
+        let Some(GestureEvent::DragEnd { velocity, .. }) = out.last() else {
+            panic!("{out:?}");
+        };
+        assert!((velocity.x - 2500.0).abs() < 1e-6, "{velocity:?}");
+
+        let out = run(
+            &mut recognizer,
+            &[
+                down(2, 100 * MS, 0.0, 0.0),
+                move_to(2, 108 * MS, 0.0, 20.0),
+                PointerEvent::Cancel(pointer(2)),
+            ],
+        );
+        assert_eq!(
+            out.last(),
+            Some(&GestureEvent::DragCancel {
+                pointer: pointer(2)
+            })
+        );
+    }
+
+    #[test]
+    fn pinch_scale_and_rotation() {
+        let mut recognizer = PinchRecognizer::new(&vc());
+        let out = run(
+            &mut recognizer,
+            &[
+                down(1, 0, 0.0, 0.0),
+                down(2, 10 * MS, 100.0, 0.0),
+                // Both pointers move in one event: spread to twice the
+                // distance and turn a quarter turn clockwise around the
+                // focus.
+                move_to(1, 20 * MS, 50.0, -100.0),
+                move_to(2, 20 * MS, 50.0, 100.0),
+            ],
+        );
+        let [
+            GestureEvent::PinchStart { focus, span },
+            GestureEvent::PinchUpdate {
+                focus: update_focus,
+                scale,
+                rotation,
+            },
+        ] = &out[..]
+        else {
+            panic!("{out:?}");
+        };
+        assert_eq!(*focus, PhysicalPosition::new(50.0, 0.0));
+        assert_eq!(*span, 100.0);
+        assert_eq!(*update_focus, PhysicalPosition::new(50.0, 0.0));
+        assert!((scale - 2.0).abs() < 1e-9, "{scale}");
+        assert!(
+            (rotation - core::f64::consts::FRAC_PI_2).abs() < 1e-9,
+            "{rotation}"
+        );
+    }
+
+    #[test]
+    fn pinch_partial_batch() {
+        let mut recognizer = PinchRecognizer::new(&vc());
+        let mut out = run(
+            &mut recognizer,
+            &[
+                down(1, 0, 0.0, 0.0),
+                down(2, 10 * MS, 100.0, 0.0),
+                move_to(1, 20 * MS, -50.0, 0.0),
+            ],
+        );
+        assert_eq!(out.len(), 1);
+        recognizer.tick(30 * MS, &mut out);
+        let Some(GestureEvent::PinchUpdate { scale, .. }) = out.last() else {
+            panic!("{out:?}");
+        };
+        assert!((scale - 1.5).abs() < 1e-9, "{scale}");
+    }
+
+    #[test]
+    fn pinch_min_span_and_cancel() {
+        let mut recognizer = PinchRecognizer::new(&vc());
+        let out = run(
+            &mut recognizer,
+            &[down(1, 0, 0.0, 0.0), down(2, 10 * MS, 10.0, 0.0)],
+        );
+        assert!(out.is_empty());
+        let out = run(
+            &mut recognizer,
+            &[
+                move_to(1, 20 * MS, -10.0, 0.0),
+                move_to(2, 20 * MS, 20.0, 0.0),
+                PointerEvent::Cancel(pointer(1)),
+                PointerEvent::Cancel(pointer(2)),
+            ],
+        );
+        assert!(matches!(
+            out[..],
+            [GestureEvent::PinchStart { .. }, GestureEvent::PinchEnd]
+        ));
+    }
+}
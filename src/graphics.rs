@@ -1,22 +1,40 @@
 use jni::{JNIEnv, objects::JObject, sys::jint};
 
+use crate::error::*;
+
 #[repr(transparent)]
 pub struct Rect<'local>(pub JObject<'local>);
 
 impl<'local> Rect<'local> {
     pub fn left(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.get_field(&self.0, "left", "I").unwrap().i().unwrap()
+        self.try_left(env).unwrap()
+    }
+
+    pub fn try_left(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| env.get_field(&self.0, "left", "I")?.i())
     }
 
     pub fn top(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.get_field(&self.0, "top", "I").unwrap().i().unwrap()
+        self.try_top(env).unwrap()
+    }
+
+    pub fn try_top(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| env.get_field(&self.0, "top", "I")?.i())
     }
 
     pub fn right(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.get_field(&self.0, "right", "I").unwrap().i().unwrap()
+        self.try_right(env).unwrap()
+    }
+
+    pub fn try_right(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| env.get_field(&self.0, "right", "I")?.i())
     }
 
     pub fn bottom(&self, env: &mut JNIEnv<'local>) -> jint {
-        env.get_field(&self.0, "bottom", "I").unwrap().i().unwrap()
+        self.try_bottom(env).unwrap()
+    }
+
+    pub fn try_bottom(&self, env: &mut JNIEnv<'local>) -> Result<jint, Error> {
+        catch(env, |env| env.get_field(&self.0, "bottom", "I")?.i())
     }
 }
@@ -0,0 +1,74 @@
+//! Containment of Rust panics at the JNI boundary.
+
+use jni::JNIEnv;
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::RefCell,
+    panic::{self, AssertUnwindSafe},
+    sync::Once,
+};
+
+thread_local! {
+    /// Backtrace of the most recent panic on this thread, captured by
+    /// the hook installed in [`install_panic_hook`].
+    static LAST_PANIC_BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+}
+
+/// Install a panic hook that records a backtrace before deferring
+/// to the previously installed hook.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            LAST_PANIC_BACKTRACE.with(|backtrace| {
+                *backtrace.borrow_mut() = Some(Backtrace::force_capture());
+            });
+            previous(info);
+        }));
+    });
+}
+
+/// Run `f`, converting a panic into a pending Java `RuntimeException`.
+///
+/// Returns `None` if `f` panicked, in which case the exception will be
+/// thrown as soon as control returns to Java.
+pub(crate) fn catch_panic<'local, T>(
+    env: JNIEnv<'local>,
+    f: impl FnOnce(JNIEnv<'local>) -> T,
+) -> Option<T> {
+    install_panic_hook();
+    // SAFETY: The clone is only used after `f` has returned or unwound,
+    // so the two environments are never used at the same time.
+    let mut throw_env = unsafe { env.unsafe_clone() };
+    match panic::catch_unwind(AssertUnwindSafe(|| f(env))) {
+        Ok(result) => Some(result),
+        Err(payload) => {
+            throw_panic(&mut throw_env, payload);
+            None
+        }
+    }
+}
+
+fn throw_panic(env: &mut JNIEnv, payload: Box<dyn Any + Send>) {
+    let message = if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "Box<dyn Any>"
+    };
+    let backtrace = LAST_PANIC_BACKTRACE
+        .with(|backtrace| backtrace.borrow_mut().take())
+        .map_or_else(
+            || "backtrace unavailable".to_string(),
+            |backtrace| backtrace.to_string(),
+        );
+    // Throwing with an exception already pending is not allowed.
+    let _ = env.exception_clear();
+    let _ = env.throw_new(
+        "java/lang/RuntimeException",
+        format!("Rust panic in view peer: {message}\n{backtrace}"),
+    );
+}
@@ -5,7 +5,7 @@ use jni::{
 };
 use std::borrow::Cow;
 
-use crate::{binder::*, callback_ctx::*, events::KeyEvent, util::*, view::*};
+use crate::{binder::*, callback_ctx::*, error::*, events::KeyEvent, util::*, view::*};
 
 pub const INPUT_TYPE_MASK_CLASS: u32 = 0x0000000f;
 pub const INPUT_TYPE_MASK_VARIATION: u32 = 0x00000ff0;
@@ -70,15 +70,24 @@ impl<'local> InputMethodManager<'local> {
         view: &View<'local>,
         flags: jint,
     ) -> bool {
-        env.call_method(
-            &self.0,
-            "showSoftInput",
-            "(Landroid/view/View;I)Z",
-            &[(&view.0).into(), flags.into()],
-        )
-        .unwrap()
-        .z()
-        .unwrap()
+        self.try_show_soft_input(env, view, flags).unwrap()
+    }
+
+    pub fn try_show_soft_input(
+        &self,
+        env: &mut JNIEnv<'local>,
+        view: &View<'local>,
+        flags: jint,
+    ) -> Result<bool, Error> {
+        catch(env, |env| {
+            env.call_method(
+                &self.0,
+                "showSoftInput",
+                "(Landroid/view/View;I)Z",
+                &[(&view.0).into(), flags.into()],
+            )?
+            .z()
+        })
     }
 
     pub fn hide_soft_input_from_window(
@@ -87,27 +96,45 @@ impl<'local> InputMethodManager<'local> {
         window_token: &IBinder<'local>,
         flags: jint,
     ) -> bool {
-        env.call_method(
-            &self.0,
-            "hideSoftInputFromWindow",
-            "(Landroid/os/IBinder;I)Z",
-            &[(&window_token.0).into(), flags.into()],
-        )
-        .unwrap()
-        .z()
-        .unwrap()
+        self.try_hide_soft_input_from_window(env, window_token, flags)
+            .unwrap()
+    }
+
+    pub fn try_hide_soft_input_from_window(
+        &self,
+        env: &mut JNIEnv<'local>,
+        window_token: &IBinder<'local>,
+        flags: jint,
+    ) -> Result<bool, Error> {
+        catch(env, |env| {
+            env.call_method(
+                &self.0,
+                "hideSoftInputFromWindow",
+                "(Landroid/os/IBinder;I)Z",
+                &[(&window_token.0).into(), flags.into()],
+            )?
+            .z()
+        })
     }
 
     pub fn restart_input(&self, env: &mut JNIEnv<'local>, view: &View<'local>) {
-        env.call_method(
-            &self.0,
-            "restartInput",
-            "(Landroid/view/View;)V",
-            &[(&view.0).into()],
-        )
-        .unwrap()
-        .v()
-        .unwrap();
+        self.try_restart_input(env, view).unwrap()
+    }
+
+    pub fn try_restart_input(
+        &self,
+        env: &mut JNIEnv<'local>,
+        view: &View<'local>,
+    ) -> Result<(), Error> {
+        catch(env, |env| {
+            env.call_method(
+                &self.0,
+                "restartInput",
+                "(Landroid/view/View;)V",
+                &[(&view.0).into()],
+            )?
+            .v()
+        })
     }
 
     pub fn update_selection(
@@ -119,21 +146,41 @@ impl<'local> InputMethodManager<'local> {
         candidates_start: jint,
         candidates_end: jint,
     ) {
-        env.call_method(
-            &self.0,
-            "updateSelection",
-            "(Landroid/view/View;IIII)V",
-            &[
-                (&view.0).into(),
-                sel_start.into(),
-                sel_end.into(),
-                candidates_start.into(),
-                candidates_end.into(),
-            ],
+        self.try_update_selection(
+            env,
+            view,
+            sel_start,
+            sel_end,
+            candidates_start,
+            candidates_end,
         )
         .unwrap()
-        .v()
-        .unwrap();
+    }
+
+    pub fn try_update_selection(
+        &self,
+        env: &mut JNIEnv<'local>,
+        view: &View<'local>,
+        sel_start: jint,
+        sel_end: jint,
+        candidates_start: jint,
+        candidates_end: jint,
+    ) -> Result<(), Error> {
+        catch(env, |env| {
+            env.call_method(
+                &self.0,
+                "updateSelection",
+                "(Landroid/view/View;IIII)V",
+                &[
+                    (&view.0).into(),
+                    sel_start.into(),
+                    sel_end.into(),
+                    candidates_start.into(),
+                    candidates_end.into(),
+                ],
+            )?
+            .v()
+        })
     }
 }
 
@@ -142,28 +189,65 @@ pub struct EditorInfo<'local>(pub JObject<'local>);
 
 impl<'local> EditorInfo<'local> {
     pub fn set_input_type(&self, env: &mut JNIEnv<'local>, value: u32) {
-        env.set_field(&self.0, "inputType", "I", (value as jint).into())
-            .unwrap();
+        self.try_set_input_type(env, value).unwrap()
+    }
+
+    pub fn try_set_input_type(&self, env: &mut JNIEnv<'local>, value: u32) -> Result<(), Error> {
+        catch(env, |env| {
+            env.set_field(&self.0, "inputType", "I", (value as jint).into())
+        })
     }
 
     pub fn set_ime_options(&self, env: &mut JNIEnv<'local>, value: u32) {
-        env.set_field(&self.0, "imeOptions", "I", (value as jint).into())
-            .unwrap();
+        self.try_set_ime_options(env, value).unwrap()
+    }
+
+    pub fn try_set_ime_options(&self, env: &mut JNIEnv<'local>, value: u32) -> Result<(), Error> {
+        catch(env, |env| {
+            env.set_field(&self.0, "imeOptions", "I", (value as jint).into())
+        })
     }
 
     pub fn set_initial_sel_start(&self, env: &mut JNIEnv<'local>, value: jint) {
-        env.set_field(&self.0, "initialSelStart", "I", value.into())
-            .unwrap();
+        self.try_set_initial_sel_start(env, value).unwrap()
+    }
+
+    pub fn try_set_initial_sel_start(
+        &self,
+        env: &mut JNIEnv<'local>,
+        value: jint,
+    ) -> Result<(), Error> {
+        catch(env, |env| {
+            env.set_field(&self.0, "initialSelStart", "I", value.into())
+        })
     }
 
     pub fn set_initial_sel_end(&self, env: &mut JNIEnv<'local>, value: jint) {
-        env.set_field(&self.0, "initialSelEnd", "I", value.into())
-            .unwrap();
+        self.try_set_initial_sel_end(env, value).unwrap()
+    }
+
+    pub fn try_set_initial_sel_end(
+        &self,
+        env: &mut JNIEnv<'local>,
+        value: jint,
+    ) -> Result<(), Error> {
+        catch(env, |env| {
+            env.set_field(&self.0, "initialSelEnd", "I", value.into())
+        })
     }
 
     pub fn set_initial_caps_mode(&self, env: &mut JNIEnv<'local>, value: u32) {
-        env.set_field(&self.0, "initialCapsMode", "I", (value as jint).into())
-            .unwrap();
+        self.try_set_initial_caps_mode(env, value).unwrap()
+    }
+
+    pub fn try_set_initial_caps_mode(
+        &self,
+        env: &mut JNIEnv<'local>,
+        value: u32,
+    ) -> Result<(), Error> {
+        catch(env, |env| {
+            env.set_field(&self.0, "initialCapsMode", "I", (value as jint).into())
+        })
     }
 }
 
@@ -273,7 +357,7 @@ pub trait InputConnection {
     // just returns false.
 }
 
-fn with_input_connection<'local, F, T: Default>(
+fn with_input_connection<'local, F, T: Default + CallbackResult<'local>>(
     env: JNIEnv<'local>,
     view: View<'local>,
     id: jlong,
@@ -540,18 +624,28 @@ pub(crate) extern "system" fn close_input_connection<'local>(
 }
 
 pub fn caps_mode(env: &mut JNIEnv, text: &str, off: usize, req_modes: u32) -> u32 {
-    let text = env.new_string(text).unwrap();
-    env.call_static_method(
-        "android/text/TextUtils",
-        "getCapsMode",
-        "(Ljava/lang/CharSequence;II)I",
-        &[
-            (&text).into(),
-            (off as jint).into(),
-            (req_modes as jint).into(),
-        ],
-    )
-    .unwrap()
-    .i()
-    .unwrap() as u32
+    try_caps_mode(env, text, off, req_modes).unwrap()
+}
+
+pub fn try_caps_mode(
+    env: &mut JNIEnv,
+    text: &str,
+    off: usize,
+    req_modes: u32,
+) -> Result<u32, Error> {
+    catch(env, |env| {
+        let text = env.new_string(text)?;
+        Ok(env
+            .call_static_method(
+                "android/text/TextUtils",
+                "getCapsMode",
+                "(Ljava/lang/CharSequence;II)I",
+                &[
+                    (&text).into(),
+                    (off as jint).into(),
+                    (req_modes as jint).into(),
+                ],
+            )?
+            .i()? as u32)
+    })
 }
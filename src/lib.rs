@@ -11,18 +11,47 @@ mod bundle;
 pub use bundle::*;
 mod callback_ctx;
 pub use callback_ctx::*;
+mod choreographer;
+pub use choreographer::*;
+mod configuration;
+pub use configuration::*;
 mod context;
 pub use context::*;
+mod display;
+pub use display::*;
+mod error;
+pub use error::*;
 mod events;
 pub use events::*;
+#[cfg(feature = "async")]
+mod executor;
+#[cfg(feature = "async")]
+pub use executor::*;
+mod frame;
+pub use frame::*;
+mod gestures;
+pub use gestures::*;
 mod graphics;
 pub use graphics::*;
+mod guard;
 mod ime;
 pub use ime::*;
+#[cfg(feature = "debug-local-refs")]
+mod local_refs;
+mod predictor;
+pub use predictor::*;
+mod scroller;
+pub use scroller::*;
+mod snapshot;
+pub use snapshot::*;
 mod surface;
 pub use surface::*;
 mod util;
+mod velocity_tracker;
+pub use velocity_tracker::*;
 mod view;
 pub use view::*;
 mod view_configuration;
 pub use view_configuration::*;
+mod window_insets;
+pub use window_insets::*;
@@ -0,0 +1,210 @@
+//! Counting of the local references held by callbacks, to find those that
+//! should delete them or use [`CallbackCtx::with_local_frame`].
+//!
+//! While a callback runs, the JNI function table of its thread is replaced
+//! with one that counts the references returned by the functions that
+//! create them, and those deleted with `DeleteLocalRef`. Only non-variadic
+//! functions are counted, which are the ones that `jni` uses.
+
+use jni::{
+    JNIEnv,
+    objects::JValue,
+    sys::{
+        self, JNI_OK, JNINativeInterface_, jboolean, jchar, jclass, jfieldID, jint, jlong,
+        jmethodID, jobject, jobjectArray, jsize, jvalue,
+    },
+};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{c_char, c_void},
+    ptr,
+    sync::OnceLock,
+};
+
+#[cfg(doc)]
+use crate::CallbackCtx;
+use crate::error::catch;
+
+struct FunctionTable(JNINativeInterface_);
+
+// SAFETY: The table only holds function pointers, and is never modified
+// once built.
+unsafe impl Send for FunctionTable {}
+unsafe impl Sync for FunctionTable {}
+
+static COUNTING_TABLE: OnceLock<FunctionTable> = OnceLock::new();
+
+thread_local! {
+    /// The table replaced by the counting one on this thread.
+    static ORIGINAL_TABLE: Cell<*const JNINativeInterface_> = const { Cell::new(ptr::null()) };
+    /// The number of live references in each local frame pushed while the
+    /// counting table is installed, innermost last.
+    static FRAMES: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+fn original() -> &'static JNINativeInterface_ {
+    // SAFETY: Only called through the counting table, which is installed
+    // after the original is saved. The VM's tables live as long as it does.
+    unsafe { &*ORIGINAL_TABLE.get() }
+}
+
+fn created(obj: jobject) {
+    if !obj.is_null() {
+        FRAMES.with_borrow_mut(|frames| {
+            if let Some(live) = frames.last_mut() {
+                *live += 1;
+            }
+        });
+    }
+}
+
+macro_rules! counting_functions {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            #[allow(non_snake_case)]
+            unsafe extern "system" fn $name(env: *mut sys::JNIEnv, $($arg: $ty),*) -> jobject {
+                // SAFETY: The arguments are passed through unchanged.
+                let obj = unsafe { original().$name.unwrap()(env, $($arg),*) };
+                created(obj);
+                obj
+            }
+        )*
+
+        fn counting_table(original: &JNINativeInterface_) -> JNINativeInterface_ {
+            JNINativeInterface_ {
+                $($name: Some($name),)*
+                PushLocalFrame: Some(PushLocalFrame),
+                PopLocalFrame: Some(PopLocalFrame),
+                DeleteLocalRef: Some(DeleteLocalRef),
+                ..*original
+            }
+        }
+    };
+}
+
+counting_functions! {
+    DefineClass(name: *const c_char, loader: jobject, buf: *const sys::jbyte, len: jsize);
+    FindClass(name: *const c_char);
+    ToReflectedMethod(cls: jclass, method_id: jmethodID, is_static: jboolean);
+    GetSuperclass(sub: jclass);
+    ToReflectedField(cls: jclass, field_id: jfieldID, is_static: jboolean);
+    ExceptionOccurred();
+    NewLocalRef(obj: jobject);
+    AllocObject(clazz: jclass);
+    NewObjectA(clazz: jclass, method_id: jmethodID, args: *const jvalue);
+    GetObjectClass(obj: jobject);
+    CallObjectMethodA(obj: jobject, method_id: jmethodID, args: *const jvalue);
+    CallNonvirtualObjectMethodA(
+        obj: jobject,
+        clazz: jclass,
+        method_id: jmethodID,
+        args: *const jvalue
+    );
+    GetObjectField(obj: jobject, field_id: jfieldID);
+    CallStaticObjectMethodA(clazz: jclass, method_id: jmethodID, args: *const jvalue);
+    GetStaticObjectField(clazz: jclass, field_id: jfieldID);
+    NewString(unicode: *const jchar, len: jsize);
+    NewStringUTF(utf: *const c_char);
+    NewObjectArray(len: jsize, clazz: jclass, init: jobject);
+    GetObjectArrayElement(array: jobjectArray, index: jsize);
+    NewBooleanArray(len: jsize);
+    NewByteArray(len: jsize);
+    NewCharArray(len: jsize);
+    NewShortArray(len: jsize);
+    NewIntArray(len: jsize);
+    NewLongArray(len: jsize);
+    NewFloatArray(len: jsize);
+    NewDoubleArray(len: jsize);
+    NewDirectByteBuffer(address: *mut c_void, capacity: jlong);
+}
+
+#[allow(non_snake_case)]
+unsafe extern "system" fn PushLocalFrame(env: *mut sys::JNIEnv, capacity: jint) -> jint {
+    // SAFETY: The arguments are passed through unchanged.
+    let result = unsafe { original().PushLocalFrame.unwrap()(env, capacity) };
+    if result == JNI_OK {
+        FRAMES.with_borrow_mut(|frames| frames.push(0));
+    }
+    result
+}
+
+#[allow(non_snake_case)]
+unsafe extern "system" fn PopLocalFrame(env: *mut sys::JNIEnv, result: jobject) -> jobject {
+    // SAFETY: The arguments are passed through unchanged.
+    let result = unsafe { original().PopLocalFrame.unwrap()(env, result) };
+    FRAMES.with_borrow_mut(|frames| frames.pop());
+    created(result);
+    result
+}
+
+#[allow(non_snake_case)]
+unsafe extern "system" fn DeleteLocalRef(env: *mut sys::JNIEnv, obj: jobject) {
+    // SAFETY: The arguments are passed through unchanged.
+    unsafe { original().DeleteLocalRef.unwrap()(env, obj) };
+    if !obj.is_null() {
+        FRAMES.with_borrow_mut(|frames| {
+            if let Some(live) = frames.last_mut() {
+                *live = live.saturating_sub(1);
+            }
+        });
+    }
+}
+
+/// Start counting the local references created through `env`.
+pub(crate) fn install(env: &JNIEnv) {
+    let raw = env.get_raw();
+    // SAFETY: `raw` points to the function table pointer of a valid
+    // environment, which is only used on this thread.
+    unsafe {
+        let table = &COUNTING_TABLE
+            .get_or_init(|| FunctionTable(counting_table(&**raw)))
+            .0;
+        if !ptr::eq(*raw, table) {
+            ORIGINAL_TABLE.set(*raw);
+            *raw = table;
+        }
+    }
+}
+
+/// Stop counting once every counted frame has been popped.
+pub(crate) fn uninstall_if_idle(env: &JNIEnv) {
+    if !FRAMES.with_borrow(Vec::is_empty) {
+        return;
+    }
+    let raw = env.get_raw();
+    // SAFETY: As in `install`.
+    unsafe {
+        if COUNTING_TABLE
+            .get()
+            .is_some_and(|table| ptr::eq(*raw, &table.0))
+        {
+            *raw = ORIGINAL_TABLE.get();
+        }
+    }
+}
+
+/// Log a warning if the innermost frame holds more than `capacity`
+/// references. `what` is the callback that created them.
+pub(crate) fn check(env: &mut JNIEnv, capacity: i32, what: &str) {
+    let Some(live) = FRAMES.with_borrow(|frames| frames.last().copied()) else {
+        return;
+    };
+    if live <= capacity as usize {
+        return;
+    }
+    let message = format!(
+        "{what} returned holding {live} local references, more than the {capacity} \
+         reserved for it; delete them when done, or use `CallbackCtx::with_local_frame`"
+    );
+    let _ = catch(env, |env| {
+        let tag = env.new_string("android-view")?;
+        let message = env.new_string(message)?;
+        env.call_static_method(
+            "android/util/Log",
+            "w",
+            "(Ljava/lang/String;Ljava/lang/String;)I",
+            &[JValue::Object(&tag), JValue::Object(&message)],
+        )?;
+        Ok(())
+    });
+}
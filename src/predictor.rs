@@ -0,0 +1,410 @@
+//! Prediction of pointer positions a short time ahead of the latest sample.
+//!
+//! [`MotionPredictor`] uses the platform `android.view.MotionPredictor` on
+//! API level 34 and later when it supports the input device, and otherwise
+//! falls back to [`KalmanPredictor`], which is plain Rust and can be driven
+//! directly from recorded [`PointerState`] streams.
+
+use dpi::PhysicalPosition;
+use jni::{JNIEnv, objects::GlobalRef};
+use std::collections::BTreeMap;
+use ui_events::pointer::{PointerEvent, PointerInfo, PointerState};
+
+use crate::{Context, MotionEvent, MotionEventSnapshot, ViewConfiguration, error::*, util::*};
+
+/// Samples further apart than this start a new stroke.
+const MAX_SAMPLE_GAP_NANOS: u64 = 100_000_000;
+
+/// Number of samples needed before the velocity estimate is trusted.
+const MIN_SAMPLES: usize = 3;
+
+/// Tuning for pointer prediction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PredictorConfig {
+    /// How far past the latest sample to predict, in nanoseconds.
+    pub horizon_nanos: u64,
+    /// Spacing of predicted samples from [`KalmanPredictor`], in nanoseconds.
+    pub sample_interval_nanos: u64,
+    /// Expected variation in velocity, in px²/s³. Higher values follow
+    /// changes in direction more quickly at the cost of more jitter.
+    pub process_noise: f64,
+    /// Expected variance of reported positions, in px².
+    pub measurement_noise: f64,
+}
+
+impl Default for PredictorConfig {
+    fn default() -> Self {
+        Self {
+            horizon_nanos: 20_000_000,
+            sample_interval_nanos: 5_000_000,
+            process_noise: 1.0e6,
+            measurement_noise: 1.0,
+        }
+    }
+}
+
+/// Constant-velocity Kalman filter for one axis.
+#[derive(Clone, Copy, Debug, Default)]
+struct AxisFilter {
+    position: f64,
+    velocity: f64,
+    covariance: [[f64; 2]; 2],
+}
+
+impl AxisFilter {
+    fn reset(&mut self, position: f64, config: &PredictorConfig) {
+        *self = Self {
+            position,
+            velocity: 0.0,
+            covariance: [[config.measurement_noise, 0.0], [0.0, config.process_noise]],
+        };
+    }
+
+    fn update(&mut self, position: f64, dt: f64, config: &PredictorConfig) {
+        let q = config.process_noise;
+        let [[p00, p01], [p10, p11]] = self.covariance;
+
+        // Advance the state by `dt`.
+        self.position += self.velocity * dt;
+        let p00 = p00 + dt * (p01 + p10) + dt * dt * p11 + q * dt.powi(3) / 3.0;
+        let p01 = p01 + dt * p11 + q * dt * dt / 2.0;
+        let p10 = p10 + dt * p11 + q * dt * dt / 2.0;
+        let p11 = p11 + q * dt;
+
+        // Correct it with the measured position.
+        let s = p00 + config.measurement_noise;
+        let (k0, k1) = (p00 / s, p10 / s);
+        let residual = position - self.position;
+        self.position += k0 * residual;
+        self.velocity += k1 * residual;
+        self.covariance = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+    }
+}
+
+/// Predicts the path of a single pointer from its recent samples.
+#[derive(Clone, Debug)]
+pub struct KalmanPredictor {
+    config: PredictorConfig,
+    x: AxisFilter,
+    y: AxisFilter,
+    last: Option<PointerState>,
+    sample_count: usize,
+}
+
+impl KalmanPredictor {
+    pub fn new(config: PredictorConfig) -> Self {
+        Self {
+            config,
+            x: AxisFilter::default(),
+            y: AxisFilter::default(),
+            last: None,
+            sample_count: 0,
+        }
+    }
+
+    /// Forget all samples, as at the start of a new stroke.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.sample_count = 0;
+    }
+
+    /// Add a sample. Samples that are not newer than the previous one are ignored.
+    pub fn update(&mut self, sample: &PointerState) {
+        let PhysicalPosition { x, y } = sample.position;
+        match &self.last {
+            Some(last) if sample.time <= last.time => return,
+            Some(last) if sample.time - last.time <= MAX_SAMPLE_GAP_NANOS => {
+                let dt = (sample.time - last.time) as f64 * 1e-9;
+                self.x.update(x, dt, &self.config);
+                self.y.update(y, dt, &self.config);
+                self.sample_count += 1;
+            }
+            _ => {
+                self.x.reset(x, &self.config);
+                self.y.reset(y, &self.config);
+                self.sample_count = 1;
+            }
+        }
+        self.last = Some(sample.clone());
+    }
+
+    /// Predicted samples following the latest one, spaced by
+    /// [`PredictorConfig::sample_interval_nanos`] up to the horizon.
+    ///
+    /// Returns nothing until enough samples have been seen to estimate a velocity.
+    pub fn predict(&self) -> Vec<PointerState> {
+        let Some(last) = &self.last else {
+            return vec![];
+        };
+        let interval = self.config.sample_interval_nanos;
+        if self.sample_count < MIN_SAMPLES || interval == 0 {
+            return vec![];
+        }
+        (1..=self.config.horizon_nanos / interval)
+            .map(|i| {
+                let offset = i * interval;
+                let dt = offset as f64 * 1e-9;
+                PointerState {
+                    time: last.time + offset,
+                    position: PhysicalPosition {
+                        x: last.position.x + self.x.velocity * dt,
+                        y: last.position.y + self.y.velocity * dt,
+                    },
+                    ..last.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Fills [`PointerUpdate::predicted`](ui_events::pointer::PointerUpdate::predicted)
+/// for the pointer events of a view.
+pub struct MotionPredictor {
+    config: PredictorConfig,
+    /// The platform `MotionPredictor`, on API level 34 and later.
+    platform: Option<GlobalRef>,
+    pointers: BTreeMap<PointerInfo, KalmanPredictor>,
+}
+
+impl MotionPredictor {
+    /// Create a predictor that uses the platform `MotionPredictor` where it is
+    /// available and [`KalmanPredictor`] otherwise.
+    ///
+    /// This never fails: if the platform predictor can't be created, the
+    /// exception is cleared and only [`KalmanPredictor`] is used.
+    pub fn new<'local>(
+        env: &mut JNIEnv<'local>,
+        context: &Context<'local>,
+        config: PredictorConfig,
+    ) -> Self {
+        let platform = if sdk_int(env) >= 34 {
+            catch(env, |env| {
+                let predictor = env.new_object(
+                    "android/view/MotionPredictor",
+                    "(Landroid/content/Context;)V",
+                    &[(&context.0).into()],
+                )?;
+                env.new_global_ref(predictor)
+            })
+            .ok()
+        } else {
+            None
+        };
+        Self {
+            platform,
+            ..Self::builtin(config)
+        }
+    }
+
+    /// Create a predictor that only uses [`KalmanPredictor`].
+    pub fn builtin(config: PredictorConfig) -> Self {
+        Self {
+            config,
+            platform: None,
+            pointers: BTreeMap::new(),
+        }
+    }
+
+    /// Record `event` and convert it to pointer events as with
+    /// [`MotionEvent::to_pointer_events`], with predicted samples
+    /// attached to every move.
+    pub fn to_pointer_events<'local>(
+        &mut self,
+        env: &mut JNIEnv<'local>,
+        event: &MotionEvent<'local>,
+        vc: &ViewConfiguration,
+    ) -> Vec<PointerEvent> {
+        self.try_to_pointer_events(env, event, vc).unwrap()
+    }
+
+    pub fn try_to_pointer_events<'local>(
+        &mut self,
+        env: &mut JNIEnv<'local>,
+        event: &MotionEvent<'local>,
+        vc: &ViewConfiguration,
+    ) -> Result<Vec<PointerEvent>, Error> {
+        let snapshot = event.try_snapshot(env)?;
+        let mut events = snapshot.to_pointer_events(vc);
+        match &self.platform {
+            Some(platform) if Self::platform_available(env, platform, &snapshot)? => {
+                Self::predict_platform(
+                    env,
+                    platform,
+                    event,
+                    &snapshot,
+                    self.config.horizon_nanos,
+                    &mut events,
+                )?;
+            }
+            _ => self.predict_builtin(&mut events),
+        }
+        Ok(events)
+    }
+
+    fn platform_available<'local>(
+        env: &mut JNIEnv<'local>,
+        platform: &GlobalRef,
+        snapshot: &MotionEventSnapshot,
+    ) -> Result<bool, Error> {
+        catch(env, |env| {
+            env.call_method(
+                platform,
+                "isPredictionAvailable",
+                "(II)Z",
+                &[snapshot.device_id.into(), snapshot.source.into()],
+            )?
+            .z()
+        })
+    }
+
+    fn predict_platform<'local>(
+        env: &mut JNIEnv<'local>,
+        platform: &GlobalRef,
+        event: &MotionEvent<'local>,
+        snapshot: &MotionEventSnapshot,
+        horizon_nanos: u64,
+        events: &mut [PointerEvent],
+    ) -> Result<(), Error> {
+        let predicted = catch(env, |env| {
+            env.call_method(
+                platform,
+                "record",
+                "(Landroid/view/MotionEvent;)V",
+                &[(&event.0).into()],
+            )?;
+            if !events.iter().any(|e| matches!(e, PointerEvent::Move(_))) {
+                return Ok(None);
+            }
+            let time = snapshot.event_time_nanos() + horizon_nanos as i64;
+            let predicted = env
+                .call_method(
+                    platform,
+                    "predict",
+                    "(J)Landroid/view/MotionEvent;",
+                    &[time.into()],
+                )?
+                .l()?;
+            Ok((!predicted.is_null()).then_some(MotionEvent(predicted)))
+        })?;
+        let Some(predicted) = predicted else {
+            return Ok(());
+        };
+        let predicted = predicted.try_snapshot(env)?;
+        for e in events {
+            if let PointerEvent::Move(update) = e
+                && let Some(i) = predicted.pointer_index_of(&update.pointer)
+            {
+                update.predicted = predicted.pointer_samples(i);
+            }
+        }
+        Ok(())
+    }
+
+    fn predict_builtin(&mut self, events: &mut [PointerEvent]) {
+        for e in events {
+            match e {
+                PointerEvent::Down { pointer, state, .. } => {
+                    let predictor = self
+                        .pointers
+                        .entry(*pointer)
+                        .or_insert_with(|| KalmanPredictor::new(self.config));
+                    predictor.reset();
+                    predictor.update(state);
+                }
+                PointerEvent::Move(update) => {
+                    let predictor = self
+                        .pointers
+                        .entry(update.pointer)
+                        .or_insert_with(|| KalmanPredictor::new(self.config));
+                    for state in update.coalesced.iter().chain([&update.current]) {
+                        predictor.update(state);
+                    }
+                    update.predicted = predictor.predict();
+                }
+                PointerEvent::Up { pointer, .. }
+                | PointerEvent::Cancel(pointer)
+                | PointerEvent::Leave(pointer) => {
+                    self.pointers.remove(pointer);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time_millis: u64, x: f64, y: f64) -> PointerState {
+        PointerState {
+            time: time_millis * 1_000_000,
+            position: PhysicalPosition { x, y },
+            ..Default::default()
+        }
+    }
+
+    /// A stroke moving at 1000 px/s right and 500 px/s down, sampled every
+    /// 8 ms.
+    fn linear_stroke(predictor: &mut KalmanPredictor, samples: u64) {
+        for i in 0..samples {
+            let t = i * 8;
+            predictor.update(&sample(t, t as f64, t as f64 * 0.5));
+        }
+    }
+
+    #[test]
+    fn needs_enough_samples() {
+        let mut predictor = KalmanPredictor::new(PredictorConfig::default());
+        assert!(predictor.predict().is_empty());
+        linear_stroke(&mut predictor, MIN_SAMPLES as u64 - 1);
+        assert!(predictor.predict().is_empty());
+        predictor.update(&sample(100, 100.0, 50.0));
+        assert!(!predictor.predict().is_empty());
+    }
+
+    #[test]
+    fn predicts_linear_stroke() {
+        let mut predictor = KalmanPredictor::new(PredictorConfig::default());
+        linear_stroke(&mut predictor, 20);
+        let predicted = predictor.predict();
+        // 20 ms ahead in 5 ms steps.
+        assert_eq!(predicted.len(), 4);
+        let last_time = 19 * 8;
+        for (i, state) in predicted.iter().enumerate() {
+            let offset = (i as u64 + 1) * 5;
+            assert_eq!(state.time, (last_time + offset) * 1_000_000);
+            let x = (last_time + offset) as f64;
+            assert!((state.position.x - x).abs() < 0.5, "{state:?}");
+            assert!((state.position.y - x * 0.5).abs() < 0.25, "{state:?}");
+        }
+    }
+
+    #[test]
+    fn gap_starts_new_stroke() {
+        let mut predictor = KalmanPredictor::new(PredictorConfig::default());
+        linear_stroke(&mut predictor, 20);
+        predictor.update(&sample(1000, 0.0, 0.0));
+        assert!(predictor.predict().is_empty());
+    }
+
+    #[test]
+    fn ignores_stale_samples() {
+        let mut predictor = KalmanPredictor::new(PredictorConfig::default());
+        linear_stroke(&mut predictor, 20);
+        let before = predictor.predict();
+        predictor.update(&sample(0, 500.0, 500.0));
+        assert_eq!(predictor.predict(), before);
+    }
+
+    #[test]
+    fn reset_forgets_samples() {
+        let mut predictor = KalmanPredictor::new(PredictorConfig::default());
+        linear_stroke(&mut predictor, 20);
+        predictor.reset();
+        assert!(predictor.predict().is_empty());
+    }
+}
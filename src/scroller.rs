@@ -0,0 +1,468 @@
+//! Fling animation, following the physics of Android's `OverScroller`.
+
+use dpi::PhysicalPosition;
+use std::sync::OnceLock;
+
+use crate::{Velocity, ViewConfiguration};
+
+/// `ln(0.78) / ln(0.9)`.
+const DECELERATION_RATE: f64 = 2.358_201_815_172_796;
+const INFLEXION: f64 = 0.35;
+const START_TENSION: f64 = 0.5;
+const END_TENSION: f64 = 1.0;
+const P1: f64 = START_TENSION * INFLEXION;
+const P2: f64 = 1.0 - END_TENSION * (1.0 - INFLEXION);
+const SPLINE_SAMPLES: usize = 100;
+
+/// Deceleration past the edge of the scroll range, in px/s².
+const GRAVITY: f64 = 2000.0;
+
+/// Fraction of the fling distance covered at each hundredth of its duration.
+fn spline_position() -> &'static [f64; SPLINE_SAMPLES + 1] {
+    static SPLINE: OnceLock<[f64; SPLINE_SAMPLES + 1]> = OnceLock::new();
+    SPLINE.get_or_init(|| {
+        let mut spline = [1.0; SPLINE_SAMPLES + 1];
+        let mut x_min = 0.0;
+        for (i, position) in spline.iter_mut().take(SPLINE_SAMPLES).enumerate() {
+            let alpha = i as f64 / SPLINE_SAMPLES as f64;
+            let mut x_max = 1.0;
+            let (x, coef) = loop {
+                let x = x_min + (x_max - x_min) / 2.0;
+                let coef = 3.0 * x * (1.0 - x);
+                let tx = coef * ((1.0 - x) * P1 + x * P2) + x * x * x;
+                if (tx - alpha).abs() < 1e-5 {
+                    break (x, coef);
+                }
+                if tx > alpha {
+                    x_max = x;
+                } else {
+                    x_min = x;
+                }
+            };
+            *position = coef * ((1.0 - x) * START_TENSION + x) + x * x * x;
+        }
+        spline
+    })
+}
+
+/// A one-dimensional fling that decelerates along Android's fling spline.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fling {
+    start: f64,
+    distance: f64,
+    duration_nanos: u64,
+}
+
+impl Fling {
+    /// Start a fling at `start` with `velocity` in pixels per second.
+    ///
+    /// `friction` is [`ViewConfiguration::scroll_friction`] and `density`
+    /// is the display density, as in `DisplayMetrics.density`.
+    pub fn new(start: f64, velocity: f64, friction: f32, density: f32) -> Self {
+        if velocity == 0.0 {
+            return Self {
+                start,
+                ..Default::default()
+            };
+        }
+        let ppi = density as f64 * 160.0;
+        // Gravity in pixels per second², scaled as Android does.
+        let physical_coeff = 9.80665 * 39.37 * ppi * 0.84;
+        let friction = friction as f64 * physical_coeff;
+        let l = (INFLEXION * velocity.abs() / friction).ln();
+        Self {
+            start,
+            distance: velocity.signum()
+                * friction
+                * (DECELERATION_RATE / (DECELERATION_RATE - 1.0) * l).exp(),
+            duration_nanos: (1e9 * (l / (DECELERATION_RATE - 1.0)).exp()) as u64,
+        }
+    }
+
+    pub fn duration_nanos(&self) -> u64 {
+        self.duration_nanos
+    }
+
+    /// Where the fling comes to rest.
+    pub fn final_position(&self) -> f64 {
+        self.start + self.distance
+    }
+
+    /// Position and velocity `elapsed_nanos` after the start of the fling.
+    pub fn sample(&self, elapsed_nanos: u64) -> (f64, f64) {
+        if elapsed_nanos >= self.duration_nanos {
+            return (self.final_position(), 0.0);
+        }
+        let spline = spline_position();
+        let t = elapsed_nanos as f64 / self.duration_nanos as f64;
+        let index = (SPLINE_SAMPLES as f64 * t) as usize;
+        let t_inf = index as f64 / SPLINE_SAMPLES as f64;
+        let t_sup = (index + 1) as f64 / SPLINE_SAMPLES as f64;
+        let velocity_coef = (spline[index + 1] - spline[index]) / (t_sup - t_inf);
+        let distance_coef = spline[index] + (t - t_inf) * velocity_coef;
+        (
+            self.start + distance_coef * self.distance,
+            velocity_coef * self.distance / (self.duration_nanos as f64 * 1e-9),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Phase {
+    #[default]
+    Finished,
+    Spline(Fling),
+    /// Decelerating past an edge.
+    Ballistic {
+        start: f64,
+        velocity: f64,
+        deceleration: f64,
+        duration_nanos: u64,
+    },
+    /// Springing back to an edge.
+    Cubic {
+        start: f64,
+        end: f64,
+        duration_nanos: u64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct AxisScroller {
+    phase: Phase,
+    start_time: u64,
+    position: f64,
+    velocity: f64,
+    min: f64,
+    max: f64,
+}
+
+impl AxisScroller {
+    fn fling(&mut self, time: u64, start: f64, velocity: f64, friction: f32, density: f32) {
+        self.position = start;
+        self.velocity = velocity;
+        if start < self.min || start > self.max {
+            self.spring_back(time);
+        } else {
+            self.start_time = time;
+            self.phase = Phase::Spline(Fling::new(start, velocity, friction, density));
+        }
+    }
+
+    /// Start returning to the nearest edge if outside the range.
+    fn spring_back(&mut self, time: u64) -> bool {
+        let end = self.position.clamp(self.min, self.max);
+        if end == self.position {
+            return false;
+        }
+        self.start_time = time;
+        self.velocity = 0.0;
+        self.phase = Phase::Cubic {
+            start: self.position,
+            end,
+            duration_nanos: (1e9 * (2.0 * (self.position - end).abs() / GRAVITY).sqrt()) as u64,
+        };
+        true
+    }
+
+    /// Start decelerating past the edge at `edge`, with at most `over`
+    /// pixels of travel before springing back.
+    fn edge_reached(&mut self, time: u64, edge: f64, velocity: f64, over: f64) {
+        let mut deceleration = -velocity.signum() * GRAVITY;
+        if velocity * velocity / (2.0 * GRAVITY) > over {
+            deceleration = -velocity.signum() * velocity * velocity / (2.0 * over.max(1.0));
+        }
+        self.start_time = time;
+        self.position = edge;
+        self.velocity = velocity;
+        self.phase = Phase::Ballistic {
+            start: edge,
+            velocity,
+            deceleration,
+            duration_nanos: (1e9 * -velocity / deceleration) as u64,
+        };
+    }
+
+    /// Advance to `time`, returning whether the animation is still running.
+    fn update(&mut self, time: u64, over: f64) -> bool {
+        let elapsed = time.saturating_sub(self.start_time);
+        match self.phase {
+            Phase::Finished => return false,
+            Phase::Spline(fling) => {
+                let (position, velocity) = fling.sample(elapsed);
+                self.position = position;
+                self.velocity = velocity;
+                if position < self.min || position > self.max {
+                    let edge = position.clamp(self.min, self.max);
+                    if over > 0.0 && velocity != 0.0 {
+                        self.edge_reached(time, edge, velocity, over);
+                    } else {
+                        self.position = edge;
+                        self.velocity = 0.0;
+                        self.phase = Phase::Finished;
+                    }
+                } else if elapsed >= fling.duration_nanos() {
+                    self.phase = Phase::Finished;
+                }
+            }
+            Phase::Ballistic {
+                start,
+                velocity,
+                deceleration,
+                duration_nanos,
+            } => {
+                let t = elapsed.min(duration_nanos) as f64 * 1e-9;
+                self.position = start + velocity * t + deceleration * t * t / 2.0;
+                self.velocity = velocity + deceleration * t;
+                if elapsed >= duration_nanos {
+                    self.spring_back(time);
+                }
+            }
+            Phase::Cubic {
+                start,
+                end,
+                duration_nanos,
+            } => {
+                if elapsed >= duration_nanos {
+                    self.position = end;
+                    self.velocity = 0.0;
+                    self.phase = Phase::Finished;
+                } else {
+                    let t = elapsed as f64 / duration_nanos as f64;
+                    let d = end - start;
+                    self.position = start + d * t * t * (3.0 - 2.0 * t);
+                    self.velocity = d * 6.0 * t * (1.0 - t) / (duration_nanos as f64 * 1e-9);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// A two-dimensional fling and spring-back animator, driven by frame times
+/// such as those passed to `do_frame`.
+#[derive(Clone, Debug)]
+pub struct OverScroller {
+    x: AxisScroller,
+    y: AxisScroller,
+    friction: f32,
+    density: f32,
+    min_velocity: f64,
+    max_velocity: f64,
+    /// Maximum distance in pixels a fling may travel past the edge of the
+    /// scroll range before springing back. 0 stops flings at the edge.
+    pub overscroll: f64,
+}
+
+impl OverScroller {
+    /// Create a scroller using the fling parameters of `vc`.
+    ///
+    /// `density` is the display density, as in `DisplayMetrics.density`.
+    pub fn new(vc: &ViewConfiguration, density: f32) -> Self {
+        let mut scroller = Self {
+            x: AxisScroller::default(),
+            y: AxisScroller::default(),
+            friction: vc.scroll_friction,
+            density,
+            min_velocity: vc.scaled_minimum_fling_velocity as f64,
+            max_velocity: vc.scaled_maximum_fling_velocity as f64,
+            overscroll: 0.0,
+        };
+        scroller.set_bounds(
+            PhysicalPosition::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+            PhysicalPosition::new(f64::INFINITY, f64::INFINITY),
+        );
+        scroller
+    }
+
+    /// Set the range that flings are confined to.
+    pub fn set_bounds(&mut self, min: PhysicalPosition<f64>, max: PhysicalPosition<f64>) {
+        (self.x.min, self.x.max) = (min.x, max.x);
+        (self.y.min, self.y.max) = (min.y, max.y);
+    }
+
+    /// Start a fling at `time_nanos` from `start`.
+    ///
+    /// The velocity is limited to the maximum fling velocity, and nothing
+    /// happens if it is below the minimum, in which case this returns `false`.
+    pub fn fling(
+        &mut self,
+        time_nanos: u64,
+        start: PhysicalPosition<f64>,
+        velocity: Velocity,
+    ) -> bool {
+        let velocity = velocity.clamp(self.max_velocity);
+        if velocity.x.abs() < self.min_velocity && velocity.y.abs() < self.min_velocity {
+            return false;
+        }
+        self.x
+            .fling(time_nanos, start.x, velocity.x, self.friction, self.density);
+        self.y
+            .fling(time_nanos, start.y, velocity.y, self.friction, self.density);
+        true
+    }
+
+    /// Start returning `start` to within the bounds, returning `false`
+    /// if it is already inside them.
+    pub fn spring_back(&mut self, time_nanos: u64, start: PhysicalPosition<f64>) -> bool {
+        self.x.position = start.x;
+        self.y.position = start.y;
+        let x = self.x.spring_back(time_nanos);
+        let y = self.y.spring_back(time_nanos);
+        x || y
+    }
+
+    /// Advance the animation to `time_nanos`, returning whether it is still running.
+    pub fn compute_scroll_offset(&mut self, time_nanos: u64) -> bool {
+        let x = self.x.update(time_nanos, self.overscroll);
+        let y = self.y.update(time_nanos, self.overscroll);
+        x || y
+    }
+
+    pub fn current_position(&self) -> PhysicalPosition<f64> {
+        PhysicalPosition::new(self.x.position, self.y.position)
+    }
+
+    pub fn current_velocity(&self) -> Velocity {
+        Velocity {
+            x: self.x.velocity,
+            y: self.y.velocity,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.x.phase == Phase::Finished && self.y.phase == Phase::Finished
+    }
+
+    /// Stop the animation where it is.
+    pub fn abort(&mut self) {
+        self.x.phase = Phase::Finished;
+        self.y.phase = Phase::Finished;
+        self.x.velocity = 0.0;
+        self.y.velocity = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ViewConfiguration.getScrollFriction`.
+    const FRICTION: f32 = 0.015;
+
+    #[test]
+    fn fling_matches_aosp() {
+        // Distances from `SplineOverScroller.getSplineFlingDistance` and
+        // durations in whole milliseconds from `getSplineFlingDuration`.
+        for (density, velocity, distance, duration_millis) in [
+            (1.0, 1000.0, 194.3136, 555),
+            (2.625, 4000.0, 1059.8616, 757),
+            (3.5, -8000.0, -2857.1264, 1020),
+        ] {
+            let fling = Fling::new(100.0, velocity, FRICTION, density);
+            assert!(
+                (fling.final_position() - 100.0 - distance).abs() < 1e-3,
+                "{fling:?}"
+            );
+            assert_eq!(fling.duration_nanos() / 1_000_000, duration_millis);
+        }
+    }
+
+    #[test]
+    fn spline_endpoints() {
+        let spline = spline_position();
+        // The bisection stops within 1e-5 of each sample, as in AOSP.
+        assert!(spline[0] < 1e-4);
+        assert_eq!(spline[SPLINE_SAMPLES], 1.0);
+        assert!(spline.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn fling_decelerates_to_rest() {
+        let fling = Fling::new(0.0, 4000.0, FRICTION, 2.625);
+        let (start, start_velocity) = fling.sample(0);
+        assert!(start.abs() < 0.1, "{start}");
+        // The spline starts at roughly the fling velocity.
+        assert!((start_velocity - 4000.0).abs() < 400.0, "{start_velocity}");
+        let mut last = (start, start_velocity);
+        for millis in (16..fling.duration_nanos() / 1_000_000).step_by(16) {
+            let sample = fling.sample(millis * 1_000_000);
+            assert!(sample.0 > last.0 && sample.1 <= last.1, "{sample:?}");
+            last = sample;
+        }
+        assert_eq!(
+            fling.sample(fling.duration_nanos()),
+            (fling.final_position(), 0.0)
+        );
+    }
+
+    #[test]
+    fn zero_velocity_stays() {
+        let fling = Fling::new(5.0, 0.0, FRICTION, 1.0);
+        assert_eq!(fling.duration_nanos(), 0);
+        assert_eq!(fling.sample(0), (5.0, 0.0));
+    }
+
+    fn scroller() -> OverScroller {
+        let vc = ViewConfiguration {
+            scroll_friction: FRICTION,
+            scaled_minimum_fling_velocity: 50,
+            scaled_maximum_fling_velocity: 8000,
+            ..Default::default()
+        };
+        OverScroller::new(&vc, 1.0)
+    }
+
+    #[test]
+    fn fling_velocity_limits() {
+        let mut scroller = scroller();
+        let start = PhysicalPosition::new(0.0, 0.0);
+        assert!(!scroller.fling(0, start, Velocity { x: 10.0, y: -10.0 }));
+        assert!(scroller.is_finished());
+        assert!(scroller.fling(0, start, Velocity { x: 20000.0, y: 0.0 }));
+        assert_eq!(scroller.current_velocity().x, 8000.0);
+    }
+
+    #[test]
+    fn fling_stops_at_edge() {
+        let mut scroller = scroller();
+        scroller.set_bounds(
+            PhysicalPosition::new(0.0, 0.0),
+            PhysicalPosition::new(100.0, 100.0),
+        );
+        scroller.fling(
+            0,
+            PhysicalPosition::new(50.0, 50.0),
+            Velocity { x: 2000.0, y: 0.0 },
+        );
+        let mut time = 0;
+        while scroller.compute_scroll_offset(time) {
+            time += 16_000_000;
+        }
+        assert_eq!(
+            scroller.current_position(),
+            PhysicalPosition::new(100.0, 50.0)
+        );
+    }
+
+    #[test]
+    fn spring_back_returns_to_bounds() {
+        let mut scroller = scroller();
+        scroller.set_bounds(
+            PhysicalPosition::new(0.0, 0.0),
+            PhysicalPosition::new(100.0, 100.0),
+        );
+        assert!(!scroller.spring_back(0, PhysicalPosition::new(50.0, 50.0)));
+        assert!(scroller.spring_back(0, PhysicalPosition::new(-40.0, 50.0)));
+        // sqrt(2 * 40 / 2000) s.
+        scroller.compute_scroll_offset(100_000_000);
+        let x = scroller.current_position().x;
+        assert!(-40.0 < x && x < 0.0, "{x}");
+        scroller.compute_scroll_offset(200_000_000);
+        assert_eq!(
+            scroller.current_position(),
+            PhysicalPosition::new(0.0, 50.0)
+        );
+        assert!(!scroller.compute_scroll_offset(216_000_000));
+    }
+}
@@ -0,0 +1,1221 @@
+//! Owned, JNI-free copies of input events.
+//!
+//! A snapshot is read from Java in one pass, after which it can be kept,
+//! cloned, and sent to other threads long after the callback that
+//! delivered the event has returned.
+
+use dpi::PhysicalPosition;
+use ndk::event::{
+    Axis, ButtonState, KeyAction, KeyEventFlags, Keycode, MetaState, MotionAction,
+    MotionEventFlags, Source, ToolType,
+};
+use num_enum::FromPrimitive;
+use std::collections::BTreeMap;
+use ui_events::{
+    ScrollDelta,
+    keyboard::{KeyboardEvent, Modifiers},
+    pointer::{
+        ContactGeometry, PersistentDeviceId, PointerButton, PointerButtons, PointerEvent,
+        PointerId, PointerInfo, PointerOrientation, PointerState, PointerType, PointerUpdate,
+    },
+};
+
+use crate::{ViewConfiguration, events::meta_state_to_modifiers};
+
+/// Number of axis values stored for each pointer in each sample of a
+/// [`MotionEventSnapshot`], covering `AXIS_X` through
+/// `AXIS_GESTURE_SWIPE_FINGER_COUNT`. The gesture axes from `AXIS_GESTURE_X_OFFSET`
+/// on are 0 before API level 34.
+pub const MOTION_EVENT_AXIS_COUNT: usize = 54;
+
+const ACTION_MASK: i32 = 0xff;
+const SOURCE_GAMEPAD: i32 = 0x401;
+const SOURCE_JOYSTICK: i32 = 0x1000010;
+const ACTION_POINTER_INDEX_MASK: i32 = 0xff00;
+const ACTION_POINTER_INDEX_SHIFT: i32 = 8;
+
+fn is_gamepad_source(source: i32) -> bool {
+    source & SOURCE_GAMEPAD == SOURCE_GAMEPAD || source & SOURCE_JOYSTICK == SOURCE_JOYSTICK
+}
+
+/// Identity of one pointer in a [`MotionEventSnapshot`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PointerProperties {
+    /// The value of `MotionEvent.getPointerId`.
+    pub id: i32,
+    /// The value of `MotionEvent.getToolType`.
+    pub tool_type: i32,
+}
+
+/// The end of a pen that is in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PenTool {
+    Tip,
+    Eraser,
+}
+
+/// Pen data with no counterpart in [`PointerState`], from
+/// [`MotionEventSnapshot::pen_samples`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PenSample {
+    /// Time of the sample, matching [`PointerState::time`].
+    pub time: u64,
+    pub tool: PenTool,
+    /// Whether the pen is touching the screen rather than hovering.
+    pub in_contact: bool,
+    /// Hover distance from the screen in device-specific units, or 0 when
+    /// touching or when the device doesn't report it.
+    pub distance: f32,
+    /// Tilt toward the right of the screen, in radians from -π/2 to π/2.
+    pub tilt_x: f32,
+    /// Tilt toward the bottom of the screen, in radians from -π/2 to π/2.
+    pub tilt_y: f32,
+}
+
+/// An owned copy of an Android `MotionEvent`, including every pointer,
+/// every axis and the full batched history.
+///
+/// Fields hold the raw values reported by Android; the accessor methods
+/// mirror those of [`MotionEvent`](crate::MotionEvent) and return typed values.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MotionEventSnapshot {
+    pub device_id: i32,
+    pub source: i32,
+    /// The unmasked action, which includes the action pointer index.
+    pub action: i32,
+    pub action_button: i32,
+    pub button_state: u32,
+    pub flags: u32,
+    pub meta_state: u32,
+    /// Millisecond timestamp of the initial down event of the gesture.
+    pub down_time: i64,
+    pub pointers: Vec<PointerProperties>,
+    /// Nanosecond timestamp of each sample, oldest historical sample first
+    /// and the current sample last.
+    pub sample_times: Vec<i64>,
+    /// Axis values laid out as `[sample][pointer][axis]`, with samples in
+    /// the same order as `sample_times` and [`MOTION_EVENT_AXIS_COUNT`]
+    /// values per pointer.
+    pub axis_values: Vec<f32>,
+}
+
+impl MotionEventSnapshot {
+    /// Build a snapshot from the arrays produced by the Java `EventSnapshots` helper.
+    pub(crate) fn from_packed(header: &[i64], axis_values: Vec<f32>) -> Self {
+        let pointer_count = header[8] as usize;
+        let history_size = header[9] as usize;
+        let (pointers, sample_times) = header[10..].split_at(2 * pointer_count);
+        Self {
+            device_id: header[0] as i32,
+            source: header[1] as i32,
+            action: header[2] as i32,
+            action_button: header[3] as i32,
+            button_state: header[4] as u32,
+            flags: header[5] as u32,
+            meta_state: header[6] as u32,
+            down_time: header[7],
+            pointers: pointers
+                .chunks_exact(2)
+                .map(|p| PointerProperties {
+                    id: p[0] as i32,
+                    tool_type: p[1] as i32,
+                })
+                .collect(),
+            sample_times: sample_times[..=history_size].to_vec(),
+            axis_values,
+        }
+    }
+
+    /// Copy a native motion event, such as one obtained from
+    /// [`MotionEvent::to_native`](crate::MotionEvent::to_native).
+    ///
+    /// `action_button` is not available from the NDK before API level 33,
+    /// so it must be supplied by the caller. The NDK reports 0 for axes the
+    /// platform doesn't have, such as the gesture axes before API level 34.
+    pub fn from_native(event: &ndk::event::MotionEvent, action_button: i32) -> Self {
+        let pointer_count = event.pointer_count();
+        let mut sample_times = Vec::with_capacity(event.history_size() + 1);
+        let mut axis_values = Vec::with_capacity(
+            (event.history_size() + 1) * pointer_count * MOTION_EVENT_AXIS_COUNT,
+        );
+        for sample in event.history() {
+            sample_times.push(sample.event_time());
+            for pointer in sample.pointers() {
+                axis_values.extend(
+                    (0..MOTION_EVENT_AXIS_COUNT as i32)
+                        .map(|axis| pointer.axis_value(Axis::from_primitive(axis))),
+                );
+            }
+        }
+        sample_times.push(event.event_time());
+        for pointer in event.pointers() {
+            axis_values.extend(
+                (0..MOTION_EVENT_AXIS_COUNT as i32)
+                    .map(|axis| pointer.axis_value(Axis::from_primitive(axis))),
+            );
+        }
+        Self {
+            device_id: event.device_id(),
+            source: event.source().into(),
+            action: i32::from(event.action())
+                | ((event.pointer_index() as i32) << ACTION_POINTER_INDEX_SHIFT),
+            action_button,
+            button_state: event.button_state().0,
+            flags: event.flags().0,
+            meta_state: event.meta_state().0,
+            down_time: event.down_time() / 1_000_000,
+            pointers: event
+                .pointers()
+                .map(|p| PointerProperties {
+                    id: p.pointer_id(),
+                    tool_type: p.tool_type().into(),
+                })
+                .collect(),
+            sample_times,
+            axis_values,
+        }
+    }
+
+    pub fn source(&self) -> Source {
+        Source::from_primitive(self.source)
+    }
+
+    /// Whether this event came from a gamepad or joystick.
+    pub fn is_from_gamepad(&self) -> bool {
+        is_gamepad_source(self.source)
+    }
+
+    pub fn action_masked(&self) -> MotionAction {
+        MotionAction::from_primitive(self.action & ACTION_MASK)
+    }
+
+    pub fn action_index(&self) -> usize {
+        ((self.action & ACTION_POINTER_INDEX_MASK) >> ACTION_POINTER_INDEX_SHIFT) as usize
+    }
+
+    pub fn button_state(&self) -> ButtonState {
+        ButtonState(self.button_state)
+    }
+
+    pub fn flags(&self) -> MotionEventFlags {
+        MotionEventFlags(self.flags)
+    }
+
+    pub fn meta_state(&self) -> MetaState {
+        MetaState(self.meta_state)
+    }
+
+    pub fn pointer_count(&self) -> usize {
+        self.pointers.len()
+    }
+
+    pub fn pointer_id(&self, pointer_index: usize) -> i32 {
+        self.pointers[pointer_index].id
+    }
+
+    pub fn tool_type(&self, pointer_index: usize) -> ToolType {
+        ToolType::from(self.pointers[pointer_index].tool_type)
+    }
+
+    /// Number of historical samples, not counting the current one.
+    pub fn history_size(&self) -> usize {
+        self.sample_times.len().saturating_sub(1)
+    }
+
+    pub fn event_time_nanos(&self) -> i64 {
+        self.sample_times.last().copied().unwrap_or_default()
+    }
+
+    pub fn historical_event_time_nanos(&self, pos: usize) -> i64 {
+        self.sample_times[pos]
+    }
+
+    pub fn axis(&self, axis: Axis, pointer_index: usize) -> f32 {
+        self.sample_axis(self.history_size(), axis, pointer_index)
+    }
+
+    pub fn historical_axis(&self, axis: Axis, pointer_index: usize, pos: usize) -> f32 {
+        self.sample_axis(pos, axis, pointer_index)
+    }
+
+    /// Value of `axis` for a pointer in a sample, where samples are indexed
+    /// as in `sample_times`.
+    fn sample_axis(&self, sample: usize, axis: Axis, pointer_index: usize) -> f32 {
+        let axis = i32::from(axis);
+        if !(0..MOTION_EVENT_AXIS_COUNT as i32).contains(&axis) {
+            return 0.0;
+        }
+        let i = (sample * self.pointers.len() + pointer_index) * MOTION_EVENT_AXIS_COUNT
+            + axis as usize;
+        self.axis_values.get(i).copied().unwrap_or_default()
+    }
+
+    fn pointer_info(&self, pointer_index: usize) -> PointerInfo {
+        PointerInfo {
+            pointer_id: match self.pointer_id(pointer_index) {
+                n if n < 0 => None,
+                n => PointerId::new(n as u64 + 1),
+            },
+            persistent_device_id: PersistentDeviceId::new(self.device_id as u64),
+            pointer_type: match self.tool_type(pointer_index) {
+                ToolType::Mouse => PointerType::Mouse,
+                ToolType::Finger => PointerType::Touch,
+                ToolType::Stylus | ToolType::Eraser => PointerType::Pen,
+                _ => PointerType::Unknown,
+            },
+        }
+    }
+
+    /// Whether a pen pointer is touching the screen, as opposed to hovering.
+    fn in_contact(&self, pointer_index: usize) -> bool {
+        match self.action_masked() {
+            MotionAction::Down | MotionAction::PointerDown | MotionAction::Move => true,
+            MotionAction::Up | MotionAction::PointerUp => pointer_index != self.action_index(),
+            MotionAction::ButtonPress | MotionAction::ButtonRelease => {
+                self.axis(Axis::Pressure, pointer_index) > 0.0
+            }
+            _ => false,
+        }
+    }
+
+    fn pointer_buttons(&self, pointer_index: usize) -> PointerButtons {
+        let tool_type = self.tool_type(pointer_index);
+        let mut pb = PointerButtons::default();
+        let bs = self.button_state();
+        if bs.primary() {
+            pb |= PointerButton::Primary;
+        }
+        // Pen contact is reported like a button press, using the eraser
+        // button when the eraser end is down.
+        if matches!(tool_type, ToolType::Stylus | ToolType::Eraser)
+            && self.in_contact(pointer_index)
+        {
+            pb |= if tool_type == ToolType::Eraser {
+                PointerButton::PenEraser
+            } else {
+                PointerButton::Primary
+            };
+        }
+        // The first stylus barrel button acts as a secondary button, and the second
+        // as an auxiliary one, whichever end of the pen is in use.
+        if bs.secondary() || bs.stylus_primary() {
+            pb |= PointerButton::Secondary;
+        }
+        if bs.teriary() || bs.stylus_secondary() {
+            pb |= PointerButton::Auxiliary;
+        }
+        if bs.back() {
+            pb |= PointerButton::X1;
+        }
+        if bs.forward() {
+            pb |= PointerButton::X2;
+        }
+        pb
+    }
+
+    fn pen_orientation(&self, sample: usize, pointer_index: usize) -> PointerOrientation {
+        use core::f32::consts::FRAC_PI_2;
+        let axis = |axis| self.sample_axis(sample, axis, pointer_index);
+        let altitude = FRAC_PI_2 - axis(Axis::Tilt);
+        let azimuth = (-axis(Axis::Orientation) + 3.0 * FRAC_PI_2).rem_euclid(4.0 * FRAC_PI_2);
+        PointerOrientation { altitude, azimuth }
+    }
+
+    fn pointer_state(
+        &self,
+        sample: usize,
+        pointer_index: usize,
+        buttons: PointerButtons,
+        modifiers: Modifiers,
+    ) -> PointerState {
+        let tool_type = self.tool_type(pointer_index);
+        let axis = |axis| self.sample_axis(sample, axis, pointer_index);
+        let orientation = if matches!(tool_type, ToolType::Stylus | ToolType::Eraser) {
+            self.pen_orientation(sample, pointer_index)
+        } else {
+            Default::default()
+        };
+        let contact_geometry = {
+            let height = axis(Axis::TouchMajor) as f64;
+            let width = axis(Axis::TouchMinor) as f64;
+            if tool_type == ToolType::Finger && height > 0.0 && width > 0.0 {
+                ContactGeometry { width, height }
+            } else {
+                Default::default()
+            }
+        };
+        PointerState {
+            time: self.sample_times[sample] as u64,
+            position: PhysicalPosition::<f64> {
+                x: axis(Axis::X) as f64,
+                y: axis(Axis::Y) as f64,
+            },
+            buttons,
+            // `TapCounter` will attach an appropriate count.
+            count: 0,
+            modifiers,
+            contact_geometry,
+            orientation,
+            pressure: axis(Axis::Pressure) * 0.5,
+            // Android has no standard axis for tangential pressure; see
+            // `PenTracker::with_tangential_pressure_axis`.
+            tangential_pressure: 0.0,
+        }
+    }
+
+    /// Pen data for a pointer in every sample of this event, oldest first,
+    /// or `None` if the pointer is not a stylus or eraser.
+    ///
+    /// The tool can change between events without the pointer changing, such
+    /// as when a pen is flipped while hovering; [`PenSample::tool`] and the
+    /// buttons of the pointer state follow the end that is in use.
+    /// [`PenTracker`] reports such a change as the old tool leaving and the
+    /// new one arriving.
+    pub fn pen_samples(&self, pointer_index: usize) -> Option<Vec<PenSample>> {
+        let tool = match self.tool_type(pointer_index) {
+            ToolType::Stylus => PenTool::Tip,
+            ToolType::Eraser => PenTool::Eraser,
+            _ => return None,
+        };
+        let in_contact = self.in_contact(pointer_index);
+        Some(
+            (0..self.sample_times.len())
+                .map(|sample| {
+                    let PointerOrientation { altitude, azimuth } =
+                        self.pen_orientation(sample, pointer_index);
+                    let tan_altitude = altitude.tan();
+                    PenSample {
+                        time: self.sample_times[sample] as u64,
+                        tool,
+                        in_contact,
+                        distance: self.sample_axis(sample, Axis::Distance, pointer_index),
+                        tilt_x: (azimuth.cos() / tan_altitude).atan(),
+                        tilt_y: (azimuth.sin() / tan_altitude).atan(),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Index of the pointer described by `pointer`, if it is in this event.
+    pub(crate) fn pointer_index_of(&self, pointer: &PointerInfo) -> Option<usize> {
+        (0..self.pointer_count()).find(|&i| self.pointer_info(i) == *pointer)
+    }
+
+    /// State of a pointer in every sample of this event, oldest first.
+    pub(crate) fn pointer_samples(&self, pointer_index: usize) -> Vec<PointerState> {
+        let buttons = self.pointer_buttons(pointer_index);
+        let modifiers = meta_state_to_modifiers(self.meta_state());
+        (0..self.sample_times.len())
+            .map(|sample| self.pointer_state(sample, pointer_index, buttons, modifiers))
+            .collect()
+    }
+
+    /// Movement of a pointer across every sample of this event, or `None` for a palm.
+    fn pointer_update(&self, pointer_index: usize) -> Option<PointerUpdate> {
+        if self.tool_type(pointer_index) == ToolType::Palm {
+            return None;
+        }
+        let buttons = self.pointer_buttons(pointer_index);
+        let modifiers = meta_state_to_modifiers(self.meta_state());
+        Some(PointerUpdate {
+            pointer: self.pointer_info(pointer_index),
+            current: self.pointer_state(self.history_size(), pointer_index, buttons, modifiers),
+            coalesced: (0..self.history_size())
+                .map(|pos| self.pointer_state(pos, pointer_index, buttons, modifiers))
+                .collect(),
+            // Filled in by `MotionPredictor`.
+            predicted: vec![],
+        })
+    }
+
+    /// Convert the pointer at the action index to a [`PointerEvent`].
+    ///
+    /// Movement of any other pointers is not reported; see
+    /// [`to_pointer_events`](Self::to_pointer_events).
+    pub fn to_pointer_event(&self, vc: &ViewConfiguration) -> Option<PointerEvent> {
+        if self.is_from_gamepad() || self.pointer_count() == 0 {
+            // Handled by `GamepadState`.
+            return None;
+        }
+        let action = self.action_masked();
+        let action_index = self.action_index();
+        let tool_type = self.tool_type(action_index);
+        if tool_type == ToolType::Palm {
+            // I don't think we have any useful way of handling this.
+            return None;
+        }
+        let pointer = self.pointer_info(action_index);
+        let buttons = self.pointer_buttons(action_index);
+        let modifiers = meta_state_to_modifiers(self.meta_state());
+        let state = self.pointer_state(self.history_size(), action_index, buttons, modifiers);
+
+        let button = {
+            // Button constants from <https://developer.android.com/reference/android/view/MotionEvent>.
+            const BUTTON_PRIMARY: i32 = 0b1;
+            const BUTTON_STYLUS_PRIMARY: i32 = 0b100000;
+            const BUTTON_SECONDARY: i32 = 0b10;
+            const BUTTON_STYLUS_SECONDARY: i32 = 0b1000000;
+            const BUTTON_TERTIARY: i32 = 0b100;
+            const BUTTON_BACK: i32 = 0b1000;
+            const BUTTON_FORWARD: i32 = 0b10000;
+            match self.action_button {
+                BUTTON_PRIMARY => Some(PointerButton::Primary),
+                BUTTON_SECONDARY | BUTTON_STYLUS_PRIMARY => Some(PointerButton::Secondary),
+                BUTTON_TERTIARY | BUTTON_STYLUS_SECONDARY => Some(PointerButton::Auxiliary),
+                BUTTON_BACK => Some(PointerButton::X1),
+                BUTTON_FORWARD => Some(PointerButton::X2),
+                // A pen touching down or lifting off.
+                _ => match tool_type {
+                    ToolType::Stylus => Some(PointerButton::Primary),
+                    ToolType::Eraser => Some(PointerButton::PenEraser),
+                    _ => None,
+                },
+            }
+        };
+
+        Some(match action {
+            MotionAction::Down | MotionAction::PointerDown => PointerEvent::Down {
+                pointer,
+                state,
+                button,
+            },
+            MotionAction::Up | MotionAction::PointerUp => PointerEvent::Up {
+                pointer,
+                state,
+                button,
+            },
+            MotionAction::Move | MotionAction::HoverMove => {
+                PointerEvent::Move(self.pointer_update(action_index)?)
+            }
+            // Pressing or releasing a button while another is held, such as a
+            // stylus barrel button, changes the buttons of the existing pointer.
+            MotionAction::ButtonPress | MotionAction::ButtonRelease => {
+                PointerEvent::Move(self.pointer_update(action_index)?)
+            }
+            MotionAction::Cancel => PointerEvent::Cancel(pointer),
+            MotionAction::HoverEnter => PointerEvent::Enter(pointer),
+            MotionAction::HoverExit => PointerEvent::Leave(pointer),
+            MotionAction::Scroll => PointerEvent::Scroll {
+                pointer,
+                delta: ScrollDelta::PixelDelta(PhysicalPosition::<f64> {
+                    x: (self.axis(Axis::Hscroll, action_index) * vc.scaled_horizontal_scroll_factor)
+                        as f64,
+                    y: (self.axis(Axis::Vscroll, action_index) * vc.scaled_vertical_scroll_factor)
+                        as f64,
+                }),
+                state,
+            },
+            _ => {
+                // Other current `MotionAction` values relate to gamepad/joystick buttons;
+                // ui-events doesn't currently have types for these, so consider them unhandled.
+                // `GamepadState` maps gamepad input instead.
+                return None;
+            }
+        })
+    }
+
+    /// Convert to one [`PointerEvent`] for each pointer in this event.
+    ///
+    /// Moves and cancellations are reported for every pointer, each with its
+    /// own coalesced history. Down and up actions only report the action
+    /// pointer: the other pointers are where the last move left them, since
+    /// pending moves are delivered before a pointer goes down or up.
+    pub fn to_pointer_events(&self, vc: &ViewConfiguration) -> Vec<PointerEvent> {
+        if self.is_from_gamepad() {
+            return vec![];
+        }
+        match self.action_masked() {
+            MotionAction::Move => (0..self.pointer_count())
+                .filter_map(|i| self.pointer_update(i).map(PointerEvent::Move))
+                .collect(),
+            MotionAction::Cancel => (0..self.pointer_count())
+                .filter(|&i| self.tool_type(i) != ToolType::Palm)
+                .map(|i| PointerEvent::Cancel(self.pointer_info(i)))
+                .collect(),
+            _ => self.to_pointer_event(vc).into_iter().collect(),
+        }
+    }
+}
+
+/// A [`PointerEvent`] with the pen data of its pointer, from [`PenTracker`].
+#[derive(Clone, Debug)]
+pub struct PenEvent {
+    pub event: PointerEvent,
+    /// Pen data at the latest sample of the event, including the hover
+    /// distance, or `None` if the pointer is not a pen.
+    pub pen: Option<PenSample>,
+}
+
+/// Converts pen input to [`PenEvent`]s, following each pen across events.
+///
+/// When the tool of a pen changes without the pointer going away, such as
+/// when it is flipped to the eraser, the old tool is cancelled if it was
+/// touching the screen and leaves otherwise, and the new tool goes down or
+/// enters in its place.
+#[derive(Clone, Debug, Default)]
+pub struct PenTracker {
+    pens: BTreeMap<PointerInfo, PenSample>,
+    tangential_pressure_axis: Option<Axis>,
+}
+
+impl PenTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a tracker that fills [`PointerState::tangential_pressure`] of
+    /// pens from `axis`, clamped to -1..1.
+    ///
+    /// Android has no standard axis for tangential pressure, such as from
+    /// the wheel of an airbrush, so pens that report it use a vendor axis
+    /// like [`Axis::Generic1`]. Without one, it is always 0.
+    pub fn with_tangential_pressure_axis(axis: Axis) -> Self {
+        Self {
+            tangential_pressure_axis: Some(axis),
+            ..Self::default()
+        }
+    }
+
+    /// Convert `event` as with [`MotionEventSnapshot::to_pointer_events`],
+    /// adding the pen data of each pointer. Feed it every touch and hover
+    /// event of the view, in order.
+    pub fn to_pen_events(
+        &mut self,
+        event: &MotionEventSnapshot,
+        vc: &ViewConfiguration,
+    ) -> Vec<PenEvent> {
+        let mut result = vec![];
+        for mut e in event.to_pointer_events(vc) {
+            let pointer = match &e {
+                PointerEvent::Down { pointer, .. }
+                | PointerEvent::Up { pointer, .. }
+                | PointerEvent::Scroll { pointer, .. }
+                | PointerEvent::Cancel(pointer)
+                | PointerEvent::Enter(pointer)
+                | PointerEvent::Leave(pointer) => *pointer,
+                PointerEvent::Move(update) => update.pointer,
+            };
+            let index = event.pointer_index_of(&pointer);
+            let pen = index
+                .and_then(|i| event.pen_samples(i))
+                .and_then(|samples| samples.last().copied());
+            let (Some(index), Some(pen)) = (index, pen) else {
+                result.push(PenEvent {
+                    event: e,
+                    pen: None,
+                });
+                continue;
+            };
+            if let Some(axis) = self.tangential_pressure_axis {
+                fill_tangential_pressure(&mut e, event, index, axis);
+            }
+            if let Some(previous) = self.pens.get(&pointer).copied()
+                && previous.tool != pen.tool
+                && !self.switch_tool(&mut result, &e, pointer, previous, pen)
+            {
+                continue;
+            }
+            match e {
+                PointerEvent::Cancel(_) | PointerEvent::Leave(_) => {
+                    self.pens.remove(&pointer);
+                }
+                _ => {
+                    self.pens.insert(pointer, pen);
+                }
+            }
+            result.push(PenEvent {
+                event: e,
+                pen: Some(pen),
+            });
+        }
+        result
+    }
+
+    /// Report the end of the previous tool of `pointer` and the start of
+    /// the new one before `event`. Returns whether `event` should still be
+    /// reported.
+    fn switch_tool(
+        &mut self,
+        result: &mut Vec<PenEvent>,
+        event: &PointerEvent,
+        pointer: PointerInfo,
+        previous: PenSample,
+        pen: PenSample,
+    ) -> bool {
+        if matches!(event, PointerEvent::Cancel(_) | PointerEvent::Leave(_)) {
+            // The pointer is going away anyway.
+            return true;
+        }
+        self.pens.insert(pointer, pen);
+        if previous.in_contact {
+            result.push(PenEvent {
+                event: PointerEvent::Cancel(pointer),
+                pen: Some(previous),
+            });
+            return match event {
+                // The new tool continues the stroke, so starts a new one.
+                PointerEvent::Move(update) if pen.in_contact => {
+                    result.push(PenEvent {
+                        event: PointerEvent::Down {
+                            pointer,
+                            state: update.current.clone(),
+                            button: Some(match pen.tool {
+                                PenTool::Tip => PointerButton::Primary,
+                                PenTool::Eraser => PointerButton::PenEraser,
+                            }),
+                        },
+                        pen: Some(pen),
+                    });
+                    false
+                }
+                // The cancelled stroke can't end.
+                PointerEvent::Up { .. } => {
+                    self.pens.remove(&pointer);
+                    false
+                }
+                _ => true,
+            };
+        }
+        result.push(PenEvent {
+            event: PointerEvent::Leave(pointer),
+            pen: Some(previous),
+        });
+        if !matches!(event, PointerEvent::Enter(_) | PointerEvent::Down { .. }) {
+            result.push(PenEvent {
+                event: PointerEvent::Enter(pointer),
+                pen: Some(pen),
+            });
+        }
+        true
+    }
+}
+
+/// Set the tangential pressure of every state in `e` from `axis` of the
+/// pointer at `pointer_index`.
+fn fill_tangential_pressure(
+    e: &mut PointerEvent,
+    event: &MotionEventSnapshot,
+    pointer_index: usize,
+    axis: Axis,
+) {
+    let value = |sample| {
+        event
+            .sample_axis(sample, axis, pointer_index)
+            .clamp(-1.0, 1.0)
+    };
+    match e {
+        PointerEvent::Down { state, .. }
+        | PointerEvent::Up { state, .. }
+        | PointerEvent::Scroll { state, .. } => {
+            state.tangential_pressure = value(event.history_size());
+        }
+        PointerEvent::Move(update) => {
+            for (sample, state) in update.coalesced.iter_mut().enumerate() {
+                state.tangential_pressure = value(sample);
+            }
+            update.current.tangential_pressure = value(event.history_size());
+        }
+        PointerEvent::Cancel(_) | PointerEvent::Enter(_) | PointerEvent::Leave(_) => {}
+    }
+}
+
+/// An owned copy of an Android `KeyEvent`.
+///
+/// Fields hold the raw values reported by Android; the accessor methods
+/// mirror those of [`KeyEvent`](crate::KeyEvent) and return typed values.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyEventSnapshot {
+    pub device_id: i32,
+    pub source: i32,
+    pub action: i32,
+    /// Millisecond timestamp of this event.
+    pub event_time: i64,
+    /// Millisecond timestamp of the most recent key down event.
+    pub down_time: i64,
+    pub flags: u32,
+    pub meta_state: u32,
+    pub repeat_count: i32,
+    pub key_code: i32,
+    pub scan_code: i32,
+    /// The value of `KeyEvent.getUnicodeChar`, or 0 if there is none.
+    pub unicode_char: i32,
+}
+
+impl KeyEventSnapshot {
+    /// Build a snapshot from the array produced by the Java `EventSnapshots` helper.
+    pub(crate) fn from_packed(packed: &[i64]) -> Self {
+        Self {
+            device_id: packed[0] as i32,
+            source: packed[1] as i32,
+            action: packed[2] as i32,
+            event_time: packed[3],
+            down_time: packed[4],
+            flags: packed[5] as u32,
+            meta_state: packed[6] as u32,
+            repeat_count: packed[7] as i32,
+            key_code: packed[8] as i32,
+            scan_code: packed[9] as i32,
+            unicode_char: packed[10] as i32,
+        }
+    }
+
+    pub fn source(&self) -> Source {
+        Source::from_primitive(self.source)
+    }
+
+    /// Whether this event came from a gamepad or joystick.
+    pub fn is_from_gamepad(&self) -> bool {
+        is_gamepad_source(self.source)
+    }
+
+    pub fn action(&self) -> KeyAction {
+        KeyAction::from_primitive(self.action)
+    }
+
+    pub fn flags(&self) -> KeyEventFlags {
+        KeyEventFlags(self.flags)
+    }
+
+    pub fn meta_state(&self) -> MetaState {
+        MetaState(self.meta_state)
+    }
+
+    pub fn key_code(&self) -> Keycode {
+        Keycode::from_primitive(self.key_code)
+    }
+
+    pub fn unicode_char(&self) -> Option<char> {
+        if self.unicode_char <= 0 {
+            return None;
+        }
+        char::from_u32(self.unicode_char as _)
+    }
+
+    pub fn to_keyboard_event(&self) -> KeyboardEvent {
+        use ui_events::keyboard::{Key, KeyState, NamedKey, android};
+
+        let key_code = self.key_code();
+
+        KeyboardEvent {
+            state: if self.action() == KeyAction::Down {
+                KeyState::Down
+            } else {
+                KeyState::Up
+            },
+            key: match android::keycode_to_named_key(key_code.into()) {
+                NamedKey::Unidentified => {
+                    if let Some(c) = self.unicode_char() {
+                        Key::Character(c.to_string())
+                    } else {
+                        Key::Named(NamedKey::Unidentified)
+                    }
+                }
+                nk => Key::Named(nk),
+            },
+            code: android::keycode_to_code(key_code.into()),
+            location: android::keycode_to_location(key_code.into()),
+            modifiers: meta_state_to_modifiers(self.meta_state()),
+            repeat: self.repeat_count != 0,
+            is_composing: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MotionAction::*;
+
+    const SOURCE_TOUCHSCREEN: i32 = 0x1002;
+    const TOOL_FINGER: i32 = 1;
+
+    /// A touch event with the pointers `ids`, where each sample is a time
+    /// in milliseconds and the position of each pointer.
+    fn touch(action: i32, ids: &[i32], samples: &[(i64, &[(f32, f32)])]) -> MotionEventSnapshot {
+        let mut axis_values = vec![];
+        for (_, positions) in samples {
+            for &(x, y) in *positions {
+                let mut axes = [0.0; MOTION_EVENT_AXIS_COUNT];
+                axes[i32::from(Axis::X) as usize] = x;
+                axes[i32::from(Axis::Y) as usize] = y;
+                axes[i32::from(Axis::Pressure) as usize] = 1.0;
+                axis_values.extend(axes);
+            }
+        }
+        MotionEventSnapshot {
+            device_id: 2,
+            source: SOURCE_TOUCHSCREEN,
+            action,
+            pointers: ids
+                .iter()
+                .map(|&id| PointerProperties {
+                    id,
+                    tool_type: TOOL_FINGER,
+                })
+                .collect(),
+            sample_times: samples.iter().map(|(t, _)| t * 1_000_000).collect(),
+            axis_values,
+            ..Default::default()
+        }
+    }
+
+    fn action(action: MotionAction, index: i32) -> i32 {
+        i32::from(action) | index << ACTION_POINTER_INDEX_SHIFT
+    }
+
+    /// The ID that `pointer_info` gives to the Android pointer `id`.
+    fn pointer_id(id: u64) -> Option<PointerId> {
+        PointerId::new(id + 1)
+    }
+
+    /// The kind of `e`, its pointer ID and its latest position.
+    fn describe(e: &PointerEvent) -> (&'static str, Option<PointerId>, (f64, f64)) {
+        let (kind, pointer, state) = match e {
+            PointerEvent::Down { pointer, state, .. } => ("down", pointer, state),
+            PointerEvent::Up { pointer, state, .. } => ("up", pointer, state),
+            PointerEvent::Move(update) => ("move", &update.pointer, &update.current),
+            e => panic!("unexpected {e:?}"),
+        };
+        (
+            kind,
+            pointer.pointer_id,
+            (state.position.x, state.position.y),
+        )
+    }
+
+    #[test]
+    fn two_finger_move() {
+        let event = touch(
+            action(Move, 0),
+            &[0, 3],
+            &[
+                (8, &[(10.0, 20.0), (100.0, 200.0)]),
+                (12, &[(11.0, 22.0), (104.0, 203.0)]),
+                (16, &[(12.0, 24.0), (108.0, 206.0)]),
+            ],
+        );
+        let events = event.to_pointer_events(&ViewConfiguration::default());
+        let [PointerEvent::Move(first), PointerEvent::Move(second)] = &events[..] else {
+            panic!("expected two moves, got {events:?}");
+        };
+        for (update, id, history, current) in [
+            (first, 0, [(10.0, 20.0), (11.0, 22.0)], (12.0, 24.0)),
+            (second, 3, [(100.0, 200.0), (104.0, 203.0)], (108.0, 206.0)),
+        ] {
+            assert_eq!(update.pointer.pointer_id, pointer_id(id));
+            assert_eq!(update.pointer.pointer_type, PointerType::Touch);
+            assert_eq!(
+                (update.current.position.x, update.current.position.y),
+                current
+            );
+            assert_eq!(update.current.time, 16_000_000);
+            let coalesced: Vec<_> = update
+                .coalesced
+                .iter()
+                .map(|s| (s.time, (s.position.x, s.position.y)))
+                .collect();
+            assert_eq!(
+                coalesced,
+                [(8_000_000, history[0]), (12_000_000, history[1])]
+            );
+        }
+    }
+
+    #[test]
+    fn pointer_down_and_up_report_action_pointer() {
+        let vc = ViewConfiguration::default();
+        let sequence = [
+            touch(action(Down, 0), &[0], &[(0, &[(10.0, 10.0)])]),
+            touch(
+                action(PointerDown, 1),
+                &[0, 1],
+                &[(20, &[(10.0, 10.0), (50.0, 60.0)])],
+            ),
+            touch(
+                action(Move, 0),
+                &[0, 1],
+                &[(30, &[(12.0, 11.0), (55.0, 62.0)])],
+            ),
+            // The first finger lifts while the second stays down.
+            touch(
+                action(PointerUp, 0),
+                &[0, 1],
+                &[(40, &[(12.0, 11.0), (55.0, 62.0)])],
+            ),
+            touch(action(Up, 0), &[1], &[(50, &[(56.0, 63.0)])]),
+        ];
+        let events: Vec<_> = sequence
+            .iter()
+            .flat_map(|event| event.to_pointer_events(&vc))
+            .map(|e| describe(&e))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("down", pointer_id(0), (10.0, 10.0)),
+                ("down", pointer_id(1), (50.0, 60.0)),
+                ("move", pointer_id(0), (12.0, 11.0)),
+                ("move", pointer_id(1), (55.0, 62.0)),
+                ("up", pointer_id(0), (12.0, 11.0)),
+                ("up", pointer_id(1), (56.0, 63.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn cancel_reports_every_pointer() {
+        let event = touch(
+            action(Cancel, 0),
+            &[0, 1],
+            &[(0, &[(10.0, 10.0), (50.0, 60.0)])],
+        );
+        let cancelled: Vec<_> = event
+            .to_pointer_events(&ViewConfiguration::default())
+            .iter()
+            .map(|e| match e {
+                PointerEvent::Cancel(pointer) => pointer.pointer_id,
+                e => panic!("unexpected {e:?}"),
+            })
+            .collect();
+        assert_eq!(cancelled, [pointer_id(0), pointer_id(1)]);
+    }
+
+    const SOURCE_STYLUS: i64 = 0x4002;
+    const TOOL_STYLUS: i64 = 2;
+    const TOOL_ERASER: i64 = 4;
+    const BUTTON_STYLUS_PRIMARY: i64 = 0x20;
+
+    /// A single-pointer stylus event in the layout written by
+    /// `EventSnapshots`: the packed header, and the axes that are not 0.
+    type Packed = (&'static [i64], &'static [(Axis, f32)]);
+
+    fn unpack(&(header, axes): &Packed) -> MotionEventSnapshot {
+        let mut axis_values = vec![0.0; MOTION_EVENT_AXIS_COUNT];
+        for &(axis, value) in axes {
+            axis_values[i32::from(axis) as usize] = value;
+        }
+        MotionEventSnapshot::from_packed(header, axis_values)
+    }
+
+    use Axis::{Distance, Pressure, X, Y};
+
+    /// Hover, touch, press the barrel button while drawing, lift, then
+    /// flip the pen and erase.
+    #[rustfmt::skip]
+    const STROKE_AND_ERASE: &[Packed] = &[
+        // HOVER_ENTER, HOVER_MOVE and HOVER_EXIT with the tip.
+        (&[3, SOURCE_STYLUS, 9, 0, 0, 0, 0, 51_200, 1, 0, 0, TOOL_STYLUS, 51_200_000_000],
+            &[(X, 100.0), (Y, 200.0), (Distance, 12.0)]),
+        (&[3, SOURCE_STYLUS, 7, 0, 0, 0, 0, 51_200, 1, 0, 0, TOOL_STYLUS, 51_208_000_000],
+            &[(X, 102.0), (Y, 201.0), (Distance, 6.0)]),
+        (&[3, SOURCE_STYLUS, 10, 0, 0, 0, 0, 51_200, 1, 0, 0, TOOL_STYLUS, 51_216_000_000],
+            &[(X, 103.0), (Y, 202.0)]),
+        // DOWN, MOVE, BUTTON_PRESS, MOVE, BUTTON_RELEASE and UP.
+        (&[3, SOURCE_STYLUS, 0, 0, 0, 0, 0, 51_216, 1, 0, 0, TOOL_STYLUS, 51_216_000_000],
+            &[(X, 103.0), (Y, 202.0), (Pressure, 0.3)]),
+        (&[3, SOURCE_STYLUS, 2, 0, 0, 0, 0, 51_216, 1, 0, 0, TOOL_STYLUS, 51_224_000_000],
+            &[(X, 110.0), (Y, 205.0), (Pressure, 0.5)]),
+        (&[3, SOURCE_STYLUS, 11, BUTTON_STYLUS_PRIMARY, BUTTON_STYLUS_PRIMARY, 0, 0, 51_216, 1, 0,
+                0, TOOL_STYLUS, 51_230_000_000],
+            &[(X, 112.0), (Y, 206.0), (Pressure, 0.5)]),
+        (&[3, SOURCE_STYLUS, 2, 0, BUTTON_STYLUS_PRIMARY, 0, 0, 51_216, 1, 0, 0, TOOL_STYLUS,
+                51_232_000_000],
+            &[(X, 120.0), (Y, 210.0), (Pressure, 0.6)]),
+        (&[3, SOURCE_STYLUS, 12, BUTTON_STYLUS_PRIMARY, 0, 0, 0, 51_216, 1, 0, 0, TOOL_STYLUS,
+                51_238_000_000],
+            &[(X, 121.0), (Y, 210.0), (Pressure, 0.6)]),
+        (&[3, SOURCE_STYLUS, 1, 0, 0, 0, 0, 51_216, 1, 0, 0, TOOL_STYLUS, 51_240_000_000],
+            &[(X, 121.0), (Y, 210.0)]),
+        // HOVER_ENTER with the tip, then HOVER_MOVE and HOVER_EXIT with the eraser.
+        (&[3, SOURCE_STYLUS, 9, 0, 0, 0, 0, 51_216, 1, 0, 0, TOOL_STYLUS, 51_248_000_000],
+            &[(X, 121.0), (Y, 210.0), (Distance, 4.0)]),
+        (&[3, SOURCE_STYLUS, 7, 0, 0, 0, 0, 51_216, 1, 0, 0, TOOL_ERASER, 51_600_000_000],
+            &[(X, 121.0), (Y, 211.0), (Distance, 5.0)]),
+        (&[3, SOURCE_STYLUS, 10, 0, 0, 0, 0, 51_216, 1, 0, 0, TOOL_ERASER, 51_608_000_000],
+            &[(X, 121.0), (Y, 211.0)]),
+        // DOWN and UP with the eraser.
+        (&[3, SOURCE_STYLUS, 0, 0, 0, 0, 0, 51_608, 1, 0, 0, TOOL_ERASER, 51_608_000_000],
+            &[(X, 121.0), (Y, 211.0), (Pressure, 0.4)]),
+        (&[3, SOURCE_STYLUS, 1, 0, 0, 0, 0, 51_608, 1, 0, 0, TOOL_ERASER, 51_616_000_000],
+            &[(X, 121.0), (Y, 212.0)]),
+    ];
+
+    /// The tool changes while the pen is touching the screen.
+    #[rustfmt::skip]
+    const SWITCH_MID_STROKE: &[Packed] = &[
+        (&[3, SOURCE_STYLUS, 0, 0, 0, 0, 0, 60_000, 1, 0, 0, TOOL_STYLUS, 60_000_000_000],
+            &[(X, 10.0), (Y, 10.0), (Pressure, 0.5)]),
+        (&[3, SOURCE_STYLUS, 2, 0, 0, 0, 0, 60_000, 1, 0, 0, TOOL_STYLUS, 60_008_000_000],
+            &[(X, 20.0), (Y, 10.0), (Pressure, 0.5)]),
+        (&[3, SOURCE_STYLUS, 2, 0, 0, 0, 0, 60_000, 1, 0, 0, TOOL_ERASER, 60_016_000_000],
+            &[(X, 30.0), (Y, 10.0), (Pressure, 0.5)]),
+        (&[3, SOURCE_STYLUS, 2, 0, 0, 0, 0, 60_000, 1, 0, 0, TOOL_ERASER, 60_024_000_000],
+            &[(X, 40.0), (Y, 10.0), (Pressure, 0.5)]),
+        (&[3, SOURCE_STYLUS, 1, 0, 0, 0, 0, 60_000, 1, 0, 0, TOOL_ERASER, 60_032_000_000],
+            &[(X, 40.0), (Y, 10.0)]),
+    ];
+
+    /// The parts of a [`PenEvent`] that the fixtures check.
+    #[derive(Debug, PartialEq)]
+    struct Summary {
+        kind: &'static str,
+        button: Option<PointerButton>,
+        buttons: PointerButtons,
+        tool: PenTool,
+        in_contact: bool,
+        distance: f32,
+    }
+
+    fn summary(
+        kind: &'static str,
+        button: Option<PointerButton>,
+        buttons: &[PointerButton],
+        tool: PenTool,
+        in_contact: bool,
+        distance: f32,
+    ) -> Summary {
+        let mut set = PointerButtons::default();
+        for &b in buttons {
+            set |= b;
+        }
+        Summary {
+            kind,
+            button,
+            buttons: set,
+            tool,
+            in_contact,
+            distance,
+        }
+    }
+
+    fn summarize(e: &PenEvent) -> Summary {
+        let (kind, button, buttons) = match &e.event {
+            PointerEvent::Down { button, state, .. } => ("down", *button, state.buttons),
+            PointerEvent::Up { button, state, .. } => ("up", *button, state.buttons),
+            PointerEvent::Move(update) => ("move", None, update.current.buttons),
+            PointerEvent::Cancel(_) => ("cancel", None, PointerButtons::default()),
+            PointerEvent::Enter(_) => ("enter", None, PointerButtons::default()),
+            PointerEvent::Leave(_) => ("leave", None, PointerButtons::default()),
+            PointerEvent::Scroll { state, .. } => ("scroll", None, state.buttons),
+        };
+        let pen = e.pen.expect("pen data");
+        Summary {
+            kind,
+            button,
+            buttons,
+            tool: pen.tool,
+            in_contact: pen.in_contact,
+            distance: pen.distance,
+        }
+    }
+
+    fn run(recording: &[Packed]) -> Vec<Summary> {
+        let vc = ViewConfiguration::default();
+        let mut tracker = PenTracker::new();
+        recording
+            .iter()
+            .flat_map(|packed| tracker.to_pen_events(&unpack(packed), &vc))
+            .map(|e| summarize(&e))
+            .collect()
+    }
+
+    use PenTool::{Eraser as EraserEnd, Tip};
+    use PointerButton::{PenEraser, Primary, Secondary};
+
+    #[test]
+    fn stroke_and_erase() {
+        assert_eq!(
+            run(STROKE_AND_ERASE),
+            [
+                summary("enter", None, &[], Tip, false, 12.0),
+                summary("move", None, &[], Tip, false, 6.0),
+                summary("leave", None, &[], Tip, false, 0.0),
+                summary("down", Some(Primary), &[Primary], Tip, true, 0.0),
+                summary("move", None, &[Primary], Tip, true, 0.0),
+                // The barrel button is a secondary button while drawing.
+                summary("move", None, &[Primary, Secondary], Tip, true, 0.0),
+                summary("move", None, &[Primary, Secondary], Tip, true, 0.0),
+                summary("move", None, &[Primary], Tip, true, 0.0),
+                summary("up", Some(Primary), &[], Tip, false, 0.0),
+                summary("enter", None, &[], Tip, false, 4.0),
+                // Flipping the pen while hovering.
+                summary("leave", None, &[], Tip, false, 4.0),
+                summary("enter", None, &[], EraserEnd, false, 5.0),
+                summary("move", None, &[], EraserEnd, false, 5.0),
+                summary("leave", None, &[], EraserEnd, false, 0.0),
+                summary("down", Some(PenEraser), &[PenEraser], EraserEnd, true, 0.0),
+                summary("up", Some(PenEraser), &[], EraserEnd, false, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn switch_mid_stroke() {
+        assert_eq!(
+            run(SWITCH_MID_STROKE),
+            [
+                summary("down", Some(Primary), &[Primary], Tip, true, 0.0),
+                summary("move", None, &[Primary], Tip, true, 0.0),
+                summary("cancel", None, &[], Tip, true, 0.0),
+                summary("down", Some(PenEraser), &[PenEraser], EraserEnd, true, 0.0),
+                summary("move", None, &[PenEraser], EraserEnd, true, 0.0),
+                summary("up", Some(PenEraser), &[], EraserEnd, false, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn hover_distance_on_move() {
+        let vc = ViewConfiguration::default();
+        let event = unpack(&STROKE_AND_ERASE[1]);
+        let events = PenTracker::new().to_pen_events(&event, &vc);
+        let [PenEvent { event, pen }] = &events[..] else {
+            panic!("{events:?}");
+        };
+        let PointerEvent::Move(update) = event else {
+            panic!("{event:?}");
+        };
+        assert_eq!(update.pointer.pointer_type, PointerType::Pen);
+        assert_eq!(update.current.position, PhysicalPosition::new(102.0, 201.0));
+        assert_eq!(update.current.time, 51_208_000_000);
+        assert_eq!(pen.unwrap().distance, 6.0);
+    }
+
+    #[test]
+    fn tangential_pressure_from_vendor_axis() {
+        let vc = ViewConfiguration::default();
+        let mut event = unpack(&STROKE_AND_ERASE[4]);
+        event.axis_values[i32::from(Axis::Generic1) as usize] = 0.25;
+        let pressure = |tracker: &mut PenTracker| match &tracker.to_pen_events(&event, &vc)[..] {
+            [
+                PenEvent {
+                    event: PointerEvent::Move(update),
+                    ..
+                },
+            ] => update.current.tangential_pressure,
+            events => panic!("{events:?}"),
+        };
+        assert_eq!(pressure(&mut PenTracker::new()), 0.0);
+        assert_eq!(
+            pressure(&mut PenTracker::with_tangential_pressure_axis(
+                Axis::Generic1
+            )),
+            0.25
+        );
+    }
+
+    #[test]
+    fn non_pen_has_no_pen_data() {
+        let vc = ViewConfiguration::default();
+        let event = touch(action(Down, 0), &[0], &[(0, &[(1.0, 1.0)])]);
+        let events = PenTracker::new().to_pen_events(&event, &vc);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].pen, None);
+    }
+}
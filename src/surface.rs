@@ -1,12 +1,21 @@
 use jni::{JNIEnv, objects::JObject};
 use ndk::native_window::NativeWindow;
 
+use crate::error::*;
+
 #[repr(transparent)]
 pub struct Surface<'local>(pub JObject<'local>);
 
 impl<'local> Surface<'local> {
     pub fn to_native_window(&self, env: &mut JNIEnv<'local>) -> NativeWindow {
-        unsafe { NativeWindow::from_surface(env.get_raw(), self.0.as_raw()) }.unwrap()
+        self.try_to_native_window(env).unwrap()
+    }
+
+    pub fn try_to_native_window(&self, env: &mut JNIEnv<'local>) -> Result<NativeWindow, Error> {
+        let window = unsafe { NativeWindow::from_surface(env.get_raw(), self.0.as_raw()) };
+        window.ok_or(Error::Jni(jni::errors::Error::NullPtr(
+            "ANativeWindow_fromSurface",
+        )))
     }
 }
 
@@ -15,11 +24,15 @@ pub struct SurfaceHolder<'local>(pub JObject<'local>);
 
 impl<'local> SurfaceHolder<'local> {
     pub fn surface(&self, env: &mut JNIEnv<'local>) -> Surface<'local> {
-        Surface(
-            env.call_method(&self.0, "getSurface", "()Landroid/view/Surface;", &[])
-                .unwrap()
-                .l()
-                .unwrap(),
-        )
+        self.try_surface(env).unwrap()
+    }
+
+    pub fn try_surface(&self, env: &mut JNIEnv<'local>) -> Result<Surface<'local>, Error> {
+        catch(env, |env| {
+            Ok(Surface(
+                env.call_method(&self.0, "getSurface", "()Landroid/view/Surface;", &[])?
+                    .l()?,
+            ))
+        })
     }
 }
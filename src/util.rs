@@ -1,5 +1,35 @@
-use jni::sys::{JNI_FALSE, JNI_TRUE, jboolean};
+use jni::{
+    JNIEnv,
+    sys::{JNI_FALSE, JNI_TRUE, jboolean, jint},
+};
+use std::sync::OnceLock;
+
+use crate::error::catch;
 
 pub(crate) fn as_jboolean(flag: bool) -> jboolean {
     if flag { JNI_TRUE } else { JNI_FALSE }
 }
+
+/// The value of `Build.VERSION.SDK_INT`, or 0 if it can't be read.
+pub(crate) fn sdk_int(env: &mut JNIEnv) -> jint {
+    static SDK_INT: OnceLock<jint> = OnceLock::new();
+    *SDK_INT.get_or_init(|| {
+        catch(env, |env| {
+            env.get_static_field("android/os/Build$VERSION", "SDK_INT", "I")?
+                .i()
+        })
+        .unwrap_or(0)
+    })
+}
+
+/// Nanoseconds on `CLOCK_MONOTONIC`, the time base of input events and
+/// `SystemClock.uptimeNanos`.
+pub(crate) fn uptime_nanos() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid, writable `timespec`.
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
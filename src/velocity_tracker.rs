@@ -0,0 +1,258 @@
+//! Pointer velocity estimation, following Android's default `VelocityTracker`
+//! strategy: an unweighted quadratic least-squares fit (LSQ2).
+
+use std::collections::{BTreeMap, VecDeque};
+use ui_events::pointer::{PointerEvent, PointerInfo, PointerState};
+
+/// Maximum number of samples kept for each pointer.
+const HISTORY_SIZE: usize = 20;
+
+/// Samples older than this, relative to the newest, are not used.
+const HORIZON_NANOS: u64 = 100_000_000;
+
+/// A pointer that doesn't move for this long is considered to have stopped.
+const ASSUME_POINTER_STOPPED_NANOS: u64 = 40_000_000;
+
+/// A velocity in pixels per second.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Velocity {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Velocity {
+    pub fn magnitude(&self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
+    /// Limit each component to `-max..=max`, as Android does with
+    /// `ViewConfiguration.getScaledMaximumFlingVelocity`.
+    pub fn clamp(self, max: f64) -> Self {
+        Self {
+            x: self.x.clamp(-max, max),
+            y: self.y.clamp(-max, max),
+        }
+    }
+}
+
+/// Tracks the velocity of every pointer from a stream of [`PointerEvent`]s.
+#[derive(Clone, Debug, Default)]
+pub struct VelocityTracker {
+    pointers: BTreeMap<PointerInfo, VecDeque<(u64, f64, f64)>>,
+}
+
+impl VelocityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget all pointers.
+    pub fn clear(&mut self) {
+        self.pointers.clear();
+    }
+
+    /// Add the samples in `event`, including any coalesced ones.
+    pub fn add_event(&mut self, event: &PointerEvent) {
+        match event {
+            PointerEvent::Down { pointer, state, .. } => {
+                let history = self.pointers.entry(*pointer).or_default();
+                history.clear();
+                push_sample(history, state);
+            }
+            PointerEvent::Move(update) => {
+                let history = self.pointers.entry(update.pointer).or_default();
+                for state in update.coalesced.iter().chain([&update.current]) {
+                    push_sample(history, state);
+                }
+            }
+            PointerEvent::Up { pointer, state, .. } => {
+                // The up event repeats the last position, so it only matters
+                // for deciding whether the pointer stopped before lifting.
+                if let Some(history) = self.pointers.get_mut(pointer)
+                    && history
+                        .back()
+                        .is_some_and(|&(t, ..)| state.time > t + ASSUME_POINTER_STOPPED_NANOS)
+                {
+                    history.clear();
+                }
+            }
+            PointerEvent::Cancel(pointer) | PointerEvent::Leave(pointer) => {
+                self.pointers.remove(pointer);
+            }
+            PointerEvent::Enter(..) | PointerEvent::Scroll { .. } => {}
+        }
+    }
+
+    /// The current velocity of `pointer`, or `None` if it is not being tracked.
+    pub fn velocity(&self, pointer: &PointerInfo) -> Option<Velocity> {
+        let history = self.pointers.get(pointer)?;
+        let Some(&(newest, ..)) = history.back() else {
+            return Some(Velocity::default());
+        };
+        // Ages in seconds relative to the newest sample, so that the
+        // linear coefficient of the fit is the current velocity.
+        let samples: Vec<_> = history
+            .iter()
+            .filter(|&&(t, ..)| newest - t <= HORIZON_NANOS)
+            .map(|&(t, x, y)| (-((newest - t) as f64) * 1e-9, x, y))
+            .collect();
+        Some(Velocity {
+            x: fit_velocity(samples.iter().map(|&(t, x, _)| (t, x))),
+            y: fit_velocity(samples.iter().map(|&(t, _, y)| (t, y))),
+        })
+    }
+}
+
+fn push_sample(history: &mut VecDeque<(u64, f64, f64)>, state: &PointerState) {
+    if let Some(&(t, ..)) = history.back() {
+        if state.time <= t {
+            return;
+        }
+        if state.time - t > ASSUME_POINTER_STOPPED_NANOS {
+            history.clear();
+        }
+    }
+    if history.len() == HISTORY_SIZE {
+        history.pop_front();
+    }
+    history.push_back((state.time, state.position.x, state.position.y));
+}
+
+/// Slope at `t = 0` of a least-squares fit through `samples`, using a quadratic
+/// when there are enough samples and falling back to a line.
+fn fit_velocity(samples: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+    let n = samples.clone().count() as f64;
+    if n < 2.0 {
+        return 0.0;
+    }
+    let (mut sx, mut sy, mut sxy, mut sx2, mut sx3, mut sx2y, mut sx4) =
+        (0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for (x, y) in samples {
+        let x2 = x * x;
+        sx += x;
+        sy += y;
+        sxy += x * y;
+        sx2 += x2;
+        sx3 += x2 * x;
+        sx2y += x2 * y;
+        sx4 += x2 * x2;
+    }
+    let sxx = sx2 - sx * sx / n;
+    let sxy = sxy - sx * sy / n;
+    if n >= 3.0 {
+        let sxx2 = sx3 - sx * sx2 / n;
+        let sx2y = sx2y - sx2 * sy / n;
+        let sx2x2 = sx4 - sx2 * sx2 / n;
+        let denominator = sxx * sx2x2 - sxx2 * sxx2;
+        if denominator != 0.0 {
+            return (sxy * sx2x2 - sx2y * sxx2) / denominator;
+        }
+    }
+    if sxx == 0.0 { 0.0 } else { sxy / sxx }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dpi::PhysicalPosition;
+    use ui_events::pointer::{PointerId, PointerType, PointerUpdate};
+
+    const POINTER: PointerInfo = PointerInfo {
+        pointer_id: Some(PointerId::PRIMARY),
+        persistent_device_id: None,
+        pointer_type: PointerType::Touch,
+    };
+
+    fn state(time_millis: f64, x: f64, y: f64) -> PointerState {
+        PointerState {
+            time: (time_millis * 1e6) as u64,
+            position: PhysicalPosition { x, y },
+            ..Default::default()
+        }
+    }
+
+    /// Track a stroke sampled every 8 ms with `position` giving the
+    /// position at each time in seconds.
+    fn track(samples: usize, position: impl Fn(f64) -> (f64, f64)) -> VelocityTracker {
+        let mut tracker = VelocityTracker::new();
+        for i in 0..samples {
+            let t = i as f64 * 8.0;
+            let (x, y) = position(t * 1e-3);
+            let state = state(t, x, y);
+            tracker.add_event(&if i == 0 {
+                PointerEvent::Down {
+                    pointer: POINTER,
+                    button: None,
+                    state,
+                }
+            } else {
+                PointerEvent::Move(PointerUpdate {
+                    pointer: POINTER,
+                    current: state,
+                    coalesced: vec![],
+                    predicted: vec![],
+                })
+            });
+        }
+        tracker
+    }
+
+    fn assert_velocity(velocity: Velocity, x: f64, y: f64) {
+        assert!(
+            (velocity.x - x).abs() < 1e-3 && (velocity.y - y).abs() < 1e-3,
+            "{velocity:?} != ({x}, {y})"
+        );
+    }
+
+    #[test]
+    fn constant_velocity() {
+        let tracker = track(10, |t| (100.0 + 1000.0 * t, 50.0 - 500.0 * t));
+        assert_velocity(tracker.velocity(&POINTER).unwrap(), 1000.0, -500.0);
+    }
+
+    #[test]
+    fn constant_acceleration() {
+        // LSQ2 fits a quadratic exactly, so this is the velocity at the
+        // last sample: 400 + 2 * 3000 * 0.072.
+        let tracker = track(10, |t| (400.0 * t + 3000.0 * t * t, 0.0));
+        assert_velocity(tracker.velocity(&POINTER).unwrap(), 832.0, 0.0);
+    }
+
+    #[test]
+    fn uses_recent_samples() {
+        // A direction change more than 100 ms before the last sample is
+        // outside the horizon.
+        let tracker = track(20, |t| {
+            if t < 0.04 {
+                (-2000.0 * t, 0.0)
+            } else {
+                (-80.0 + 1000.0 * (t - 0.04), 0.0)
+            }
+        });
+        assert_velocity(tracker.velocity(&POINTER).unwrap(), 1000.0, 0.0);
+    }
+
+    #[test]
+    fn single_sample_is_still() {
+        let tracker = track(1, |_| (10.0, 10.0));
+        assert_eq!(tracker.velocity(&POINTER), Some(Velocity::default()));
+    }
+
+    #[test]
+    fn pause_before_lift_stops() {
+        let mut tracker = track(10, |t| (1000.0 * t, 0.0));
+        tracker.add_event(&PointerEvent::Up {
+            pointer: POINTER,
+            button: None,
+            state: state(72.0 + 50.0, 72.0, 0.0),
+        });
+        assert_eq!(tracker.velocity(&POINTER), Some(Velocity::default()));
+    }
+
+    #[test]
+    fn cancel_forgets_pointer() {
+        let mut tracker = track(10, |t| (1000.0 * t, 0.0));
+        tracker.add_event(&PointerEvent::Cancel(POINTER));
+        assert_eq!(tracker.velocity(&POINTER), None);
+    }
+}
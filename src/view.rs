@@ -1,105 +1,313 @@
 use jni::{
-    JNIEnv, NativeMethod,
+    JNIEnv, JavaVM, NativeMethod,
     descriptors::Desc,
-    objects::{JClass, JIntArray, JObject},
+    objects::{GlobalRef, JClass, JIntArray, JObject, WeakRef},
     sys::{JNI_TRUE, jboolean, jint, jlong},
 };
 use ndk::event::Keycode;
 use num_enum::FromPrimitive;
 use send_wrapper::SendWrapper;
 use std::{
-    cell::RefCell,
+    any::Any,
+    cell::{Cell, OnceCell, RefCell},
     collections::BTreeMap,
     ffi::c_void,
+    marker::PhantomData,
+    mem,
+    ops::{BitOr, BitOrAssign},
     rc::Rc,
     sync::{
-        Mutex, Once,
+        Arc, Mutex, MutexGuard, PoisonError,
         atomic::{AtomicI64, Ordering},
     },
 };
 
+#[cfg(feature = "async")]
+use crate::executor::Executor;
 use crate::{
-    accessibility::*, binder::*, callback_ctx::*, context::*, events::*, graphics::*, ime::*,
-    surface::*, util::*, view_configuration::*,
+    accessibility::*, binder::*, callback_ctx::*, choreographer::*, configuration::*, context::*,
+    display::*, error::*, events::*, frame::*, graphics::*, guard::*, ime::*, surface::*, util::*,
+    view_configuration::*, window_insets::*,
 };
 
+/// Identifies a timer started with [`View::start_timer`].
+///
+/// Tokens are unique within the process, so a peer can tell its own timers
+/// apart from those started by libraries it uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerToken(jlong);
+
+impl TimerToken {
+    /// Allocate a token that has not been used before.
+    pub fn next() -> Self {
+        static NEXT_TOKEN: AtomicI64 = AtomicI64::new(1);
+        Self(NEXT_TOKEN.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 #[repr(transparent)]
 pub struct View<'local>(pub JObject<'local>);
 
 impl<'local> View<'local> {
     pub fn post_frame_callback(&self, env: &mut JNIEnv<'local>) {
-        env.call_method(&self.0, "postFrameCallback", "()V", &[])
-            .unwrap()
-            .v()
-            .unwrap()
+        self.try_post_frame_callback(env).unwrap()
+    }
+
+    pub fn try_post_frame_callback(&self, env: &mut JNIEnv<'local>) -> Result<(), Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "postFrameCallback", "()V", &[])?
+                .v()
+        })
     }
 
     pub fn remove_frame_callback(&self, env: &mut JNIEnv<'local>) {
-        env.call_method(&self.0, "removeFrameCallback", "()V", &[])
+        self.try_remove_frame_callback(env).unwrap()
+    }
+
+    pub fn try_remove_frame_callback(&self, env: &mut JNIEnv<'local>) -> Result<(), Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "removeFrameCallback", "()V", &[])?
+                .v()
+        })
+    }
+
+    /// Call [`ViewPeer::on_timer`] with a new token after `delay_millis`.
+    pub fn start_timer(&self, env: &mut JNIEnv<'local>, delay_millis: jlong) -> TimerToken {
+        self.try_start_timer(env, delay_millis).unwrap()
+    }
+
+    pub fn try_start_timer(
+        &self,
+        env: &mut JNIEnv<'local>,
+        delay_millis: jlong,
+    ) -> Result<TimerToken, Error> {
+        let token = TimerToken::next();
+        self.try_start_timer_with_token(env, token, delay_millis)?;
+        Ok(token)
+    }
+
+    /// Call [`ViewPeer::on_timer`] with `token` after `delay_millis`,
+    /// replacing any timer already running with that token.
+    pub fn start_timer_with_token(
+        &self,
+        env: &mut JNIEnv<'local>,
+        token: TimerToken,
+        delay_millis: jlong,
+    ) {
+        self.try_start_timer_with_token(env, token, delay_millis)
             .unwrap()
+    }
+
+    pub fn try_start_timer_with_token(
+        &self,
+        env: &mut JNIEnv<'local>,
+        token: TimerToken,
+        delay_millis: jlong,
+    ) -> Result<(), Error> {
+        catch(env, |env| {
+            env.call_method(
+                &self.0,
+                "startTimer",
+                "(JJ)V",
+                &[token.0.into(), delay_millis.into()],
+            )?
             .v()
-            .unwrap()
+        })
     }
 
-    pub fn post_delayed(&self, env: &mut JNIEnv<'local>, delay_millis: jlong) -> bool {
-        env.call_method(&self.0, "postDelayed", "(J)Z", &[delay_millis.into()])
-            .unwrap()
-            .z()
-            .unwrap()
+    /// Returns `false` if the timer has already fired or been cancelled.
+    pub fn cancel_timer(&self, env: &mut JNIEnv<'local>, token: TimerToken) -> bool {
+        self.try_cancel_timer(env, token).unwrap()
     }
 
-    pub fn remove_delayed_callbacks(&self, env: &mut JNIEnv<'local>) -> bool {
-        env.call_method(&self.0, "removeDelayedCallbacks", "()Z", &[])
-            .unwrap()
-            .z()
-            .unwrap()
+    pub fn try_cancel_timer(
+        &self,
+        env: &mut JNIEnv<'local>,
+        token: TimerToken,
+    ) -> Result<bool, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "cancelTimer", "(J)Z", &[token.0.into()])?
+                .z()
+        })
+    }
+
+    pub fn cancel_all_timers(&self, env: &mut JNIEnv<'local>) {
+        self.try_cancel_all_timers(env).unwrap()
+    }
+
+    pub fn try_cancel_all_timers(&self, env: &mut JNIEnv<'local>) -> Result<(), Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "cancelAllTimers", "()V", &[])?.v()
+        })
+    }
+
+    /// The ID of the peer registered for this view.
+    ///
+    /// [`PeerHandle::from_id`] turns this into a handle to the peer.
+    pub fn peer_id(&self, env: &mut JNIEnv<'local>) -> jlong {
+        self.try_peer_id(env).unwrap()
+    }
+
+    pub fn try_peer_id(&self, env: &mut JNIEnv<'local>) -> Result<jlong, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "getViewPeer", "()J", &[])?.j()
+        })
     }
 
     pub fn is_focused(&self, env: &mut JNIEnv<'local>) -> bool {
-        env.call_method(&self.0, "isFocused", "()Z", &[])
-            .unwrap()
-            .z()
-            .unwrap()
+        self.try_is_focused(env).unwrap()
+    }
+
+    pub fn try_is_focused(&self, env: &mut JNIEnv<'local>) -> Result<bool, Error> {
+        catch(env, |env| {
+            env.call_method(&self.0, "isFocused", "()Z", &[])?.z()
+        })
     }
 
     pub fn input_method_manager(&self, env: &mut JNIEnv<'local>) -> InputMethodManager<'local> {
-        InputMethodManager(
-            env.get_field(
-                &self.0,
-                "mInputMethodManager",
-                "Landroid/view/inputmethod/InputMethodManager;",
-            )
-            .unwrap()
-            .l()
-            .unwrap(),
-        )
+        self.try_input_method_manager(env).unwrap()
+    }
+
+    pub fn try_input_method_manager(
+        &self,
+        env: &mut JNIEnv<'local>,
+    ) -> Result<InputMethodManager<'local>, Error> {
+        catch(env, |env| {
+            Ok(InputMethodManager(
+                env.get_field(
+                    &self.0,
+                    "mInputMethodManager",
+                    "Landroid/view/inputmethod/InputMethodManager;",
+                )?
+                .l()?,
+            ))
+        })
     }
 
     pub fn context(&self, env: &mut JNIEnv<'local>) -> Context<'local> {
-        Context(
-            env.call_method(&self.0, "getContext", "()Landroid/content/Context;", &[])
-                .unwrap()
-                .l()
-                .unwrap(),
-        )
+        self.try_context(env).unwrap()
+    }
+
+    pub fn try_context(&self, env: &mut JNIEnv<'local>) -> Result<Context<'local>, Error> {
+        catch(env, |env| {
+            Ok(Context(
+                env.call_method(&self.0, "getContext", "()Landroid/content/Context;", &[])?
+                    .l()?,
+            ))
+        })
+    }
+
+    /// The display the view's window is on, or `None` if it isn't attached.
+    pub fn display(&self, env: &mut JNIEnv<'local>) -> Option<Display<'local>> {
+        self.try_display(env).unwrap()
+    }
+
+    pub fn try_display(&self, env: &mut JNIEnv<'local>) -> Result<Option<Display<'local>>, Error> {
+        catch(env, |env| {
+            let display = env
+                .call_method(&self.0, "getDisplay", "()Landroid/view/Display;", &[])?
+                .l()?;
+            Ok((!display.is_null()).then_some(Display(display)))
+        })
     }
 
     pub fn view_configuration(&self, env: &mut JNIEnv<'local>) -> ViewConfiguration {
         ViewConfiguration::new(&self.0, env)
     }
 
+    pub fn try_view_configuration(
+        &self,
+        env: &mut JNIEnv<'local>,
+    ) -> Result<ViewConfiguration, Error> {
+        ViewConfiguration::try_new(&self.0, env)
+    }
+
     pub fn window_token(&self, env: &mut JNIEnv<'local>) -> IBinder<'local> {
-        IBinder(
-            env.call_method(&self.0, "getWindowToken", "()Landroid/os/IBinder;", &[])
-                .unwrap()
-                .l()
-                .unwrap(),
-        )
+        self.try_window_token(env).unwrap()
+    }
+
+    pub fn try_window_token(&self, env: &mut JNIEnv<'local>) -> Result<IBinder<'local>, Error> {
+        catch(env, |env| {
+            Ok(IBinder(
+                env.call_method(&self.0, "getWindowToken", "()Landroid/os/IBinder;", &[])?
+                    .l()?,
+            ))
+        })
+    }
+}
+
+/// The [`ViewPeer`] callbacks that `RustView` forwards to the peer. Events
+/// for the others go straight to the `SurfaceView` implementation without
+/// calling into native code.
+///
+/// Frame, timer, detach, accessibility and input method callbacks are
+/// always forwarded. Configuration, display and window visibility changes
+/// always reach native code, to refresh [`CallbackCtx::display_metrics`]
+/// and to hold back frames while the window is hidden, but are only passed
+/// on to peers that handle them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ViewCallbacks(u32);
+
+include!(concat!(env!("OUT_DIR"), "/callbacks.rs"));
+
+impl ViewCallbacks {
+    /// The callbacks that this crate needs whether or not the peer handles
+    /// them.
+    const REQUIRED: Self = Self(
+        Self::CONFIGURATION_CHANGED.0 | Self::DISPLAY_CHANGED.0 | Self::WINDOW_VISIBILITY_CHANGED.0,
+    );
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn all() -> Self {
+        Self(Self::ALL_BITS)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for ViewCallbacks {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl BitOr for ViewCallbacks {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for ViewCallbacks {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
     }
 }
 
 #[allow(unused_variables)]
-pub trait ViewPeer {
+pub trait ViewPeer: Any {
+    /// The callbacks this peer implements, read once when the view is
+    /// created. Defaults to all of them.
+    fn callbacks(&self) -> ViewCallbacks {
+        ViewCallbacks::all()
+    }
+
+    /// Where [`do_frame`](Self::do_frame) calls come from, read once when
+    /// the peer is registered. Defaults to [`FrameSource::Java`].
+    fn frame_source(&self) -> FrameSource {
+        FrameSource::Java
+    }
+
     fn on_measure(
         &mut self,
         ctx: &mut CallbackCtx,
@@ -123,6 +331,34 @@ pub trait ViewPeer {
     fn on_size_changed(&mut self, ctx: &mut CallbackCtx, w: jint, h: jint, oldw: jint, oldh: jint) {
     }
 
+    /// Called from `onApplyWindowInsets`. Return the insets consumed from
+    /// each edge, or `None` to leave them to the `SurfaceView`
+    /// implementation.
+    fn on_apply_window_insets(
+        &mut self,
+        ctx: &mut CallbackCtx,
+        insets: &WindowInsets,
+    ) -> Option<Insets> {
+        None
+    }
+
+    /// Called as the IME insets animate, from API 30.
+    fn on_ime_animation(&mut self, ctx: &mut CallbackCtx, animation: ImeAnimation) {}
+
+    /// Called from `onConfigurationChanged`, for the changes the activity
+    /// handles itself through `android:configChanges`.
+    fn on_configuration_changed(&mut self, ctx: &mut CallbackCtx, config: &Configuration) {}
+
+    /// Called when the view's window moves to another display, such as
+    /// when a foldable is folded or the activity is moved to an external
+    /// monitor.
+    fn on_display_changed<'local>(
+        &mut self,
+        ctx: &mut CallbackCtx<'local>,
+        display: &Display<'local>,
+    ) {
+    }
+
     fn on_key_down<'local>(
         &mut self,
         ctx: &mut CallbackCtx<'local>,
@@ -214,9 +450,9 @@ pub trait ViewPeer {
     ) {
     }
 
-    fn do_frame(&mut self, ctx: &mut CallbackCtx, frame_time_nanos: jlong) {}
+    fn do_frame(&mut self, ctx: &mut CallbackCtx, frame: &FrameInfo) {}
 
-    fn delayed_callback(&mut self, ctx: &mut CallbackCtx) {}
+    fn on_timer(&mut self, ctx: &mut CallbackCtx, token: TimerToken) {}
 
     fn as_accessibility_node_provider(&mut self) -> Option<&mut dyn AccessibilityNodeProvider> {
         None
@@ -227,11 +463,86 @@ pub trait ViewPeer {
     }
 }
 
+type PostedCallback = Box<dyn FnOnce(&mut CallbackCtx, &mut dyn ViewPeer) + Send>;
+
+/// Closures posted through [`ViewHandle`]s, or `None` once the peer has
+/// been removed.
+type PostedQueue = Arc<Mutex<Option<Vec<PostedCallback>>>>;
+
+/// A registered peer, along with the state this crate tracks for it.
+struct PeerEntry {
+    peer: RefCell<Box<dyn ViewPeer>>,
+    /// Set when a callback on this peer panicked. A poisoned peer
+    /// receives no further callbacks.
+    poisoned: Cell<bool>,
+    posted: PostedQueue,
+    /// Set if the peer uses [`FrameSource::Native`] and it is available.
+    native_frames: Option<Rc<NativeFrames>>,
+    frame_scheduler: Rc<FrameScheduler>,
+    /// The callbacks the peer handles.
+    callbacks: ViewCallbacks,
+    display_metrics: Rc<Cell<Option<DisplayMetrics>>>,
+    /// The `EventSnapshots` class of the view's base class, found on the
+    /// first callback.
+    event_snapshots: OnceCell<Option<GlobalRef>>,
+    #[cfg(feature = "async")]
+    executor: Rc<Executor>,
+}
+
+impl PeerEntry {
+    fn callback_ctx<'local>(
+        &self,
+        env: JNIEnv<'local>,
+        view: View<'local>,
+        id: jlong,
+    ) -> CallbackCtx<'local> {
+        let mut ctx = CallbackCtx::new(env, view, id);
+        ctx.native_frames = self.native_frames.clone();
+        ctx.frame_scheduler = Some(Rc::clone(&self.frame_scheduler));
+        ctx.callbacks = self.callbacks;
+        ctx.display_metrics_cache = Some(Rc::clone(&self.display_metrics));
+        #[cfg(feature = "async")]
+        {
+            ctx.executor = Some(Rc::clone(&self.executor));
+        }
+        ctx
+    }
+
+    /// Run `f` with panics converted to a Java `RuntimeException`,
+    /// poisoning the peer if one occurs.
+    fn guard<'local, T: Default>(
+        &self,
+        env: JNIEnv<'local>,
+        f: impl FnOnce(JNIEnv<'local>) -> T,
+    ) -> T {
+        catch_panic(env, f).unwrap_or_else(|| {
+            self.poisoned.set(true);
+            T::default()
+        })
+    }
+}
+
+type PeerMap = BTreeMap<jlong, SendWrapper<Rc<PeerEntry>>>;
+
 static NEXT_PEER_ID: AtomicI64 = AtomicI64::new(0);
-static PEER_MAP: Mutex<BTreeMap<jlong, SendWrapper<Rc<RefCell<Box<dyn ViewPeer>>>>>> =
-    Mutex::new(BTreeMap::new());
+static PEER_MAP: Mutex<PeerMap> = Mutex::new(BTreeMap::new());
+
+/// Lock the peer map. This is done outside `catch_panic`, so a poisoned
+/// lock is ignored rather than unwinding into Java; the map is never left
+/// half-updated.
+fn peer_map() -> MutexGuard<'static, PeerMap> {
+    PEER_MAP.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn peer_entry(id: jlong) -> Option<Rc<PeerEntry>> {
+    peer_map().get(&id).map(|entry| Rc::clone(entry))
+}
 
-pub(crate) fn with_peer<'local, F, T: Default>(
+pub(crate) fn native_frames(id: jlong) -> Option<Rc<NativeFrames>> {
+    peer_entry(id)?.native_frames.clone()
+}
+
+pub(crate) fn with_peer<'local, F, T: Default + CallbackResult<'local>>(
     env: JNIEnv<'local>,
     view: View<'local>,
     id: jlong,
@@ -240,18 +551,37 @@ pub(crate) fn with_peer<'local, F, T: Default>(
 where
     F: FnOnce(&mut CallbackCtx<'local>, &mut dyn ViewPeer) -> T,
 {
-    let map = PEER_MAP.lock().unwrap();
-    let Some(peer) = map.get(&id) else {
+    let Some(entry) = peer_entry(id) else {
         return T::default();
     };
-    let peer = Rc::clone(&**peer);
-    drop(map);
-    let mut peer = peer.borrow_mut();
-    let mut ctx = CallbackCtx::new(env, view);
-    let result = f(&mut ctx, &mut **peer);
-    drop(peer);
-    ctx.finish();
-    result
+    if entry.poisoned.get() {
+        return T::default();
+    }
+    entry.guard(env, |mut env| {
+        let snapshots = entry
+            .event_snapshots
+            .get_or_init(|| event_snapshots_class_of(&mut env, &view))
+            .clone();
+        let _scope = EventSnapshotsScope::enter(snapshots);
+        let mut peer = entry.peer.borrow_mut();
+        let mut ctx = entry.callback_ctx(env, view, id);
+        #[cfg(feature = "debug-local-refs")]
+        {
+            ctx.callback = std::any::type_name::<F>();
+        }
+        let result = f(&mut ctx, &mut **peer);
+        drop(peer);
+        #[cfg(feature = "async")]
+        entry.executor.run(&mut ctx);
+        entry.frame_scheduler.flush(&mut ctx);
+        ctx.finish(result)
+    })
+}
+
+extern "system" fn callbacks<'local>(env: JNIEnv<'local>, view: View<'local>, peer: jlong) -> jint {
+    with_peer(env, view, peer, |ctx, _| {
+        (ctx.callbacks | ViewCallbacks::REQUIRED).bits() as jint
+    })
 }
 
 extern "system" fn on_measure<'local>(
@@ -415,16 +745,21 @@ extern "system" fn on_detached_from_window<'local>(
     view: View<'local>,
     peer: jlong,
 ) {
-    let mut map = PEER_MAP.lock().unwrap();
-    let peer = map.remove(&peer).unwrap();
-    drop(map);
-    let mut peer = peer.borrow_mut();
-    let mut ctx = CallbackCtx::new(env, view);
-    peer.on_detached_from_window(&mut ctx);
-    drop(peer);
-    ctx.view.remove_frame_callback(&mut ctx.env);
-    ctx.view.remove_delayed_callbacks(&mut ctx.env);
-    ctx.finish();
+    let Some(entry) = peer_map().remove(&peer) else {
+        return;
+    };
+    *entry.posted.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    entry.guard(env, |env| {
+        let mut ctx = entry.callback_ctx(env, view, peer);
+        if !entry.poisoned.get() {
+            entry.peer.borrow_mut().on_detached_from_window(&mut ctx);
+        }
+        #[cfg(feature = "async")]
+        entry.executor.clear();
+        ctx.remove_frame_callback();
+        ctx.view.cancel_all_timers(&mut ctx.env);
+        ctx.finish(());
+    })
 }
 
 extern "system" fn on_window_visibility_changed<'local>(
@@ -434,7 +769,15 @@ extern "system" fn on_window_visibility_changed<'local>(
     visibility: jint,
 ) {
     with_peer(env, view, peer, |ctx, peer| {
-        peer.on_window_visibility_changed(ctx, visibility);
+        if let Some(scheduler) = ctx.frame_scheduler.clone() {
+            scheduler.set_window_visibility(ctx, visibility);
+        }
+        if ctx
+            .callbacks
+            .contains(ViewCallbacks::WINDOW_VISIBILITY_CHANGED)
+        {
+            peer.on_window_visibility_changed(ctx, visibility);
+        }
     })
 }
 
@@ -479,266 +822,422 @@ extern "system" fn do_frame<'local>(
     view: View<'local>,
     peer: jlong,
     frame_time_nanos: jlong,
+    expected_presentation_time_nanos: jlong,
+    deadline_nanos: jlong,
+    frame_interval_nanos: jlong,
+) {
+    let frame = FrameInfo {
+        frame_time_nanos,
+        expected_presentation_time_nanos,
+        deadline_nanos,
+        frame_interval_nanos,
+    };
+    deliver_frame(env, view, peer, frame);
+}
+
+pub(crate) fn deliver_frame<'local>(
+    env: JNIEnv<'local>,
+    view: View<'local>,
+    peer: jlong,
+    frame: FrameInfo,
+) {
+    with_peer(env, view, peer, |ctx, peer| {
+        if let Some(scheduler) = &ctx.frame_scheduler {
+            scheduler.frame_delivered();
+        }
+        #[cfg(feature = "async")]
+        if let Some(executor) = &ctx.executor {
+            executor.frame(frame.frame_time_nanos);
+        }
+        peer.do_frame(ctx, &frame);
+    })
+}
+
+extern "system" fn on_timer<'local>(
+    env: JNIEnv<'local>,
+    view: View<'local>,
+    peer: jlong,
+    token: jlong,
 ) {
     with_peer(env, view, peer, |ctx, peer| {
-        peer.do_frame(ctx, frame_time_nanos);
+        let token = TimerToken(token);
+        #[cfg(feature = "async")]
+        if ctx
+            .executor
+            .as_ref()
+            .is_some_and(|executor| executor.timer_fired(token))
+        {
+            return;
+        }
+        peer.on_timer(ctx, token);
     })
 }
 
-extern "system" fn delayed_callback<'local>(env: JNIEnv<'local>, view: View<'local>, peer: jlong) {
+extern "system" fn run_posted<'local>(env: JNIEnv<'local>, view: View<'local>, peer: jlong) {
+    let Some(posted) = posted_queue(peer) else {
+        return;
+    };
+    let callbacks = posted
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_mut()
+        .map(mem::take)
+        .unwrap_or_default();
     with_peer(env, view, peer, |ctx, peer| {
-        peer.delayed_callback(ctx);
+        for callback in callbacks {
+            callback(ctx, peer);
+        }
     })
 }
 
-pub fn register_view_peer(peer: impl 'static + ViewPeer) -> jlong {
+fn posted_queue(id: jlong) -> Option<PostedQueue> {
+    peer_entry(id).map(|entry| Arc::clone(&entry.posted))
+}
+
+/// A handle for running code on the UI thread of a view from any thread.
+///
+/// Obtained from [`CallbackCtx::view_handle`].
+#[derive(Clone)]
+pub struct ViewHandle {
+    vm: Arc<JavaVM>,
+    view: WeakRef,
+    posted: PostedQueue,
+}
+
+impl ViewHandle {
+    pub(crate) fn try_new<'local>(
+        env: &mut JNIEnv<'local>,
+        view: &View<'local>,
+        peer: jlong,
+    ) -> Result<Self, Error> {
+        let (vm, weak) = catch(env, |env| {
+            let weak = env
+                .new_weak_ref(&view.0)?
+                .ok_or(jni::errors::Error::NullPtr("view"))?;
+            Ok((env.get_java_vm()?, weak))
+        })?;
+        Ok(Self {
+            vm: Arc::new(vm),
+            view: weak,
+            // A peer that has already been removed gets a closed queue.
+            posted: posted_queue(peer).unwrap_or_default(),
+        })
+    }
+
+    /// Run `f` with the peer on the view's UI thread.
+    ///
+    /// `f` is dropped without being run if the peer isn't a `P`, or has been
+    /// removed by the time it would run. Returns `false` if the peer has
+    /// already been removed, or the view has been garbage collected or
+    /// can't be woken, in which case everything still queued is dropped.
+    pub fn post<P: ViewPeer>(
+        &self,
+        f: impl 'static + Send + FnOnce(&mut CallbackCtx, &mut P),
+    ) -> bool {
+        self.try_post(f).unwrap()
+    }
+
+    pub fn try_post<P: ViewPeer>(
+        &self,
+        f: impl 'static + Send + FnOnce(&mut CallbackCtx, &mut P),
+    ) -> Result<bool, Error> {
+        self.try_post_raw(Box::new(move |ctx, peer: &mut dyn ViewPeer| {
+            if let Some(peer) = (peer as &mut dyn Any).downcast_mut::<P>() {
+                f(ctx, peer);
+            }
+        }))
+    }
+
+    pub(crate) fn try_post_raw(&self, f: PostedCallback) -> Result<bool, Error> {
+        let needs_wake = {
+            let mut posted = self.posted.lock().unwrap();
+            let Some(posted) = posted.as_mut() else {
+                return Ok(false);
+            };
+            posted.push(f);
+            // Otherwise a wake-up is already pending and will run this too.
+            posted.len() == 1
+        };
+        if !needs_wake {
+            return Ok(true);
+        }
+        let woken = self
+            .vm
+            .attach_current_thread()
+            .map_err(Error::Jni)
+            .and_then(|mut env| {
+                catch(&mut env, |env| {
+                    let Some(view) = self.view.upgrade_local(env)? else {
+                        return Ok(false);
+                    };
+                    env.call_method(&view, "wakeForPosted", "()Z", &[])?.z()
+                })
+            });
+        if !matches!(woken, Ok(true)) {
+            // Nothing will run the queue, so empty it for the next post to
+            // try again. The callbacks are dropped outside the lock.
+            let _dropped = self
+                .posted
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .as_mut()
+                .map(mem::take);
+        }
+        woken
+    }
+}
+
+/// A registered peer of type `T`.
+///
+/// The handle itself can be sent between threads, but the peer can only
+/// be reached from the UI thread.
+pub struct PeerHandle<T> {
+    id: jlong,
+    _peer: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for PeerHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PeerHandle<T> {}
+
+impl<T: ViewPeer> PeerHandle<T> {
+    /// Get a handle to the peer registered as `id`, if it is a `T`.
+    ///
+    /// Must be called on the UI thread. Returns `None` if the peer is
+    /// borrowed because this is called from one of its own callbacks.
+    pub fn from_id(id: jlong) -> Option<Self> {
+        let entry = peer_entry(id)?;
+        let is_t = (&**entry.peer.try_borrow().ok()? as &dyn Any).is::<T>();
+        is_t.then_some(Self {
+            id,
+            _peer: PhantomData,
+        })
+    }
+
+    /// The ID returned to Java from `newViewPeer`, and available there
+    /// from `RustView.getViewPeer`.
+    pub fn id(&self) -> jlong {
+        self.id
+    }
+
+    /// Run `f` with the peer.
+    ///
+    /// Must be called on the UI thread. Returns `None` if the peer has been
+    /// removed or poisoned, or is already borrowed because this is called
+    /// from one of its own callbacks.
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let entry = peer_entry(self.id)?;
+        if entry.poisoned.get() {
+            return None;
+        }
+        let mut peer = entry.peer.try_borrow_mut().ok()?;
+        (&mut **peer as &mut dyn Any).downcast_mut::<T>().map(f)
+    }
+
+    /// Run `f` with the peer and a [`CallbackCtx`] for `view`, as in a
+    /// callback from Java. A panic in `f` poisons the peer, and is thrown
+    /// as a Java exception when control returns to Java.
+    ///
+    /// Local references created in `f` are deleted when it returns.
+    ///
+    /// Unlike [`with`](Self::with), this must not be called from one of the
+    /// peer's own callbacks: the peer is already borrowed there, so the
+    /// call panics and poisons the peer.
+    pub fn with_ctx<'local, R>(
+        &self,
+        env: JNIEnv<'local>,
+        view: View<'local>,
+        f: impl FnOnce(&mut CallbackCtx, &mut T) -> R,
+    ) -> Option<R> {
+        with_peer(env, view, self.id, |ctx, peer| {
+            (peer as &mut dyn Any)
+                .downcast_mut::<T>()
+                .map(|peer| f(ctx, peer))
+        })
+    }
+}
+
+pub fn register_view_peer<T: ViewPeer>(peer: T) -> PeerHandle<T> {
     let id = NEXT_PEER_ID.fetch_add(1, Ordering::Relaxed);
-    let mut map = PEER_MAP.lock().unwrap();
-    map.insert(id, SendWrapper::new(Rc::new(RefCell::new(Box::new(peer)))));
-    id
+    let native_frames = match peer.frame_source() {
+        FrameSource::Java => None,
+        FrameSource::Native => NativeFrames::new().map(Rc::new),
+    };
+    let callbacks = peer.callbacks();
+    let mut map = peer_map();
+    let entry = PeerEntry {
+        peer: RefCell::new(Box::new(peer)),
+        poisoned: Cell::new(false),
+        posted: Arc::new(Mutex::new(Some(Vec::new()))),
+        native_frames,
+        frame_scheduler: Rc::default(),
+        callbacks,
+        display_metrics: Rc::default(),
+        event_snapshots: OnceCell::new(),
+        #[cfg(feature = "async")]
+        executor: Rc::default(),
+    };
+    map.insert(id, SendWrapper::new(Rc::new(entry)));
+    PeerHandle {
+        id,
+        _peer: PhantomData,
+    }
 }
 
+/// The base class of views in this crate's Java library.
+pub const RUST_VIEW_CLASS: &str = "org/linebender/android/rustview/RustView";
+
+/// The view classes registered so far, to detect conflicting registrations.
+struct Registrations {
+    /// Base classes whose natives have been registered, along with the
+    /// `EventSnapshots` class in their package.
+    bases: Vec<(GlobalRef, GlobalRef)>,
+    /// View classes, along with the `newViewPeer` implementation they
+    /// were registered with.
+    views: Vec<(GlobalRef, usize)>,
+}
+
+static REGISTRATIONS: Mutex<Registrations> = Mutex::new(Registrations {
+    bases: Vec::new(),
+    views: Vec::new(),
+});
+
+/// The `EventSnapshots` class registered with the base class of `view`,
+/// or `None` if it can't be found.
+fn event_snapshots_class_of(env: &mut JNIEnv, view: &View) -> Option<GlobalRef> {
+    let registrations = REGISTRATIONS.lock().unwrap();
+    for (base, snapshots) in &registrations.bases {
+        if catch(env, |env| env.is_instance_of(&view.0, base)).ok()? {
+            return Some(snapshots.clone());
+        }
+    }
+    None
+}
+
+/// Generated by `build.rs` from its table of natives, which also checks
+/// the declarations in `RustView.java`.
+fn base_native_methods() -> Vec<NativeMethod> {
+    include!(concat!(env!("OUT_DIR"), "/natives.rs"))
+}
+
+/// Register the natives of `class`, a subclass of the default base class
+/// [`RUST_VIEW_CLASS`].
 pub fn register_view_class<'local, 'other_local>(
     env: &mut JNIEnv<'local>,
     class: impl Desc<'local, JClass<'other_local>>,
     new_peer: for<'a> extern "system" fn(JNIEnv<'a>, View<'a>, Context<'a>) -> jlong,
 ) {
-    static REGISTER_BASE_NATIVES: Once = Once::new();
-    REGISTER_BASE_NATIVES.call_once(|| {
+    register_view_class_with_base(env, RUST_VIEW_CLASS, class, new_peer)
+}
+
+/// Register the natives of `class`, a subclass of `base_class`, which is a
+/// copy of `RustView` under another name, such as when the Java library is
+/// shaded into another package. `EventSnapshots` is expected to be in the
+/// same package as `base_class`.
+///
+/// The natives of each base class are registered once, with the class
+/// identified by its class loader as well as its name. Registering a class
+/// again with the same `new_peer` does nothing.
+pub fn register_view_class_with_base<'local, 'other_local>(
+    env: &mut JNIEnv<'local>,
+    base_class: &str,
+    class: impl Desc<'local, JClass<'other_local>>,
+    new_peer: for<'a> extern "system" fn(JNIEnv<'a>, View<'a>, Context<'a>) -> jlong,
+) {
+    try_register_view_class_with_base(env, base_class, class, new_peer).unwrap()
+}
+
+pub fn try_register_view_class_with_base<'local, 'other_local>(
+    env: &mut JNIEnv<'local>,
+    base_class: &str,
+    class: impl Desc<'local, JClass<'other_local>>,
+    new_peer: for<'a> extern "system" fn(JNIEnv<'a>, View<'a>, Context<'a>) -> jlong,
+) -> Result<(), Error> {
+    let snapshots_class = match base_class.rsplit_once('/') {
+        Some((package, _)) => format!("{package}/EventSnapshots"),
+        None => "EventSnapshots".into(),
+    };
+    // Classes are resolved before taking the lock, since initializing them
+    // may run code that registers other view classes.
+    let (base, class, snapshots, extends_base) = catch(env, |env| {
+        let base = env.find_class(base_class)?;
+        let base = env.new_global_ref(base)?;
+        let class = class.lookup(env)?;
+        let class = env.new_global_ref(class.as_ref())?;
+        let snapshots = env.find_class(snapshots_class)?;
+        let snapshots = env.new_global_ref(snapshots)?;
+        let extends_base = env.is_assignable_from(&class, &base)?;
+        Ok((base, class, snapshots, extends_base))
+    })?;
+    if !extends_base {
+        return Err(Error::InvalidViewClass(format!(
+            "view class does not extend {base_class}"
+        )));
+    }
+
+    let mut registrations = REGISTRATIONS.lock().unwrap();
+
+    for (registered, registered_new_peer) in &registrations.views {
+        if catch(env, |env| env.is_same_object(registered, &class))? {
+            if *registered_new_peer != new_peer as usize {
+                return Err(Error::ConflictingRegistration(
+                    "view class was already registered with a different newViewPeer".into(),
+                ));
+            }
+            return Ok(());
+        }
+    }
+
+    let mut base_registered = false;
+    for (registered, _) in &registrations.bases {
+        base_registered |= catch(env, |env| env.is_same_object(registered, &base))?;
+    }
+    if !base_registered {
+        catch(env, |env| {
+            env.register_native_methods(&base, &base_native_methods())
+        })?;
+        set_event_snapshots_class(snapshots.clone());
+        registrations.bases.push((base, snapshots));
+    }
+
+    catch(env, |env| {
         env.register_native_methods(
-            "org/linebender/android/rustview/RustView",
-            &[
-                NativeMethod {
-                    name: "onMeasureNative".into(),
-                    sig: "(JII)[I".into(),
-                    fn_ptr: on_measure as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onLayoutNative".into(),
-                    sig: "(JZIIII)V".into(),
-                    fn_ptr: on_layout as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onSizeChangedNative".into(),
-                    sig: "(JIIII)V".into(),
-                    fn_ptr: on_size_changed as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onKeyDownNative".into(),
-                    sig: "(JILandroid/view/KeyEvent;)Z".into(),
-                    fn_ptr: on_key_down as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onKeyUpNative".into(),
-                    sig: "(JILandroid/view/KeyEvent;)Z".into(),
-                    fn_ptr: on_key_up as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onTrackballEventNative".into(),
-                    sig: "(JLandroid/view/MotionEvent;)Z".into(),
-                    fn_ptr: on_trackball_event as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onTouchEventNative".into(),
-                    sig: "(JLandroid/view/MotionEvent;)Z".into(),
-                    fn_ptr: on_touch_event as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onGenericMotionEventNative".into(),
-                    sig: "(JLandroid/view/MotionEvent;)Z".into(),
-                    fn_ptr: on_generic_motion_event as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onHoverEventNative".into(),
-                    sig: "(JLandroid/view/MotionEvent;)Z".into(),
-                    fn_ptr: on_hover_event as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onFocusChangedNative".into(),
-                    sig: "(JZILandroid/graphics/Rect;)V".into(),
-                    fn_ptr: on_focus_changed as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onWindowFocusChangedNative".into(),
-                    sig: "(JZ)V".into(),
-                    fn_ptr: on_window_focus_changed as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onAttachedToWindowNative".into(),
-                    sig: "(J)V".into(),
-                    fn_ptr: on_attached_to_window as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onDetachedFromWindowNative".into(),
-                    sig: "(J)V".into(),
-                    fn_ptr: on_detached_from_window as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onWindowVisibilityChangedNative".into(),
-                    sig: "(JI)V".into(),
-                    fn_ptr: on_window_visibility_changed as *mut c_void,
-                },
-                NativeMethod {
-                    name: "surfaceCreatedNative".into(),
-                    sig: "(JLandroid/view/SurfaceHolder;)V".into(),
-                    fn_ptr: surface_created as *mut c_void,
-                },
-                NativeMethod {
-                    name: "surfaceChangedNative".into(),
-                    sig: "(JLandroid/view/SurfaceHolder;III)V".into(),
-                    fn_ptr: surface_changed as *mut c_void,
-                },
-                NativeMethod {
-                    name: "surfaceDestroyedNative".into(),
-                    sig: "(JLandroid/view/SurfaceHolder;)V".into(),
-                    fn_ptr: surface_destroyed as *mut c_void,
-                },
-                NativeMethod {
-                    name: "doFrameNative".into(),
-                    sig: "(JJ)V".into(),
-                    fn_ptr: do_frame as *mut c_void,
-                },
-                NativeMethod {
-                    name: "delayedCallbackNative".into(),
-                    sig: "(J)V".into(),
-                    fn_ptr: delayed_callback as *mut c_void,
-                },
-                NativeMethod {
-                    name: "hasAccessibilityNodeProviderNative".into(),
-                    sig: "(J)Z".into(),
-                    fn_ptr: has_accessibility_node_provider as *mut c_void,
-                },
-                NativeMethod {
-                    name: "createAccessibilityNodeInfoNative".into(),
-                    sig: "(JI)Landroid/view/accessibility/AccessibilityNodeInfo;".into(),
-                    fn_ptr: create_accessibility_node_info as *mut c_void,
-                },
-                NativeMethod {
-                    name: "accessibilityFindFocusNative".into(),
-                    sig: "(JI)Landroid/view/accessibility/AccessibilityNodeInfo;".into(),
-                    fn_ptr: accessibility_find_focus as *mut c_void,
-                },
-                NativeMethod {
-                    name: "performAccessibilityActionNative".into(),
-                    sig: "(JIILandroid/os/Bundle;)Z".into(),
-                    fn_ptr: perform_accessibility_action as *mut c_void,
-                },
-                NativeMethod {
-                    name: "onCreateInputConnectionNative".into(),
-                    sig: "(JLandroid/view/inputmethod/EditorInfo;)Z".into(),
-                    fn_ptr: on_create_input_connection as *mut c_void,
-                },
-                NativeMethod {
-                    name: "getTextBeforeCursorNative".into(),
-                    sig: "(JI)Ljava/lang/String;".into(),
-                    fn_ptr: get_text_before_cursor as *mut c_void,
-                },
-                NativeMethod {
-                    name: "getTextAfterCursorNative".into(),
-                    sig: "(JI)Ljava/lang/String;".into(),
-                    fn_ptr: get_text_after_cursor as *mut c_void,
-                },
-                NativeMethod {
-                    name: "getSelectedTextNative".into(),
-                    sig: "(J)Ljava/lang/String;".into(),
-                    fn_ptr: get_selected_text as *mut c_void,
-                },
-                NativeMethod {
-                    name: "getCursorCapsModeNative".into(),
-                    sig: "(JI)I".into(),
-                    fn_ptr: get_cursor_caps_mode as *mut c_void,
-                },
-                NativeMethod {
-                    name: "deleteSurroundingTextNative".into(),
-                    sig: "(JII)Z".into(),
-                    fn_ptr: delete_surrounding_text as *mut c_void,
-                },
-                NativeMethod {
-                    name: "deleteSurroundingTextInCodePointsNative".into(),
-                    sig: "(JII)Z".into(),
-                    fn_ptr: delete_surrounding_text_in_code_points as *mut c_void,
-                },
-                NativeMethod {
-                    name: "setComposingTextNative".into(),
-                    sig: "(JLjava/lang/String;I)Z".into(),
-                    fn_ptr: set_composing_text as *mut c_void,
-                },
-                NativeMethod {
-                    name: "setComposingRegionNative".into(),
-                    sig: "(JII)Z".into(),
-                    fn_ptr: set_composing_region as *mut c_void,
-                },
-                NativeMethod {
-                    name: "finishComposingTextNative".into(),
-                    sig: "(J)Z".into(),
-                    fn_ptr: finish_composing_text as *mut c_void,
-                },
-                NativeMethod {
-                    name: "commitTextNative".into(),
-                    sig: "(JLjava/lang/String;I)Z".into(),
-                    fn_ptr: commit_text as *mut c_void,
-                },
-                NativeMethod {
-                    name: "setSelectionNative".into(),
-                    sig: "(JII)Z".into(),
-                    fn_ptr: set_selection as *mut c_void,
-                },
-                NativeMethod {
-                    name: "performEditorActionNative".into(),
-                    sig: "(JI)Z".into(),
-                    fn_ptr: perform_editor_action as *mut c_void,
-                },
-                NativeMethod {
-                    name: "performContextMenuActionNative".into(),
-                    sig: "(JI)Z".into(),
-                    fn_ptr: perform_context_menu_action as *mut c_void,
-                },
-                NativeMethod {
-                    name: "beginBatchEditNative".into(),
-                    sig: "(J)Z".into(),
-                    fn_ptr: begin_batch_edit as *mut c_void,
-                },
-                NativeMethod {
-                    name: "endBatchEditNative".into(),
-                    sig: "(J)Z".into(),
-                    fn_ptr: end_batch_edit as *mut c_void,
-                },
-                NativeMethod {
-                    name: "inputConnectionSendKeyEventNative".into(),
-                    sig: "(JLandroid/view/KeyEvent;)Z".into(),
-                    fn_ptr: input_connection_send_key_event as *mut c_void,
-                },
-                NativeMethod {
-                    name: "inputConnectionClearMetaKeyStatesNative".into(),
-                    sig: "(JI)Z".into(),
-                    fn_ptr: input_connection_clear_meta_key_states as *mut c_void,
-                },
-                NativeMethod {
-                    name: "inputConnectionReportFullscreenModeNative".into(),
-                    sig: "(JZ)Z".into(),
-                    fn_ptr: input_connection_report_fullscreen_mode as *mut c_void,
-                },
-                NativeMethod {
-                    name: "requestCursorUpdatesNative".into(),
-                    sig: "(JI)Z".into(),
-                    fn_ptr: request_cursor_updates as *mut c_void,
-                },
-                NativeMethod {
-                    name: "closeInputConnectionNative".into(),
-                    sig: "(J)V".into(),
-                    fn_ptr: close_input_connection as *mut c_void,
-                },
-            ],
+            &class,
+            &[NativeMethod {
+                name: "newViewPeer".into(),
+                sig: "(Landroid/content/Context;)J".into(),
+                fn_ptr: new_peer as *mut c_void,
+            }],
         )
-        .unwrap();
-    });
-    env.register_native_methods(
-        class,
-        &[NativeMethod {
-            name: "newViewPeer".into(),
-            sig: "(Landroid/content/Context;)J".into(),
-            fn_ptr: new_peer as *mut c_void,
-        }],
-    )
-    .unwrap();
+    })?;
+    registrations.views.push((class, new_peer as usize));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod java {
+        include!("../build/java.rs");
+    }
+
+    /// The check of `build.rs`, which doesn't run again while its output is
+    /// cached.
+    #[test]
+    fn java_natives_match_registered() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(java::JAVA_FILE);
+        let mut declared = java::parse_java_natives(&std::fs::read_to_string(path).unwrap());
+        declared.sort();
+        let mut registered: Vec<_> = base_native_methods()
+            .into_iter()
+            .map(|method| (String::from(method.name), String::from(method.sig)))
+            .collect();
+        registered.sort();
+        assert_eq!(declared, registered);
+    }
 }
@@ -1,6 +1,8 @@
 //! A simple representation of Android `ViewConfiguration`.
 
-use jni::{JNIEnv, errors::Error, objects::JObject};
+use jni::{JNIEnv, objects::JObject};
+
+use crate::error::*;
 
 /// A representation of Android `ViewConfiguration`.
 ///
@@ -37,6 +39,8 @@ pub struct ViewConfiguration {
     pub scaled_paging_touch_slop: i32,
     /// Perpendicular size of the scroll bar in pixels.
     pub scaled_scroll_bar_size: i32,
+    /// Pixels a touch can travel before it is interpreted as a drag.
+    pub scaled_touch_slop: i32,
     /// Scaling factor for the vertical scroll axis value during
     /// `MotionAction::Scroll` for the number of pixels to scroll.
     pub scaled_vertical_scroll_factor: f32,
@@ -58,7 +62,19 @@ impl ViewConfiguration {
         Self::try_new(view, env).unwrap_or_default()
     }
 
-    fn try_new<'local>(view: &JObject<'local>, env: &mut JNIEnv<'local>) -> Result<Self, Error> {
+    /// Query the `ViewConfiguration` for a view, returning an error
+    /// instead of falling back to defaults if any value can't be read.
+    pub fn try_new<'local>(
+        view: &JObject<'local>,
+        env: &mut JNIEnv<'local>,
+    ) -> Result<Self, Error> {
+        catch(env, |env| Self::query(view, env))
+    }
+
+    fn query<'local>(
+        view: &JObject<'local>,
+        env: &mut JNIEnv<'local>,
+    ) -> jni::errors::Result<Self> {
         const CL: &str = "android/view/ViewConfiguration";
 
         let context = env
@@ -105,6 +121,9 @@ impl ViewConfiguration {
             scaled_scroll_bar_size: env
                 .call_method(&vc, "getScaledScrollBarSize", "()I", &[])?
                 .i()?,
+            scaled_touch_slop: env
+                .call_method(&vc, "getScaledTouchSlop", "()I", &[])?
+                .i()?,
             scaled_vertical_scroll_factor: env
                 .call_method(&vc, "getScaledVerticalScrollFactor", "()F", &[])?
                 .f()?,
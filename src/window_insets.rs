@@ -0,0 +1,296 @@
+//! Window insets, delivered by `onApplyWindowInsets`, and animations of the
+//! IME insets, delivered by `WindowInsetsAnimation`.
+//!
+//! Before API 30, the insets of each type are approximated from the system
+//! window and stable insets. Rounded corners are only reported from API 31,
+//! and IME animations from API 30.
+
+use dpi::PhysicalPosition;
+use jni::{
+    JNIEnv,
+    objects::{JIntArray, JObject},
+    sys::{jfloat, jlong},
+};
+use std::time::Duration;
+
+use crate::{error::*, view::*};
+
+/// Must match `WindowInsetsSnapshots.TYPE_COUNT`.
+const TYPE_COUNT: usize = 8;
+/// Must match `WindowInsetsSnapshots.TYPE_STRIDE`.
+const TYPE_STRIDE: usize = 9;
+/// Must match `WindowInsetsSnapshots.CORNER_STRIDE`.
+const CORNER_STRIDE: usize = 4;
+
+/// Distances in pixels from each edge of the window, as in
+/// `android.graphics.Insets`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Insets {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Insets {
+    pub const ZERO: Self = Self::new(0, 0, 0, 0);
+
+    pub const fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// The larger of each edge of `self` and `other`.
+    pub fn max(self, other: Self) -> Self {
+        Self::new(
+            self.left.max(other.left),
+            self.top.max(other.top),
+            self.right.max(other.right),
+            self.bottom.max(other.bottom),
+        )
+    }
+
+    fn from_packed(packed: &[i32]) -> Self {
+        Self::new(packed[0], packed[1], packed[2], packed[3])
+    }
+}
+
+/// The insets of one `WindowInsets.Type`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TypeInsets {
+    pub insets: Insets,
+    /// The insets the type would have if it were visible. Always zero for
+    /// the IME.
+    pub insets_ignoring_visibility: Insets,
+    pub visible: bool,
+}
+
+impl TypeInsets {
+    fn from_packed(packed: &[i32]) -> Self {
+        Self {
+            insets: Insets::from_packed(&packed[1..5]),
+            insets_ignoring_visibility: Insets::from_packed(&packed[5..9]),
+            visible: packed[0] != 0,
+        }
+    }
+}
+
+/// A rounded corner of the display, as in `android.view.RoundedCorner`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RoundedCorner {
+    pub radius: i32,
+    /// The center of the corner's circle, in window coordinates.
+    pub center: PhysicalPosition<i32>,
+}
+
+/// A rectangle in window coordinates, such as a bounding rectangle of the
+/// display cutout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BoundingRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+/// An owned copy of an Android `WindowInsets`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WindowInsets {
+    pub status_bars: TypeInsets,
+    pub navigation_bars: TypeInsets,
+    pub caption_bar: TypeInsets,
+    pub ime: TypeInsets,
+    pub system_gestures: TypeInsets,
+    pub mandatory_system_gestures: TypeInsets,
+    pub tappable_element: TypeInsets,
+    /// The safe insets of the display cutout.
+    pub display_cutout: TypeInsets,
+    /// The bounding rectangles of the display cutout.
+    pub cutout_rects: Vec<BoundingRect>,
+    /// The top-left, top-right, bottom-right and bottom-left corners, in
+    /// that order. `None` for square corners and before API 31.
+    pub rounded_corners: [Option<RoundedCorner>; 4],
+}
+
+impl WindowInsets {
+    /// Build a snapshot from the array produced by the Java
+    /// `WindowInsetsSnapshots` helper.
+    pub(crate) fn from_packed(packed: &[i32]) -> Self {
+        let types: Vec<_> = packed
+            .chunks_exact(TYPE_STRIDE)
+            .take(TYPE_COUNT)
+            .map(TypeInsets::from_packed)
+            .collect();
+        let corners = &packed[TYPE_COUNT * TYPE_STRIDE..];
+        let rounded_corners = std::array::from_fn(|i| {
+            let corner = &corners[i * CORNER_STRIDE..(i + 1) * CORNER_STRIDE];
+            (corner[0] != 0).then(|| RoundedCorner {
+                radius: corner[1],
+                center: PhysicalPosition::new(corner[2], corner[3]),
+            })
+        });
+        let rects = &corners[4 * CORNER_STRIDE..];
+        let cutout_rects = rects[1..]
+            .chunks_exact(4)
+            .take(rects[0] as usize)
+            .map(|rect| BoundingRect {
+                left: rect[0],
+                top: rect[1],
+                right: rect[2],
+                bottom: rect[3],
+            })
+            .collect();
+        Self {
+            status_bars: types[0],
+            navigation_bars: types[1],
+            caption_bar: types[2],
+            ime: types[3],
+            system_gestures: types[4],
+            mandatory_system_gestures: types[5],
+            tappable_element: types[6],
+            display_cutout: types[7],
+            cutout_rects,
+            rounded_corners,
+        }
+    }
+
+    pub(crate) fn try_from_java(env: &mut JNIEnv, packed: &JIntArray) -> Result<Self, Error> {
+        catch(env, |env| {
+            let mut buf = vec![0; env.get_array_length(packed)? as usize];
+            env.get_int_array_region(packed, 0, &mut buf)?;
+            Ok(Self::from_packed(&buf))
+        })
+    }
+
+    /// The insets of the status bars, navigation bars and caption bar
+    /// together, as for `WindowInsets.Type.systemBars()`.
+    pub fn system_bars(&self) -> Insets {
+        self.status_bars
+            .insets
+            .max(self.navigation_bars.insets)
+            .max(self.caption_bar.insets)
+    }
+
+    /// The area that content should avoid to stay clear of the system bars,
+    /// the IME and the display cutout.
+    pub fn safe_area(&self) -> Insets {
+        self.system_bars()
+            .max(self.ime.insets)
+            .max(self.display_cutout.insets)
+    }
+}
+
+/// A step in an animation of the IME insets, such as the keyboard sliding
+/// in or out, from `WindowInsetsAnimation.Callback`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImeAnimation<'a> {
+    /// The animation is about to start. The next layout has the insets at
+    /// its end, so record anything needed to animate from the current state.
+    Prepare,
+    /// The IME insets will move between `lower_bound` and `upper_bound`.
+    /// `duration` is `None` if the animation has no fixed duration, such as
+    /// when the user controls it.
+    Start {
+        lower_bound: Insets,
+        upper_bound: Insets,
+        duration: Option<Duration>,
+    },
+    /// A frame of the animation. Drawing it now, with the IME insets in
+    /// `insets`, keeps the content in step with the keyboard.
+    Progress {
+        insets: &'a WindowInsets,
+        fraction: f32,
+        interpolated_fraction: f32,
+        alpha: f32,
+    },
+    End,
+}
+
+pub(crate) extern "system" fn on_apply_window_insets<'local>(
+    env: JNIEnv<'local>,
+    view: View<'local>,
+    peer: jlong,
+    insets: JIntArray<'local>,
+) -> JIntArray<'local> {
+    with_peer(env, view, peer, |ctx, peer| {
+        let insets = WindowInsets::try_from_java(&mut ctx.env, &insets).unwrap();
+        let Some(consumed) = peer.on_apply_window_insets(ctx, &insets) else {
+            return JObject::null().into();
+        };
+        let result = ctx.env.new_int_array(4).unwrap();
+        ctx.env
+            .set_int_array_region(
+                &result,
+                0,
+                &[consumed.left, consumed.top, consumed.right, consumed.bottom],
+            )
+            .unwrap();
+        result
+    })
+}
+
+pub(crate) extern "system" fn ime_animation_prepare<'local>(
+    env: JNIEnv<'local>,
+    view: View<'local>,
+    peer: jlong,
+) {
+    with_peer(env, view, peer, |ctx, peer| {
+        peer.on_ime_animation(ctx, ImeAnimation::Prepare);
+    })
+}
+
+pub(crate) extern "system" fn ime_animation_start<'local>(
+    env: JNIEnv<'local>,
+    view: View<'local>,
+    peer: jlong,
+    bounds: JIntArray<'local>,
+    duration_millis: jlong,
+) {
+    with_peer(env, view, peer, |ctx, peer| {
+        let mut buf = [0; 8];
+        ctx.env.get_int_array_region(&bounds, 0, &mut buf).unwrap();
+        let animation = ImeAnimation::Start {
+            lower_bound: Insets::from_packed(&buf[..4]),
+            upper_bound: Insets::from_packed(&buf[4..]),
+            duration: u64::try_from(duration_millis)
+                .ok()
+                .map(Duration::from_millis),
+        };
+        peer.on_ime_animation(ctx, animation);
+    })
+}
+
+pub(crate) extern "system" fn ime_animation_progress<'local>(
+    env: JNIEnv<'local>,
+    view: View<'local>,
+    peer: jlong,
+    insets: JIntArray<'local>,
+    fraction: jfloat,
+    interpolated_fraction: jfloat,
+    alpha: jfloat,
+) {
+    with_peer(env, view, peer, |ctx, peer| {
+        let insets = WindowInsets::try_from_java(&mut ctx.env, &insets).unwrap();
+        let animation = ImeAnimation::Progress {
+            insets: &insets,
+            fraction,
+            interpolated_fraction,
+            alpha,
+        };
+        peer.on_ime_animation(ctx, animation);
+    })
+}
+
+pub(crate) extern "system" fn ime_animation_end<'local>(
+    env: JNIEnv<'local>,
+    view: View<'local>,
+    peer: jlong,
+) {
+    with_peer(env, view, peer, |ctx, peer| {
+        peer.on_ime_animation(ctx, ImeAnimation::End);
+    })
+}